@@ -0,0 +1,21 @@
+use headway::{ProgressBar, Style};
+use std::{thread::sleep, time::Duration};
+
+pub fn main() {
+    headway::set_default_style(Style::cargo());
+    let crates = [
+        "serde v1.0.197",
+        "rand v0.8.5",
+        "tokio v1.36.0",
+        "headway v0.1.2",
+    ];
+    let p = ProgressBar::new().with_prefix("Building");
+    for (mut child, name) in p.split_each(crates.iter()) {
+        let mut line = ProgressBar::new()
+            .with_prefix("Compiling")
+            .with_message(*name);
+        sleep(Duration::from_millis(500));
+        line.finish();
+        child.finish();
+    }
+}
@@ -0,0 +1,28 @@
+use std::{thread::sleep, time::Duration};
+
+use headway::ProgressBar;
+use prodash::{Count, NestedProgress, Progress};
+
+/// A function from some library which reports progress using prodash
+fn clone_repository(progress: &mut impl NestedProgress) {
+    let mut objects = progress.add_child("objects");
+    objects.init(Some(200), None);
+    for _ in 0..200 {
+        sleep(Duration::from_millis(10));
+        objects.inc();
+    }
+
+    let mut checkout = progress.add_child("checkout");
+    checkout.init(Some(50), None);
+    for _ in 0..50 {
+        sleep(Duration::from_millis(20));
+        checkout.inc();
+    }
+    checkout.done("checked out 50 files".to_string());
+}
+
+pub fn main() {
+    let mut p = ProgressBar::new().with_message("Cloning");
+    clone_repository(&mut p);
+    p.finish();
+}
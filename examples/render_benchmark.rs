@@ -0,0 +1,64 @@
+//! Measures how long it takes to render a bar, for each count format.
+//!
+//! Run with `cargo run --release --example render_benchmark`.
+
+use headway::core::{Aggregation, BarNode, Renderer};
+use headway::{CountFormat, DurationFormat, Style};
+use std::time::{Duration, Instant};
+
+const RENDERS: usize = 100_000;
+const ROUNDS: usize = 20;
+
+pub fn main() {
+    let root = BarNode::new();
+    let bars = (0..8)
+        .map(|i| {
+            let bar = root.add_child(Aggregation::Sized(10_000_000));
+            bar.set_position(i * 1_234_567);
+            bar
+        })
+        .collect::<Vec<_>>();
+
+    let styles = [
+        ("plain", Style::new().with_eta(true)),
+        (
+            "separated",
+            Style::new()
+                .with_eta(true)
+                .with_count_format(CountFormat::Separated(',')),
+        ),
+        (
+            "si",
+            Style::new()
+                .with_eta(true)
+                .with_count_format(CountFormat::Si),
+        ),
+        (
+            "clock",
+            Style::new()
+                .with_eta(true)
+                .with_duration_format(DurationFormat::Clock),
+        ),
+    ];
+    for (name, style) in styles {
+        let renderer = Renderer::new().with_style(style);
+        let mut line = String::new();
+        // Use the fastest round, since the others are most likely disturbed by other processes
+        let mut best = Duration::MAX;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            for i in 0..RENDERS {
+                let bar = &bars[i % bars.len()];
+                bar.inc();
+                line.clear();
+                renderer.render_into(bar, &mut line);
+            }
+            best = best.min(start.elapsed());
+        }
+        println!(
+            "{:<10} {:>6.0} ns per bar",
+            name,
+            best.as_nanos() as f64 / RENDERS as f64
+        );
+    }
+}
@@ -0,0 +1,13 @@
+use std::{thread::sleep, time::Duration};
+
+pub fn main() {
+    // Simulate slowly discovering files, for example by walking a directory tree
+    let discovered = (0..50).map(|i| {
+        sleep(Duration::from_millis(30));
+        format!("file{}.txt", i)
+    });
+
+    for _file in headway::scan_then_process(discovered) {
+        sleep(Duration::from_millis(40));
+    }
+}
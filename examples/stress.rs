@@ -0,0 +1,10 @@
+//! Exercises pathological cases, like thousands of bars and concurrent printing, and prints a report.
+//!
+//! Useful for checking that bars are drawn correctly in an unusual terminal.
+//! Run with `cargo run --release --example stress`.
+
+pub fn main() {
+    if !headway::selftest() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,43 @@
+//! Measures how long it takes to update a bar, from one thread and from several threads at once.
+//!
+//! Bar updates lock the state of the bar, so this mostly measures the locks.
+//! Compare `cargo run --release --example update_benchmark` with `cargo run --release --features parking_lot --example update_benchmark`.
+
+use headway::ProgressBar;
+use std::time::{Duration, Instant};
+
+const UPDATES: usize = 1_000_000;
+const ROUNDS: usize = 5;
+
+/// Updates the bar from the given number of threads, and returns the fastest time per update.
+fn measure(bar: &ProgressBar, threads: usize) -> Duration {
+    // Use the fastest round, since the others are most likely disturbed by other processes
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    for _ in 0..UPDATES / threads {
+                        bar.inc();
+                    }
+                });
+            }
+        });
+        best = best.min(start.elapsed());
+    }
+    best / UPDATES as u32
+}
+
+pub fn main() {
+    // Rendering competes with the updates for the locks, like it does in a real program
+    let _capture = headway::test_capture();
+    let bar = ProgressBar::new().with_length(UPDATES * ROUNDS * 8);
+    for threads in [1, 2, 4, 8] {
+        println!(
+            "{} threads: {:>4} ns per update",
+            threads,
+            measure(&bar, threads).as_nanos()
+        );
+    }
+}
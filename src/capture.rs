@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::MANAGER;
+
+/// Redirects all progress bar output into an in-memory buffer.
+///
+/// This is primarily useful in unit tests of code which reports progress, so that `cargo test` output isn't filled with escape codes.
+/// While the returned guard is alive, nothing is written to stdout. Instead every frame that would have been drawn is recorded
+/// and can be inspected using [`CaptureGuard::frames`]. Output is restored when the guard is dropped.
+///
+/// Captured frames are rendered as if the output was a terminal, but without colors.
+///
+/// ```
+/// use headway::ProgressBarIterable;
+///
+/// let capture = headway::test_capture();
+/// for _ in (0..10).progress() {}
+/// assert!(capture.last_frame().unwrap().contains("10/10"));
+/// ```
+///
+/// Note that there is only a single global output, so all bars in the process will be captured, including ones created
+/// by tests that happen to run in parallel. If several captures are active at the same time, the most recent one receives all frames.
+pub fn test_capture() -> CaptureGuard {
+    let frames = Arc::new(Mutex::new(vec![]));
+    let mut manager = MANAGER.lock();
+    let previous = manager.capture.replace(frames.clone());
+    let previous_interactive = std::mem::replace(&mut manager.interactive_output, true);
+    CaptureGuard {
+        frames,
+        previous,
+        previous_interactive,
+    }
+}
+
+/// Guard returned by [`test_capture`].
+///
+/// Output is restored when this guard is dropped.
+pub struct CaptureGuard {
+    frames: Arc<Mutex<Vec<String>>>,
+    previous: Option<Arc<Mutex<Vec<String>>>>,
+    previous_interactive: bool,
+}
+
+impl CaptureGuard {
+    /// All frames recorded so far.
+    ///
+    /// Each frame contains one line per visible bar, preceded by the title if one has been set (see [`crate::set_title`]).
+    pub fn frames(&self) -> Vec<String> {
+        self.frames.lock().clone()
+    }
+
+    /// The most recently recorded frame, if any.
+    pub fn last_frame(&self) -> Option<String> {
+        self.frames.lock().last().cloned()
+    }
+
+    /// Forgets all frames recorded so far.
+    pub fn clear(&self) {
+        self.frames.lock().clear();
+    }
+}
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        let mut manager = MANAGER.lock();
+        // Only restore the output if no other capture has been started after this one
+        if manager
+            .capture
+            .as_ref()
+            .is_some_and(|c| Arc::ptr_eq(c, &self.frames))
+        {
+            manager.capture = self.previous.take();
+            manager.interactive_output = self.previous_interactive;
+        }
+    }
+}
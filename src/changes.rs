@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{ProgressBarManager, ProgressBarState, MANAGER};
+
+/// A callback registered using [`on_change`].
+pub(crate) type ChangeHook = (ChangeStream, Box<dyn FnMut(&[BarChange]) + Send>);
+
+/// True if a callback has been registered for [`ChangeStream::Raw`], in which case every change is recorded
+static RECORD_RAW: AtomicBool = AtomicBool::new(false);
+
+/// Maximum number of changes recorded for a bar between two batches, see [`ProgressBarState::log_change`]
+const MAX_CHANGE_LOG: usize = 10_000;
+
+/// Which changes are passed to a callback registered using [`on_change`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChangeStream {
+    /// At most one change per bar each time the bars are drawn, from the values the previous time to the current values.
+    ///
+    /// Counters which are incremented thousands of times per second only produce a few changes per second this way.
+    #[default]
+    Coalesced,
+    /// Every single update of each bar, like every call to [`ProgressBar::inc`](crate::ProgressBar::inc).
+    ///
+    /// The changes are still delivered in batches each time the bars are drawn.
+    /// If a bar is updated more than 10,000 times between two batches, only the most recent updates are included,
+    /// together with a single change covering the ones which were left out.
+    Raw,
+}
+
+/// A change of the position or length of a bar. See [`on_change`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BarChange {
+    id: u64,
+    old_position: usize,
+    position: usize,
+    old_length: Option<usize>,
+    length: Option<usize>,
+}
+
+impl BarChange {
+    /// Unique identifier of the bar, the same as [`RenderedBar::id`](crate::RenderedBar::id).
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The position of the bar before the change.
+    pub fn old_position(&self) -> usize {
+        self.old_position
+    }
+
+    /// The position of the bar after the change.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The length of the bar before the change.
+    pub fn old_length(&self) -> Option<usize> {
+        self.old_length
+    }
+
+    /// The length of the bar after the change.
+    pub fn length(&self) -> Option<usize> {
+        self.length
+    }
+}
+
+/// Registers a callback which receives the changes to the position and length of all bars, as a stream of events.
+///
+/// Changes are collected each time the bars are drawn, and passed to the callback in batches, ordered by bar.
+/// Children of split bars are included, while the positions of the split bars themselves are computed from their children and are not.
+/// See [`ChangeStream`] for whether every update is included, or only the net change of each bar since the last batch.
+///
+/// The callback is invoked from a background thread, without holding any locks, so it may interact with progress bars.
+///
+/// ```
+/// use headway::{ChangeStream, ProgressBar};
+///
+/// headway::on_change(ChangeStream::Coalesced, |changes| {
+///     for change in changes {
+///         eprintln!(
+///             "{{\"bar\":{},\"from\":{},\"to\":{}}}",
+///             change.id(),
+///             change.old_position(),
+///             change.position()
+///         );
+///     }
+/// });
+///
+/// let p = ProgressBar::new().with_message("Hashing");
+/// for _ in p.wrap(0..100_000) {}
+/// ```
+pub fn on_change(stream: ChangeStream, callback: impl FnMut(&[BarChange]) + Send + 'static) {
+    if stream == ChangeStream::Raw {
+        RECORD_RAW.store(true, Ordering::Relaxed);
+    }
+    let mut manager = MANAGER.lock();
+    manager.change_hooks.push((stream, Box::new(callback)));
+    manager.has_change_hooks = true;
+    manager.ensure_thread_started();
+}
+
+impl ProgressBarState {
+    /// Records the current position and length for [`ChangeStream::Raw`], after either of them has changed.
+    ///
+    /// The log is only emptied when the changes are collected, which never happens for bars that are not displayed,
+    /// like hidden bars. So only the most recent changes are kept, to not use more and more memory.
+    pub(crate) fn log_change(&mut self) {
+        if RECORD_RAW.load(Ordering::Relaxed) {
+            let entry = (self.position, self.length);
+            if self.change_log.last() != Some(&entry) {
+                if self.change_log.len() >= MAX_CHANGE_LOG {
+                    // Drop many entries at once, so that this only happens rarely
+                    self.change_log.drain(..MAX_CHANGE_LOG / 2);
+                }
+                self.change_log.push(entry);
+            }
+        }
+    }
+
+    /// Adds the changes since the last batch to the streams, see [`ProgressBarManager::collect_changes`].
+    fn collect_changes(&mut self, raw: &mut Vec<BarChange>, coalesced: &mut Vec<BarChange>) {
+        let change = |(old_position, old_length), (position, length)| BarChange {
+            id: self.id,
+            old_position,
+            position,
+            old_length,
+            length,
+        };
+        let current = (self.position, self.length);
+        let mut previous = self.reported;
+        for &entry in self.change_log.iter().chain([&current]) {
+            if entry != previous {
+                raw.push(change(previous, entry));
+                previous = entry;
+            }
+        }
+        if current != self.reported {
+            coalesced.push(change(self.reported, current));
+        }
+        self.change_log.clear();
+        self.reported = current;
+
+        if let Some(nested) = &self.nested {
+            for bar in &nested.bars {
+                bar.lock().collect_changes(raw, coalesced);
+            }
+        }
+    }
+}
+
+/// Changes which have been collected, but not yet passed to the [`on_change`] callbacks, for both streams.
+pub(crate) type ChangeBatch = (Vec<BarChange>, Vec<BarChange>);
+
+impl ProgressBarManager {
+    /// Collects the changes to all bars since the last time, if there are any callbacks to pass them to.
+    ///
+    /// Called before finished bars are removed, so that their final changes are included.
+    pub(crate) fn collect_changes(&mut self) {
+        if !self.has_change_hooks {
+            return;
+        }
+        let (mut raw, mut coalesced) = (vec![], vec![]);
+        for bar in &self.bars {
+            bar.lock().collect_changes(&mut raw, &mut coalesced);
+        }
+        // Raw changes can cancel each other out, so there may be raw changes without any coalesced ones
+        if !raw.is_empty() {
+            self.pending_changes.push((raw, coalesced));
+        }
+    }
+}
+
+/// Passes the collected changes to the callbacks.
+pub(crate) fn invoke_hooks(hooks: &mut [ChangeHook], batches: &[ChangeBatch]) {
+    for (raw, coalesced) in batches {
+        for (stream, hook) in hooks.iter_mut() {
+            let changes = match stream {
+                ChangeStream::Raw => raw,
+                ChangeStream::Coalesced => coalesced,
+            };
+            if !changes.is_empty() {
+                hook(changes);
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use crate::sync::Mutex;
+use crate::MANAGER;
+
+/// A source of the current time, see [`set_clock`].
+pub trait Clock: Send + Sync {
+    /// The current time.
+    ///
+    /// Must never go backwards.
+    fn now(&self) -> Instant;
+}
+
+/// The clock set using [`set_clock`], if any
+static CLOCK: LazyLock<Mutex<Option<Arc<dyn Clock>>>> = LazyLock::new(Default::default);
+
+/// True if a clock has been set, so that the lock can be skipped otherwise
+static HAS_CLOCK: AtomicBool = AtomicBool::new(false);
+
+/// Replaces the clock used to measure time for the bars, or goes back to the system clock if `None`.
+///
+/// The clock is used for everything which is displayed, like animations, elapsed times, rates and estimated times remaining.
+/// This makes it possible to render the same output every time in tests, by using a [`ManualClock`] which only moves when it is told to.
+/// How often the bars are redrawn still depends on the system clock.
+///
+/// Animations are timed from when the clock was set, so it should be set before the bars which are tested are created.
+///
+/// ```
+/// use headway::{ManualClock, ProgressBar, Style};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// headway::set_clock(Some(clock.clone()));
+/// headway::set_default_style(Style::new().with_elapsed(true));
+///
+/// let p = ProgressBar::hidden().with_length(10);
+/// p.set_position(5);
+/// clock.advance(Duration::from_secs(90));
+/// assert_eq!(p.render_line(), "▕██████████          ▏ 5/10 1m30s elapsed");
+/// ```
+pub fn set_clock<C: Clock + 'static>(clock: Option<C>) {
+    let clock = clock.map(|c| Arc::new(c) as Arc<dyn Clock>);
+    let now = clock.as_ref().map_or_else(Instant::now, |c| c.now());
+    HAS_CLOCK.store(clock.is_some(), Ordering::Relaxed);
+    *CLOCK.lock() = clock;
+    // Start the animations from the new time, so that they look the same every time
+    MANAGER.lock().reference_time = now;
+}
+
+/// The current time, according to the clock set using [`set_clock`].
+pub(crate) fn now() -> Instant {
+    if !HAS_CLOCK.load(Ordering::Relaxed) {
+        return Instant::now();
+    }
+    match &*CLOCK.lock() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    }
+}
+
+/// Time since the given time, according to the clock set using [`set_clock`]. Like [`Instant::elapsed`].
+pub(crate) fn since(time: Instant) -> Duration {
+    now().saturating_duration_since(time)
+}
+
+/// A [`Clock`] which only moves forward when it is told to, for reproducible output in tests.
+///
+/// Handles are cheap to clone, and all clones share the same time.
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// Creates a clock which starts at the current time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock()
+    }
+}
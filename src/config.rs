@@ -0,0 +1,239 @@
+//! Settings of the global manager, which are chosen once before the first bar is created. See [`init_with`].
+
+use is_terminal::IsTerminal;
+use std::io::Write;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::sync::Mutex;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Where the bars are drawn. See [`Config::with_target`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Target {
+    /// Standard output. Text printed to stdout using `println` while bars are visible is printed above the bars.
+    #[default]
+    Stdout,
+    /// Standard error. This keeps stdout free for the actual output of the program, like when it is piped to another program.
+    ///
+    /// Text printed to stdout while bars are visible may be drawn over the bars, until they are redrawn.
+    Stderr,
+}
+
+impl Target {
+    pub(crate) fn is_terminal(self) -> bool {
+        match self {
+            Target::Stdout => std::io::stdout().is_terminal(),
+            Target::Stderr => std::io::stderr().is_terminal(),
+        }
+    }
+
+    pub(crate) fn terminal_size(self) -> Option<(terminal_size::Width, terminal_size::Height)> {
+        match self {
+            Target::Stdout => terminal_size::terminal_size_of(std::io::stdout()),
+            Target::Stderr => terminal_size::terminal_size_of(std::io::stderr()),
+        }
+    }
+}
+
+/// A destination for the bars other than stdout or stderr, like a pty, a socket or an in-memory buffer. See [`Config::with_draw_target`].
+///
+/// ```
+/// use headway::DrawTarget;
+/// use std::io::Write;
+/// use std::sync::{Arc, Mutex};
+///
+/// /// Keeps everything which is drawn, so that it can be embedded in the output of another tool
+/// struct Recorder(Arc<Mutex<Vec<u8>>>);
+///
+/// impl Write for Recorder {
+///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+///         self.0.lock().unwrap().write(buf)
+///     }
+///
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// impl DrawTarget for Recorder {
+///     fn is_terminal(&self) -> bool {
+///         true
+///     }
+///
+///     fn size(&self) -> Option<(usize, usize)> {
+///         Some((80, 24))
+///     }
+/// }
+/// ```
+pub trait DrawTarget: Write + Send {
+    /// True if the target displays escape codes like a terminal does, so that bars can be redrawn in place.
+    ///
+    /// Otherwise bars are only written once they are finished, like when stdout is not a terminal. Defaults to false,
+    /// since escape codes would end up as garbage in writers like files and sockets.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+
+    /// The size of the target as `(columns, rows)`, if it is known.
+    fn size(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+/// Files can be terminals too, like a pty which was opened by the program.
+impl DrawTarget for std::fs::File {
+    fn is_terminal(&self) -> bool {
+        IsTerminal::is_terminal(self)
+    }
+
+    fn size(&self) -> Option<(usize, usize)> {
+        let (terminal_size::Width(cols), terminal_size::Height(rows)) =
+            terminal_size::terminal_size_of(self)?;
+        Some((cols as usize, rows as usize))
+    }
+}
+
+/// A [`DrawTarget`] which is shared between the config and the thread writing the output.
+#[derive(Clone)]
+pub(crate) struct SharedDrawTarget {
+    pub(crate) target: Arc<Mutex<Box<dyn DrawTarget>>>,
+    /// The target is locked while output is written to it, which may take a long time for slow writers,
+    /// so rendering reads this instead of waiting for the lock.
+    info: Arc<Mutex<TargetInfo>>,
+}
+
+/// The last known state of a [`SharedDrawTarget`].
+#[derive(Clone, Copy)]
+struct TargetInfo {
+    /// See [`DrawTarget::is_terminal`]
+    terminal: bool,
+    /// See [`DrawTarget::size`]
+    size: Option<(usize, usize)>,
+}
+
+impl TargetInfo {
+    fn of(target: &dyn DrawTarget) -> Self {
+        Self {
+            terminal: target.is_terminal(),
+            size: target.size(),
+        }
+    }
+}
+
+impl SharedDrawTarget {
+    fn new(target: Box<dyn DrawTarget>) -> Self {
+        let info = TargetInfo::of(&*target);
+        Self {
+            target: Arc::new(Mutex::new(target)),
+            info: Arc::new(Mutex::new(info)),
+        }
+    }
+
+    /// Updates the cached information about the target, after it has been written to.
+    pub(crate) fn update_info(&self, target: &dyn DrawTarget) {
+        *self.info.lock() = TargetInfo::of(target);
+    }
+}
+
+impl std::fmt::Debug for SharedDrawTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DrawTarget")
+    }
+}
+
+/// Settings for drawing the bars, which cannot be changed once the first bar has been created. See [`init_with`].
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub(crate) target: Target,
+    pub(crate) draw_target: Option<SharedDrawTarget>,
+    pub(crate) refresh_rate: Option<f64>,
+}
+
+impl Config {
+    /// The default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets where the bars are drawn. By default they are drawn to stdout.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Draws the bars to the given writer instead of stdout or stderr.
+    ///
+    /// This overrides [`Self::with_target`]. See [`DrawTarget`] for how to draw to other kinds of writers.
+    ///
+    /// ```
+    /// use headway::Config;
+    ///
+    /// let log = std::fs::File::create(std::env::temp_dir().join("headway-progress.txt")).unwrap();
+    /// headway::init_with(Config::new().with_draw_target(log)).unwrap();
+    /// ```
+    pub fn with_draw_target(mut self, target: impl DrawTarget + 'static) -> Self {
+        self.draw_target = Some(SharedDrawTarget::new(Box::new(target)));
+        self
+    }
+
+    /// True if the bars are drawn to a terminal.
+    pub(crate) fn is_terminal(&self) -> bool {
+        match &self.draw_target {
+            Some(target) => target.info.lock().terminal,
+            None => self.target.is_terminal(),
+        }
+    }
+
+    /// The size of the terminal the bars are drawn to as `(columns, rows)`, if it is a terminal.
+    pub(crate) fn terminal_size(&self) -> Option<(usize, usize)> {
+        match &self.draw_target {
+            Some(target) => target.info.lock().size,
+            None => {
+                let (terminal_size::Width(cols), terminal_size::Height(rows)) =
+                    self.target.terminal_size()?;
+                Some((cols as usize, rows as usize))
+            }
+        }
+    }
+
+    /// Limits how many times per second the bars are redrawn.
+    ///
+    /// By default bars are redrawn up to 50 times per second when they change, and about 30 times per second while they are animated.
+    /// A lower rate reduces the amount of output, while a higher rate makes fast animations smoother.
+    pub fn with_refresh_rate(mut self, frames_per_second: f64) -> Self {
+        assert!(
+            frames_per_second.is_finite() && frames_per_second > 0.0,
+            "the refresh rate must be positive"
+        );
+        self.refresh_rate = Some(frames_per_second);
+        self
+    }
+
+    /// Time between frames, if the refresh rate has been set.
+    pub(crate) fn frame_period(&self) -> Option<Duration> {
+        self.refresh_rate
+            .map(|rate| Duration::from_secs_f64(1.0 / rate))
+    }
+}
+
+/// Configures how bars are drawn.
+///
+/// This must be called before the first bar is created, or any other function in this crate is used.
+/// Returns the config back as an error if it is too late, in which case the default settings are used.
+///
+/// ```
+/// use headway::{Config, Target};
+///
+/// // Keep stdout free for the output of the program, and redraw the bars at most 10 times per second
+/// headway::init_with(Config::new().with_target(Target::Stderr).with_refresh_rate(10.0)).unwrap();
+/// ```
+pub fn init_with(config: Config) -> Result<(), Config> {
+    CONFIG.set(config)
+}
+
+/// The settings passed to [`init_with`], or the default settings.
+pub(crate) fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
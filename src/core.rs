@@ -0,0 +1,432 @@
+//! Low-level building blocks for progress trees and renderers.
+//!
+//! [`ProgressBar`] is the recommended way to use this crate. This module is for advanced users who want to build
+//! progress trees which are not tied to the global progress bar display, or who want to render progress in their own way.
+//!
+//! The API in this module is semi-stable. It is documented and tested, but may change more often than the rest of the crate.
+//!
+//! ```
+//! use headway::core::{Aggregation, BarNode, Renderer};
+//!
+//! let root = BarNode::new();
+//! let download = root.add_child(Aggregation::Weighted(0.8));
+//! let install = root.add_child(Aggregation::Weighted(0.2));
+//! download.set_length(10);
+//! download.set_position(5);
+//! install.set_length(1);
+//!
+//! assert_eq!(root.snapshot().progress(), Some(0.4));
+//! let line = Renderer::new().render(&root);
+//! assert!(line.contains("40%"));
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::clock;
+use crate::sync::Mutex;
+use crate::{
+    LifecycleState, NestedBars, NestedMeta, ProgressBar, ProgressBarState, RenderContext,
+    RenderedBar, Style, MANAGER,
+};
+
+/// A node in a tree of progress.
+///
+/// Nodes are reference counted, so cloning a node gives another handle to the same node.
+/// Unlike [`ProgressBar`], a node is not displayed anywhere unless it is wrapped using [`ProgressBar::from_node`]
+/// or rendered using a [`Renderer`], and it is not abandoned when dropped.
+#[derive(Clone)]
+pub struct BarNode {
+    pub(crate) state: Arc<Mutex<ProgressBarState>>,
+}
+
+impl Default for BarNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a child contributes to the progress of its parent. See [`BarNode::add_child`].
+///
+/// All children of a node must use the same kind of aggregation.
+/// For [`Aggregation::Custom`] this means that all children must use the same aggregate.
+#[derive(Clone, Debug)]
+pub enum Aggregation {
+    /// The child represents the given fraction of the parent, like [`ProgressBar::split_weighted`].
+    Weighted(f64),
+    /// The child represents the given number of items in the parent, like [`ProgressBar::split_sized`].
+    Sized(usize),
+    /// The position and length of the child are added to the parent's, like [`ProgressBar::split_summed`].
+    Summed,
+    /// The progress of the parent is calculated from all children using the given aggregate, like [`ProgressBar::split_custom`].
+    Custom(Arc<dyn Aggregate>),
+}
+
+/// Calculates the progress of a parent from the progress of its children.
+///
+/// See [`ProgressBar::split_custom`] and [`Aggregation::Custom`].
+/// This is implemented for all closures taking a slice of snapshots, so most of the time you do not need to implement it yourself.
+///
+/// ```
+/// use headway::core::{Aggregate, BarSnapshot};
+///
+/// // The parent is complete when any child is complete
+/// struct Fastest;
+///
+/// impl Aggregate for Fastest {
+///     fn combine(&self, children: &[BarSnapshot]) -> BarSnapshot {
+///         children
+///             .iter()
+///             .copied()
+///             .max_by(|a, b| a.progress().unwrap_or(0.0).total_cmp(&b.progress().unwrap_or(0.0)))
+///             .unwrap_or(BarSnapshot::EMPTY)
+///     }
+/// }
+/// ```
+pub trait Aggregate: Send + Sync {
+    /// Combines the snapshots of all children, in the order they were added, into a snapshot of the parent.
+    fn combine(&self, children: &[BarSnapshot]) -> BarSnapshot;
+}
+
+impl<F: Fn(&[BarSnapshot]) -> BarSnapshot + Send + Sync> Aggregate for F {
+    fn combine(&self, children: &[BarSnapshot]) -> BarSnapshot {
+        self(children)
+    }
+}
+
+impl fmt::Debug for dyn Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Aggregate")
+    }
+}
+
+/// An [`Aggregate`] where the child with the least progress defines the progress of the parent.
+///
+/// Children without a known length count as having made no progress.
+/// This is useful when the parent is done only when all children are done, like when uploading to several servers in parallel.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Slowest;
+
+impl Aggregate for Slowest {
+    fn combine(&self, children: &[BarSnapshot]) -> BarSnapshot {
+        children
+            .iter()
+            .copied()
+            .min_by(|a, b| {
+                a.progress()
+                    .unwrap_or(0.0)
+                    .total_cmp(&b.progress().unwrap_or(0.0))
+            })
+            .unwrap_or(BarSnapshot::EMPTY)
+    }
+}
+
+/// The aggregated progress of a node and all its children, at some point in time.
+///
+/// The fractions are between 0 and 1, and sum up to at most 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BarSnapshot {
+    /// Fraction of the node which has been completed.
+    pub completed: f64,
+    /// Fraction of the node which is in progress, but of which the exact progress is unknown.
+    /// This is the case for children without a known length.
+    pub in_progress: f64,
+    /// Fraction of the node which has been abandoned and will never be completed.
+    pub abandoned: f64,
+    /// The length of the node, as far as it is known so far.
+    pub length_lower: f64,
+    /// The length of the node, if it is known.
+    pub length_upper: Option<f64>,
+}
+
+impl BarSnapshot {
+    /// A snapshot of a node with no length and no progress. This is what a node without any children reports.
+    pub const EMPTY: BarSnapshot = BarSnapshot {
+        completed: 0.0,
+        in_progress: 0.0,
+        abandoned: 0.0,
+        length_lower: 0.0,
+        length_upper: Some(0.0),
+    };
+
+    /// The number of completed items.
+    pub fn position(&self) -> f64 {
+        self.completed * self.length_lower
+    }
+
+    /// The fraction of the node which is complete, between 0 and 1. None if the length is not known.
+    pub fn progress(&self) -> Option<f64> {
+        let upper = self.length_upper?;
+        if upper > 0.0 {
+            Some((self.completed * self.length_lower / upper).clamp(0.0, 1.0))
+        } else {
+            Some(0.0)
+        }
+    }
+}
+
+impl BarNode {
+    /// Creates a new node without a length or any children.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ProgressBarState::default())),
+        }
+    }
+
+    /// Adds a child to this node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node already has children which use a different kind of aggregation.
+    pub fn add_child(&self, aggregation: Aggregation) -> BarNode {
+        let child = BarNode::new();
+        let mut state = self.state.lock();
+        let nested = state.nested.get_or_insert_with(|| NestedBars {
+            bars: vec![],
+            meta: match aggregation {
+                Aggregation::Weighted(_) => NestedMeta::Weighted(vec![]),
+                Aggregation::Sized(_) => NestedMeta::Sized(vec![]),
+                Aggregation::Summed => NestedMeta::Summed,
+                Aggregation::Custom(ref aggregate) => NestedMeta::Custom(aggregate.clone()),
+            },
+        });
+        match (&mut nested.meta, aggregation) {
+            (NestedMeta::Weighted(weights), Aggregation::Weighted(weight)) => {
+                assert!(
+                    weight.is_finite() && weight >= 0.0,
+                    "weight must be non-negative"
+                );
+                weights.push(weight);
+                child.state.lock().parent = Arc::downgrade(&self.state);
+            }
+            (NestedMeta::Sized(sizes), Aggregation::Sized(size)) => {
+                child.set_length(size);
+                sizes.push(size as f64);
+            }
+            (NestedMeta::Summed, Aggregation::Summed) => {}
+            (NestedMeta::Custom(a), Aggregation::Custom(b)) if Arc::ptr_eq(a, &b) => {}
+            _ => panic!("All children of a node must use the same kind of aggregation"),
+        }
+        nested.bars.push(child.state.clone());
+        child
+    }
+
+    /// The children of this node, in the order they were added.
+    pub fn children(&self) -> Vec<BarNode> {
+        match &self.state.lock().nested {
+            Some(nested) => nested
+                .bars
+                .iter()
+                .map(|state| BarNode {
+                    state: state.clone(),
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Length of the node, if it has been set.
+    pub fn length(&self) -> Option<usize> {
+        self.state.lock().length
+    }
+
+    /// Sets the length of the node.
+    pub fn set_length(&self, length: usize) {
+        self.state.lock().set_length(Some(length));
+    }
+
+    /// Position of the node itself. This does not include any progress of its children, see [`Self::snapshot`] for that.
+    pub fn position(&self) -> usize {
+        self.state.lock().position
+    }
+
+    /// Sets the position of the node.
+    pub fn set_position(&self, position: usize) {
+        let mut state = self.state.lock();
+        state.position = position;
+        state.record_progress();
+    }
+
+    /// Increments the position of the node by 1.
+    pub fn inc(&self) {
+        let mut state = self.state.lock();
+        state.position += 1;
+        state.record_progress();
+    }
+
+    /// Sets the message displayed next to the node.
+    pub fn set_message(&self, message: impl Into<String>) {
+        let mut state = self.state.lock();
+        state.message = Some(message.into());
+        state.last_activity = clock::now();
+    }
+
+    /// Sets the prefix displayed before the node.
+    pub fn set_prefix(&self, prefix: impl Into<String>) {
+        self.state.lock().prefix = Some(prefix.into());
+    }
+
+    /// Marks the node as finished. If the node has a length, the position is set to the length.
+    pub fn finish(&self) {
+        let mut state = self.state.lock();
+        if let Some(length) = state.length {
+            state.position = length;
+        }
+        state.lifecycle = LifecycleState::Completed;
+        state.finished_at.get_or_insert_with(clock::now);
+    }
+
+    /// Marks the node as abandoned. The remaining part of the node will never be completed.
+    pub fn abandon(&self) {
+        let mut state = self.state.lock();
+        state.lifecycle = LifecycleState::Abandoned;
+        state.finished_at.get_or_insert_with(clock::now);
+    }
+
+    /// True if the node has been finished or abandoned.
+    pub fn is_done(&self) -> bool {
+        self.state.lock().lifecycle != LifecycleState::InProgress
+    }
+
+    /// The aggregated progress of this node and all its children.
+    pub fn snapshot(&self) -> BarSnapshot {
+        self.state.lock().snapshot()
+    }
+}
+
+/// Renders nodes as text.
+///
+/// This uses the same rendering as the global progress bar display.
+pub struct Renderer {
+    style: Style,
+    color: bool,
+    reference_time: Instant,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer {
+    /// Creates a renderer with the default style and without colors.
+    pub fn new() -> Self {
+        Self {
+            style: Style::new(),
+            color: false,
+            reference_time: clock::now(),
+        }
+    }
+
+    /// Sets the style used for nodes which do not have a style of their own.
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// If enabled, the output contains ANSI escape codes for colors and animations.
+    pub fn with_color(mut self, enabled: bool) -> Self {
+        self.color = enabled;
+        self
+    }
+
+    /// Renders the node as a single line, as it would be displayed in a terminal.
+    pub fn render(&self, node: &BarNode) -> String {
+        let mut out = String::new();
+        self.render_into(node, &mut out);
+        out
+    }
+
+    /// Like [`Self::render`], but appends the line to an existing string.
+    ///
+    /// Renderers which redraw many nodes many times per second can reuse the same string, to avoid allocating for every frame.
+    ///
+    /// ```
+    /// use headway::core::{BarNode, Renderer};
+    ///
+    /// let node = BarNode::new();
+    /// node.set_length(10);
+    /// let renderer = Renderer::new();
+    /// let mut line = String::new();
+    /// for _ in 0..10 {
+    ///     node.inc();
+    ///     line.clear();
+    ///     renderer.render_into(&node, &mut line);
+    /// }
+    /// assert!(line.ends_with("10/10"));
+    /// ```
+    pub fn render_into(&self, node: &BarNode, out: &mut String) {
+        let ctx = RenderContext {
+            color: self.color,
+            reference_time: &self.reference_time,
+            default_style: &self.style,
+            width: None,
+        };
+        // SAFETY: Writes to strings cannot fail
+        node.state.lock().render(out, &ctx, &mut false).unwrap();
+    }
+
+    /// Renders the node and all its children, with the same information that is passed to [`crate::on_frame`] callbacks.
+    ///
+    /// The node gets the path `[0]`, see [`RenderedBar::path`].
+    pub fn render_tree(&self, node: &BarNode) -> RenderedBar {
+        let ctx = RenderContext {
+            color: false,
+            reference_time: &self.reference_time,
+            default_style: &self.style,
+            width: None,
+        };
+        RenderedBar::new(&node.state.lock(), &ctx, vec![0], None)
+    }
+
+    /// Renders the node as a plain text description, without any bar graphics or colors.
+    ///
+    /// ```text
+    /// Downloading 45% (45/100): assets.tar.gz
+    /// ```
+    pub fn render_plain(&self, node: &BarNode) -> String {
+        let mut out = String::new();
+        // SAFETY: Writes to strings cannot fail
+        node.state
+            .lock()
+            .render_plain(&mut out, &self.style)
+            .unwrap();
+        out
+    }
+}
+
+impl ProgressBar {
+    /// Displays a node like any other progress bar.
+    ///
+    /// The node stays visible until the returned bar is finished or abandoned, and all other handles to the node have been dropped.
+    ///
+    /// ```
+    /// use headway::core::BarNode;
+    /// use headway::ProgressBar;
+    ///
+    /// let node = BarNode::new();
+    /// node.set_length(10);
+    /// let mut p = ProgressBar::from_node(node.clone());
+    /// node.set_position(5);
+    /// p.finish();
+    /// ```
+    pub fn from_node(node: BarNode) -> Self {
+        let mut manager = MANAGER.lock();
+        manager.bars.push(node.state.clone());
+        if manager.needs_thread() {
+            manager.ensure_thread_started();
+        }
+        ProgressBar {
+            state: Some(node.state),
+        }
+    }
+
+    /// The node which holds the state of this bar, or None if the bar has been finished or abandoned.
+    pub fn node(&self) -> Option<BarNode> {
+        self.state.as_ref().map(|state| BarNode {
+            state: state.clone(),
+        })
+    }
+}
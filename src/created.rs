@@ -0,0 +1,146 @@
+use std::cell::Cell;
+use std::panic::Location;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::{
+    NestedBars, NestedMeta, ProgressBar, ProgressBarState, ProgressBarSummedNester, MANAGER,
+};
+
+/// A callback registered using [`on_bar_created`].
+pub(crate) type CreationHook = Arc<dyn Fn(&mut BarCreated) + Send + Sync>;
+
+thread_local! {
+    /// True while the creation hooks are running on this thread, so that bars created by the hooks themselves are left alone
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Registers a callback which is invoked each time a new top-level bar is created, for example using [`ProgressBar::new`].
+///
+/// This gives an application the final say over bars which are created by its dependencies. The callback can change the bar
+/// like any other bar, for example by setting a prefix or a style, hide it, or move it into a bar of the application.
+/// Bars can be told apart using the location in the code where they were created, or their [`scope_label`](crate::scope_label).
+///
+/// The callback is invoked on the thread which creates the bar, right after it has been created, so the message and length
+/// set by the creator are not known yet. Children of split bars are not passed to the callback.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// headway::on_bar_created(|created| {
+///     // Bars created by other crates are in the cargo registry
+///     if created.location().file().contains(".cargo") {
+///         created.bar().set_prefix("deps");
+///     }
+/// });
+///
+/// let p = ProgressBar::new().with_length(100);
+/// ```
+pub fn on_bar_created(callback: impl Fn(&mut BarCreated) + Send + Sync + 'static) {
+    MANAGER.lock().creation_hooks.push(Arc::new(callback));
+}
+
+/// A bar which has just been created, passed to the callbacks registered using [`on_bar_created`].
+pub struct BarCreated<'a> {
+    bar: &'a ProgressBar,
+    location: &'static Location<'static>,
+    hidden: bool,
+    parent: Option<Arc<Mutex<ProgressBarState>>>,
+}
+
+impl BarCreated<'_> {
+    /// The bar which was created. It can be changed like any other bar.
+    pub fn bar(&self) -> &ProgressBar {
+        self.bar
+    }
+
+    /// The location in the code where the bar was created.
+    ///
+    /// This is the caller of [`ProgressBar::new`], or of an adapter like [`ProgressBarIterable::progress`](crate::ProgressBarIterable::progress).
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The label of the [`scope_label`](crate::scope_label) the bar was created in, if any.
+    pub fn label(&self) -> Option<String> {
+        self.bar.label()
+    }
+
+    /// Prevents the bar from being displayed, like a bar created using [`ProgressBar::hidden`].
+    pub fn hide(&mut self) {
+        self.hidden = true;
+    }
+
+    /// Moves the bar into a bar of the application, which displays the sum of its children. See [`ProgressBar::split_summed`].
+    ///
+    /// The bar is then no longer displayed on its own line.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use headway::ProgressBar;
+    ///
+    /// let dependencies = Arc::new(ProgressBar::new().with_message("Dependencies").split_summed());
+    /// let parent = dependencies.clone();
+    /// headway::on_bar_created(move |created| {
+    ///     if created.label().as_deref() == Some("deps") {
+    ///         created.set_parent(&parent);
+    ///     }
+    /// });
+    ///
+    /// headway::scope_label("deps", || {
+    ///     for _ in ProgressBar::new().wrap(0..100) {}
+    /// });
+    /// ```
+    pub fn set_parent(&mut self, parent: &ProgressBarSummedNester) {
+        self.parent = parent.bar.state.clone();
+    }
+}
+
+/// Runs the callbacks registered using [`on_bar_created`] for a bar which was just created.
+///
+/// Must be called without holding the [`MANAGER`] lock, since the callbacks may interact with bars.
+pub(crate) fn run_hooks(
+    bar: &ProgressBar,
+    location: &'static Location<'static>,
+    hooks: Vec<CreationHook>,
+) {
+    if hooks.is_empty() || IN_HOOK.with(Cell::get) {
+        return;
+    }
+    let Some(state) = &bar.state else {
+        return;
+    };
+    let mut created = BarCreated {
+        bar,
+        location,
+        hidden: false,
+        parent: None,
+    };
+    IN_HOOK.with(|h| h.set(true));
+    // Reset the flag even if a hook panics
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            IN_HOOK.with(|h| h.set(false));
+        }
+    }
+    let reset = Reset;
+    for hook in hooks {
+        hook(&mut created);
+    }
+    drop(reset);
+
+    if created.hidden || created.parent.is_some() {
+        let mut manager = MANAGER.lock();
+        manager.bars.retain(|b| !Arc::ptr_eq(b, state));
+        if let Some(parent) = created.parent.filter(|_| !created.hidden) {
+            if let Some(NestedBars {
+                bars,
+                meta: NestedMeta::Summed,
+            }) = &mut parent.lock().nested
+            {
+                bars.push(state.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Widgets for displaying progress bars in [egui](https://docs.rs/egui), enabled by the `egui` feature.
+//!
+//! The same progress reporting code can then drive both a terminal and a desktop app.
+//! Use [`crate::on_frame`] to receive the state of all bars from the background thread, and display the latest frame each time the UI is drawn:
+//!
+//! ```no_run
+//! use std::sync::{Arc, Mutex};
+//! use headway::RenderedFrame;
+//!
+//! let latest: Arc<Mutex<Option<RenderedFrame>>> = Default::default();
+//! let l = latest.clone();
+//! headway::on_frame(move |frame| *l.lock().unwrap() = Some(frame.clone()));
+//!
+//! // In the UI code
+//! # fn ui(ui: &mut egui::Ui, latest: &Mutex<Option<RenderedFrame>>) {
+//! if let Some(frame) = &*latest.lock().unwrap() {
+//!     headway::egui::show_frame(ui, frame);
+//! }
+//! # }
+//! ```
+
+use ::egui::{ProgressBar, Response, Ui};
+
+use crate::{RenderedBar, RenderedFrame};
+
+/// Displays all bars in a frame, including nested bars. See the [module documentation](self).
+pub fn show_frame(ui: &mut Ui, frame: &RenderedFrame) {
+    if let Some(title) = frame.title() {
+        ui.strong(title);
+    }
+    for bar in frame.bars() {
+        show_bar(ui, bar);
+    }
+}
+
+/// Displays a bar, with its nested bars indented below it.
+///
+/// Bars without a known length are animated. Abandoned bars are drawn using the error color of the current theme.
+/// Returns the response of the bar itself.
+///
+/// To display a tree of [`BarNode`](crate::core::BarNode)s, render it using [`Renderer::render_tree`](crate::core::Renderer::render_tree) first.
+pub fn show_bar(ui: &mut Ui, bar: &RenderedBar) -> Response {
+    let mut widget = ProgressBar::new(bar.progress().unwrap_or(0.0) as f32)
+        .text(bar.plain_text())
+        .animate(bar.progress().is_none() && !bar.is_finished() && !bar.is_abandoned());
+    if bar.is_abandoned() {
+        widget = widget.fill(ui.visuals().error_fg_color);
+    }
+    let response = ui.add(widget);
+    if !bar.children().is_empty() {
+        ui.indent(bar.id(), |ui| {
+            for child in bar.children() {
+                show_bar(ui, child);
+            }
+        });
+    }
+    response
+}
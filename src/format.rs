@@ -0,0 +1,253 @@
+//! Helpers for formatting values for display.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::Labels;
+
+/// How durations, like the elapsed time of a bar, are displayed.
+///
+/// See [`Style::with_duration_format`](crate::Style::with_duration_format).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// A short form, like `45s`, `3m12s` or `1h05m`.
+    #[default]
+    Compact,
+    /// Like a clock, for example `00:03:12`.
+    Clock,
+    /// Rounded to the largest unit, like `about 3 minutes`.
+    Humanized,
+}
+
+/// How bars are described in plain text, like in accessible mode and in logs.
+///
+/// See [`Style::with_plain_format`](crate::Style::with_plain_format).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlainFormat {
+    /// A sentence meant to be read by people, like `Downloading 45% (45/100): assets.tar.gz`.
+    #[default]
+    Readable,
+    /// Fixed-width columns meant to be parsed by tools like `cut` and `awk`.
+    ///
+    /// Every line has the same layout, which will not change in future versions.
+    /// Columns are separated by a single space, and are padded so that each one always starts at the same character:
+    ///
+    /// | Characters | Column   | Contents                                                       |
+    /// |------------|----------|----------------------------------------------------------------|
+    /// | 1-9        | status   | `running`, `done` or `abandoned`, left-aligned                 |
+    /// | 11-13      | percent  | Whole percent from 0 to 100, right-aligned                     |
+    /// | 15-26      | position | Number of items processed, right-aligned                       |
+    /// | 28-39      | length   | Total number of items, right-aligned                           |
+    /// | 41-48      | elapsed  | Whole seconds since the bar was created, right-aligned         |
+    /// | 50-57      | eta      | Estimated seconds remaining, rounded up, right-aligned         |
+    /// | 59-        | text     | The prefix and message of the bar, separated by a space        |
+    ///
+    /// Values which are not known are written as `-`, and numbers which are too large for their column are clamped to
+    /// the largest value which fits, like `99999999`. Control characters in the text are replaced by spaces, so that each bar
+    /// is always a single line. Labels (see [`Labels`]) and count formats are not used, to keep the output the same in every language.
+    ///
+    /// ```
+    /// use headway::core::{BarNode, Renderer};
+    /// use headway::{PlainFormat, Style};
+    ///
+    /// let renderer = Renderer::new().with_style(Style::new().with_plain_format(PlainFormat::Columns));
+    /// let node = BarNode::new();
+    /// node.set_message("Resolving\tmirrors");
+    /// assert_eq!(
+    ///     renderer.render_plain(&node),
+    ///     "running     -            0            -        0        - Resolving mirrors"
+    /// );
+    ///
+    /// node.set_prefix("[2/3]");
+    /// node.set_message("Downloading");
+    /// node.set_length(200);
+    /// node.set_position(50);
+    /// node.abandon();
+    /// let line = renderer.render_plain(&node);
+    /// assert_eq!(
+    ///     line,
+    ///     "abandoned  25           50          200        0        - [2/3] Downloading"
+    /// );
+    /// assert_eq!(&line[14..26].trim(), &"50");
+    /// assert_eq!(&line[58..], "[2/3] Downloading");
+    /// ```
+    Columns,
+}
+
+/// Writes a number right-aligned in a column of the given width, or `-` if it is not known. See [`PlainFormat::Columns`].
+pub(crate) fn write_column(out: &mut String, value: Option<u64>, width: usize) -> std::fmt::Result {
+    match value {
+        Some(value) => {
+            let max = 10u64.saturating_pow(width as u32).saturating_sub(1);
+            write!(out, "{:>width$}", value.min(max), width = width)
+        }
+        None => write!(out, "{:>width$}", "-", width = width),
+    }
+}
+
+/// Writes a duration using the given format.
+pub(crate) fn write_duration(
+    out: &mut String,
+    duration: Duration,
+    format: DurationFormat,
+    labels: &Labels,
+) -> std::fmt::Result {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    match format {
+        DurationFormat::Compact => {
+            let Labels {
+                hours: h,
+                minutes: m,
+                seconds: s,
+                ..
+            } = labels;
+            let (major, major_label, minor, minor_label) = if hours > 0 {
+                (hours, h, Some(minutes), m)
+            } else if minutes > 0 {
+                (minutes, m, Some(seconds), s)
+            } else {
+                (seconds, s, None, s)
+            };
+            write_integer(out, major, 1);
+            out.push_str(major_label);
+            if let Some(minor) = minor {
+                write_integer(out, minor, 2);
+                out.push_str(minor_label);
+            }
+            Ok(())
+        }
+        DurationFormat::Clock => {
+            write_integer(out, hours, 2);
+            out.push(':');
+            write_integer(out, minutes, 2);
+            out.push(':');
+            write_integer(out, seconds, 2);
+            Ok(())
+        }
+        DurationFormat::Humanized => {
+            let [second, minute, hour] = &labels.humanized_units;
+            if secs < 60 {
+                return write!(out, "{} {}", secs, second[(secs != 1) as usize]);
+            }
+            let minutes = (secs + 30) / 60;
+            if minutes < 60 {
+                return write!(
+                    out,
+                    "{} {} {}",
+                    labels.about,
+                    minutes,
+                    minute[(minutes != 1) as usize]
+                );
+            }
+            let hours = (secs + 1800) / 3600;
+            write!(
+                out,
+                "{} {} {}",
+                labels.about,
+                hours,
+                hour[(hours != 1) as usize]
+            )
+        }
+    }
+}
+
+/// How item counts, like the position and length of a bar, are displayed.
+///
+/// See [`Style::with_count_format`](crate::Style::with_count_format).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CountFormat {
+    /// Digits only, like `123456789`.
+    #[default]
+    Plain,
+    /// Digits in groups of three, separated by the given character, like `123,456,789`.
+    Separated(char),
+    /// Abbreviated using SI prefixes, like `123.4M`.
+    ///
+    /// Values are rounded down, so that a bar never appears to have made more progress than it has.
+    Si,
+}
+
+/// Writes an item count using the given format.
+pub(crate) fn write_count(out: &mut String, value: u64, format: CountFormat) -> std::fmt::Result {
+    match format {
+        CountFormat::Plain => write_integer(out, value, 1),
+        CountFormat::Separated(separator) => {
+            let mut buffer = [0; MAX_DIGITS];
+            let digits = digits(value, 1, &mut buffer);
+            for (i, &digit) in digits.iter().enumerate() {
+                if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                    out.push(separator);
+                }
+                out.push(digit as char);
+            }
+        }
+        CountFormat::Si => {
+            const PREFIXES: [char; 6] = ['k', 'M', 'G', 'T', 'P', 'E'];
+            if value < 1000 {
+                write_integer(out, value, 1);
+                return Ok(());
+            }
+            let mut scale = 1000;
+            let mut prefix = 0;
+            while prefix + 1 < PREFIXES.len() && value / scale >= 1000 {
+                scale *= 1000;
+                prefix += 1;
+            }
+            // One decimal, rounded down
+            let tenths = value / (scale / 10);
+            write_integer(out, tenths / 10, 1);
+            if !tenths.is_multiple_of(10) {
+                out.push('.');
+                write_integer(out, tenths % 10, 1);
+            }
+            out.push(PREFIXES[prefix]);
+        }
+    }
+    Ok(())
+}
+
+/// Writes a number of items per second, like `0.5`, `42` or `1.2k`.
+///
+/// Rates below 10 are written with one decimal, since they would otherwise often be displayed as 0.
+pub(crate) fn write_rate(out: &mut String, per_sec: f64) {
+    if per_sec < 10.0 {
+        let tenths = (per_sec.max(0.0) * 10.0).round() as u64;
+        write_integer(out, tenths / 10, 1);
+        out.push('.');
+        write_integer(out, tenths % 10, 1);
+    } else {
+        // Infallible, the output is a string
+        let _ = write_count(out, per_sec.round() as u64, CountFormat::Si);
+    }
+}
+
+/// Writes a fraction between 0 and 1 as a percentage, rounded down, like `45%`.
+pub(crate) fn write_percentage(out: &mut String, fraction: f64) {
+    write_integer(out, (fraction * 100.0).floor() as u64, 1);
+    out.push('%');
+}
+
+/// The number of digits in the largest `u64`
+const MAX_DIGITS: usize = 20;
+
+/// Writes an integer, padded with zeros to at least `min_digits` digits.
+///
+/// Numbers are written many times for every frame, so this avoids the overhead of the formatting machinery in [`std::fmt`].
+pub(crate) fn write_integer(out: &mut String, value: u64, min_digits: usize) {
+    let mut buffer = [0; MAX_DIGITS];
+    let digits = digits(value, min_digits, &mut buffer);
+    // SAFETY: The buffer only contains ASCII digits
+    out.push_str(std::str::from_utf8(digits).unwrap());
+}
+
+/// The ASCII digits of the value, padded with zeros to at least `min_digits` digits, written to the end of the buffer.
+fn digits(mut value: u64, min_digits: usize, buffer: &mut [u8; MAX_DIGITS]) -> &[u8] {
+    let mut start = MAX_DIGITS;
+    while value > 0 || MAX_DIGITS - start < min_digits.clamp(1, MAX_DIGITS) {
+        start -= 1;
+        buffer[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    &buffer[start..]
+}
@@ -0,0 +1,411 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::sync::Mutex;
+use crate::{
+    LifecycleState, NestedBars, NestedMeta, ProgressBarState, RenderContext, Style, MANAGER,
+    TIMELINE_ORIGIN,
+};
+
+/// A callback registered using [`on_frame`].
+pub(crate) type FrameHook = Box<dyn FnMut(&RenderedFrame) + Send>;
+
+/// Registers a callback which is invoked each time the progress bars are drawn.
+///
+/// The callback receives the rendered text of every visible bar, together with structured information about it.
+/// This can be used to mirror the progress somewhere else, like a web UI, a braille display or a status bar.
+///
+/// Frames are produced even if the output is not a terminal, or is being captured using [`crate::test_capture`].
+/// When the last bar has been removed, a frame without any bars is produced, so that the callback knows that nothing is visible anymore.
+/// The callback is invoked from a background thread, without holding any locks, so it may interact with progress bars.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// headway::on_frame(|frame| {
+///     for bar in frame.bars() {
+///         if let Some(p) = bar.progress() {
+///             eprintln!("{:.0}% {}", p * 100.0, bar.message().unwrap_or_default());
+///         }
+///     }
+/// });
+///
+/// let p = ProgressBar::new().with_message("Uploading");
+/// for _ in p.wrap(0..100) {}
+/// ```
+pub fn on_frame(callback: impl FnMut(&RenderedFrame) + Send + 'static) {
+    let mut manager = MANAGER.lock();
+    manager.frame_hooks.push(Box::new(callback));
+    manager.has_frame_hooks = true;
+    manager.ensure_thread_started();
+}
+
+/// A function registered using [`set_frame_filter`].
+pub(crate) type FrameFilter = Box<dyn Fn(&str) -> String + Send>;
+
+/// Transforms all text right before it is written to the terminal, or removes the filter if `None`.
+///
+/// The filter receives the text of the bars in each frame, as well as the final state of bars which are printed permanently.
+/// This can be used to add a prefix to every line (like the service names of `docker compose`), strip escape codes,
+/// or write a copy of the output somewhere else. Cursor movements used to redraw the bars are added after the filter has been applied,
+/// so the filter may change the number of lines. The text passed to the filter always ends with a newline, and the filter should keep it.
+///
+/// The filter is called while the bars are locked, so it must not interact with progress bars.
+/// Frames recorded using [`crate::test_capture`] are filtered too.
+///
+/// ```
+/// headway::set_frame_filter(Some(|text: &str| {
+///     text.lines().map(|line| format!("web    | {}\n", line)).collect()
+/// }));
+/// ```
+pub fn set_frame_filter<F: Fn(&str) -> String + Send + 'static>(filter: Option<F>) {
+    MANAGER.lock().frame_filter = filter.map(|f| Box::new(f) as FrameFilter);
+}
+
+/// All visible progress bars, as drawn at some point in time.
+///
+/// See [`on_frame`].
+#[derive(Clone, Debug)]
+pub struct RenderedFrame {
+    title: Option<String>,
+    lines: Vec<String>,
+    bars: Vec<RenderedBar>,
+}
+
+impl RenderedFrame {
+    /// Renders the given bars. Bars are locked one at a time.
+    pub(crate) fn new(
+        title: Option<String>,
+        bars: &[Arc<Mutex<ProgressBarState>>],
+        reference_time: &Instant,
+        default_style: &Style,
+    ) -> Self {
+        let ctx = RenderContext {
+            color: false,
+            reference_time,
+            default_style,
+            width: None,
+        };
+        let bars = bars
+            .iter()
+            .enumerate()
+            .map(|(i, bar)| RenderedBar::new(&bar.lock(), &ctx, vec![i], None))
+            .collect::<Vec<_>>();
+        Self {
+            title,
+            lines: bars.iter().map(|bar| bar.line.clone()).collect(),
+            bars,
+        }
+    }
+
+    /// The title displayed above the bars, see [`crate::set_title`].
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// One line of text for each bar, as it would be displayed in a terminal but without colors.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Information about each bar. In the same order as [`Self::lines`].
+    ///
+    /// Bars which have been split contain their children, see [`RenderedBar::children`].
+    pub fn bars(&self) -> &[RenderedBar] {
+        &self.bars
+    }
+
+    /// All bars in the frame, including nested ones. Parents come before their children.
+    pub fn all_bars(&self) -> impl Iterator<Item = &RenderedBar> {
+        fn visit<'a>(bar: &'a RenderedBar, out: &mut Vec<&'a RenderedBar>) {
+            out.push(bar);
+            for child in &bar.children {
+                visit(child, out);
+            }
+        }
+        let mut all = vec![];
+        for bar in &self.bars {
+            visit(bar, &mut all);
+        }
+        all.into_iter()
+    }
+}
+
+/// When a bar ran, in a form which is easy to export, for example to build analytics.
+///
+/// Times are in seconds since the first progress bar in the process was created, so the timings of all bars can be compared with each other.
+/// With the `serde` feature enabled, this type can be serialized.
+///
+/// ```
+/// # fn export(frame: &headway::RenderedFrame) {
+/// for bar in frame.all_bars() {
+///     let timing = bar.timing();
+///     if let (Some(started), Some(finished)) = (timing.started, timing.finished) {
+///         println!("{:?} took {:.1}s", bar.message(), finished - started);
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BarTiming {
+    /// When the bar was created
+    pub created: f64,
+    /// When the bar, or any of its children, first made progress. None if it has not made any progress.
+    pub started: Option<f64>,
+    /// When the bar was finished or abandoned. None if it is still in progress.
+    pub finished: Option<f64>,
+    /// Total number of seconds the bar has been stalled, see [`Style::with_stall_threshold`].
+    /// Always zero if the style has no stall threshold.
+    pub stalled: f64,
+    /// Number of seconds the bar has spent making progress, see [`Style::with_activity`]
+    pub active: f64,
+    /// Number of seconds the bar has spent without making progress
+    pub idle: f64,
+}
+
+impl BarTiming {
+    pub(crate) fn new(state: &ProgressBarState) -> Self {
+        let seconds = |t: Instant| t.saturating_duration_since(*TIMELINE_ORIGIN).as_secs_f64();
+        let active = state.active_time();
+        Self {
+            created: seconds(state.created),
+            started: state.started_at().map(seconds),
+            finished: state.finished_at.map(seconds),
+            stalled: state.total_stalled().as_secs_f64(),
+            active: active.as_secs_f64(),
+            idle: state.elapsed().saturating_sub(active).as_secs_f64(),
+        }
+    }
+}
+
+/// How a bar has been split into children.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitKind {
+    /// See [`ProgressBar::split_weighted`](crate::ProgressBar::split_weighted) and [`ProgressBar::split_calibrated`](crate::ProgressBar::split_calibrated).
+    Weighted,
+    /// See [`ProgressBar::split_sized`](crate::ProgressBar::split_sized).
+    Sized,
+    /// See [`ProgressBar::split_summed`](crate::ProgressBar::split_summed).
+    Summed,
+    /// See [`ProgressBar::split_custom`](crate::ProgressBar::split_custom).
+    Custom,
+}
+
+/// Information about a single bar in a [`RenderedFrame`].
+#[derive(Clone, Debug)]
+pub struct RenderedBar {
+    id: u64,
+    parent: Option<u64>,
+    path: Vec<usize>,
+    split: Option<SplitKind>,
+    children: Vec<RenderedBar>,
+    line: String,
+    plain_text: String,
+    prefix: Option<String>,
+    message: Option<String>,
+    label: Option<String>,
+    position: usize,
+    length: Option<usize>,
+    progress: Option<f64>,
+    eta: Option<Duration>,
+    abandon_reason: Option<String>,
+    timing: BarTiming,
+    lifecycle: LifecycleState,
+}
+
+impl RenderedBar {
+    pub(crate) fn new(
+        state: &ProgressBarState,
+        ctx: &RenderContext,
+        path: Vec<usize>,
+        parent: Option<u64>,
+    ) -> Self {
+        let mut line = String::new();
+        // SAFETY: Writes to strings cannot fail
+        state.render(&mut line, ctx, &mut false).unwrap();
+        let mut plain_text = String::new();
+        state
+            .render_plain(&mut plain_text, ctx.default_style)
+            .unwrap();
+        let (progress_value, _, _, length_lower, length_upper) = state.progress_count();
+        let (split, children) = match &state.nested {
+            Some(NestedBars { bars, meta }) => {
+                let split = match meta {
+                    NestedMeta::Weighted(_) | NestedMeta::Calibrated(_) => SplitKind::Weighted,
+                    NestedMeta::Sized(_) => SplitKind::Sized,
+                    NestedMeta::Summed => SplitKind::Summed,
+                    NestedMeta::Custom(_) => SplitKind::Custom,
+                };
+                let children = bars
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| {
+                        let mut child_path = path.clone();
+                        child_path.push(i);
+                        RenderedBar::new(&child.lock(), ctx, child_path, Some(state.id))
+                    })
+                    .collect();
+                (Some(split), children)
+            }
+            None => (None, vec![]),
+        };
+        Self {
+            id: state.id,
+            parent,
+            path,
+            split,
+            children,
+            line,
+            plain_text,
+            prefix: state.prefix(),
+            message: state.full_message(),
+            label: state.label.as_deref().map(str::to_string),
+            position: (progress_value * length_lower).floor() as usize,
+            length: length_upper.map(|l| l as usize),
+            progress: state.progress(),
+            eta: state.eta(),
+            abandon_reason: state
+                .abandon_reason
+                .clone()
+                .filter(|_| state.lifecycle == LifecycleState::Abandoned),
+            timing: BarTiming::new(state),
+            lifecycle: state.lifecycle,
+        }
+    }
+
+    /// A number which uniquely identifies this bar, and stays the same between frames.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The [`Self::id`] of the bar this bar is nested in, if any.
+    pub fn parent_id(&self) -> Option<u64> {
+        self.parent
+    }
+
+    /// The position of the bar in the tree of nested bars.
+    ///
+    /// The first element is the index of the top-level bar in the frame, and each following element is the index among the children of the previous bar.
+    /// For example `[0, 2, 1]` is the second child of the third child of the first bar, which can be written as `0.2.1`.
+    ///
+    /// Note that the index of top-level bars changes when bars above them are finished. Use [`Self::id`] to track a bar between frames.
+    ///
+    /// ```
+    /// # fn print(bar: &headway::RenderedBar) {
+    /// let address = bar.path().iter().map(|i| i.to_string()).collect::<Vec<_>>().join(".");
+    /// println!("{} {}", address, bar.line());
+    /// # }
+    /// ```
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// How this bar has been split, if it has been.
+    pub fn split_kind(&self) -> Option<SplitKind> {
+        self.split
+    }
+
+    /// The bars which this bar has been split into, in the order they were created.
+    pub fn children(&self) -> &[RenderedBar] {
+        &self.children
+    }
+
+    /// The bar as it would be displayed in a terminal, but without colors.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// A plain text description of the bar, without any bar graphics. The same text is used in accessible mode (see [`crate::set_accessible_mode`]).
+    ///
+    /// ```text
+    /// Downloading 45% (45/100): assets.tar.gz
+    /// ```
+    pub fn plain_text(&self) -> &str {
+        &self.plain_text
+    }
+
+    /// The prefix displayed before the bar, if any.
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// The message displayed next to the bar, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The label of the [`scope_label`](crate::scope_label) the bar was created in, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The amount of progress the bar has made. For bars which have been split, this is the combined progress of all children.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The length of the bar, if it is known.
+    pub fn length(&self) -> Option<usize> {
+        self.length
+    }
+
+    /// The fraction of the bar which is complete, between 0 and 1. None if the length of the bar is not known.
+    pub fn progress(&self) -> Option<f64> {
+        self.progress
+    }
+
+    /// Estimated time until the bar is finished, if it is in progress and the estimate is known.
+    ///
+    /// Each bar is estimated from its own speed since it started making progress.
+    /// For bars split using [`ProgressBar::split_weighted`](crate::ProgressBar::split_weighted) or [`ProgressBar::split_sized`](crate::ProgressBar::split_sized),
+    /// the children which have not started yet are estimated from their weights, so the estimate of the parent covers the whole job.
+    ///
+    /// ```
+    /// # use std::thread::sleep;
+    /// # use std::time::Duration;
+    /// use headway::core::{Aggregation, BarNode, Renderer};
+    ///
+    /// let root = BarNode::new();
+    /// let first = root.add_child(Aggregation::Weighted(0.5));
+    /// let second = root.add_child(Aggregation::Weighted(0.5));
+    /// first.set_length(10);
+    /// second.set_length(10);
+    /// for _ in 0..5 {
+    ///     sleep(Duration::from_millis(10));
+    ///     first.inc();
+    /// }
+    ///
+    /// let bar = Renderer::new().render_tree(&root);
+    /// // The second child has not started, so it cannot estimate its own remaining time
+    /// assert!(bar.children()[1].eta().is_none());
+    /// // But the parent includes it in its estimate, based on its weight
+    /// assert!(bar.eta().unwrap() > bar.children()[0].eta().unwrap());
+    /// ```
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta
+    }
+
+    /// True if the bar has been finished.
+    pub fn is_finished(&self) -> bool {
+        self.lifecycle == LifecycleState::Completed
+    }
+
+    /// True if the bar has been abandoned.
+    pub fn is_abandoned(&self) -> bool {
+        self.lifecycle == LifecycleState::Abandoned
+    }
+
+    /// When the bar was created, started, and finished, and how long it has been stalled.
+    pub fn timing(&self) -> BarTiming {
+        self.timing
+    }
+
+    /// Why the bar was abandoned, if it is known.
+    ///
+    /// This is the error passed to [`ProgressBar::abandon_with_error`](crate::ProgressBar::abandon_with_error),
+    /// or the panic message if the bar was dropped while its thread was panicking.
+    pub fn abandon_reason(&self) -> Option<&str> {
+        self.abandon_reason.as_deref()
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::input::{Command, Input};
+use crate::sync::Mutex;
+use crate::text::{display_width, truncate_to_width};
+use crate::{
+    config, format, output, scope, LifecycleState, ProgressBarState, RenderContext, MANAGER,
+};
+
+/// Maximum number of lines kept in the log pane
+const MAX_LOG_LINES: usize = 1000;
+/// Number of rows used if the size of the terminal is unknown
+const DEFAULT_ROWS: usize = 24;
+/// Refresh rate of the dashboard, in frames per second, until it is changed using the `+` and `-` keys
+const DEFAULT_REFRESH_RATE: f64 = 30.0;
+/// The refresh rate can be changed to at most this many frames per second
+const MAX_REFRESH_RATE: f64 = 120.0;
+/// The refresh rate can be changed to at least this many frames per second
+const MIN_REFRESH_RATE: f64 = 0.25;
+
+/// Used to tell apart the times fullscreen mode has been entered, see [`FullscreenGuard`]
+static NEXT_FULLSCREEN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// State of the dashboard displayed while in fullscreen mode, see [`enter_fullscreen`].
+pub(crate) struct Fullscreen {
+    /// Identifies the [`FullscreenGuard`] which entered fullscreen mode
+    id: u64,
+    /// Lines printed using [`print_line`], and finished bars, oldest first
+    log: VecDeque<String>,
+    /// Number of lines added to the log since fullscreen mode was entered, including lines which no longer fit in [`Self::log`]
+    logged: usize,
+    /// When fullscreen mode was entered
+    since: Instant,
+    /// Number of bars which have been finished while in fullscreen mode
+    finished: usize,
+    /// Number of bars which have been abandoned while in fullscreen mode
+    abandoned: usize,
+    /// Keys pressed by the user, if stdin is a terminal
+    input: Option<Input>,
+    /// True if the user asked to leave fullscreen mode
+    pub(crate) hidden: bool,
+    /// True if the dashboard should not be redrawn
+    paused: bool,
+    /// True if the dashboard has been drawn since it was paused
+    paused_drawn: bool,
+    /// Number of lines the list of bars has been scrolled down
+    scroll: usize,
+    /// Frames per second, if it has been changed by the user
+    refresh_rate: Option<f64>,
+}
+
+impl Fullscreen {
+    /// Applies the commands of the keys which have been pressed since this was last called.
+    pub(crate) fn receive_commands(&mut self) {
+        let Some(input) = &self.input else {
+            return;
+        };
+        for command in input.commands() {
+            match command {
+                Command::Hide => self.hidden = true,
+                Command::TogglePause => {
+                    self.paused = !self.paused;
+                    self.paused_drawn = false;
+                }
+                Command::Faster => {
+                    self.refresh_rate = Some((self.refresh_rate() * 2.0).min(MAX_REFRESH_RATE))
+                }
+                Command::Slower => {
+                    self.refresh_rate = Some((self.refresh_rate() / 2.0).max(MIN_REFRESH_RATE))
+                }
+                Command::Scroll(lines) => self.scroll = self.scroll.saturating_add_signed(lines),
+            }
+        }
+    }
+
+    /// Frames per second at which the dashboard is drawn.
+    fn refresh_rate(&self) -> f64 {
+        self.refresh_rate
+            .or(config::config().refresh_rate)
+            .unwrap_or(DEFAULT_REFRESH_RATE)
+    }
+
+    /// Time between frames, if the refresh rate has been changed by the user.
+    pub(crate) fn frame_period(&self) -> Option<Duration> {
+        self.refresh_rate
+            .map(|rate| Duration::from_secs_f64(1.0 / rate))
+    }
+
+    /// Hashes everything which affects the dashboard, other than the bars. See [`crate::ProgressBarManager::hash_state`].
+    pub(crate) fn hash_view(&self, state: &mut impl Hasher) {
+        self.logged.hash(state);
+        self.hidden.hash(state);
+        self.paused.hash(state);
+        self.scroll.hash(state);
+        self.refresh_rate.map(f64::to_bits).hash(state);
+    }
+
+    /// True if the dashboard is paused, and the paused dashboard has already been drawn.
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.paused && self.paused_drawn
+    }
+
+    /// Counts a top-level bar which is no longer displayed.
+    pub(crate) fn count(&mut self, lifecycle: LifecycleState) {
+        match lifecycle {
+            LifecycleState::Abandoned => self.abandoned += 1,
+            _ => self.finished += 1,
+        }
+    }
+
+    /// Adds lines to the log pane.
+    pub(crate) fn log(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.log.len() >= MAX_LOG_LINES {
+                self.log.pop_front();
+            }
+            self.log.push_back(line.to_string());
+            self.logged += 1;
+        }
+    }
+
+    /// Renders the dashboard as one string per row of the terminal.
+    pub(crate) fn render(
+        &mut self,
+        title: Option<&str>,
+        bars: &[Arc<Mutex<ProgressBarState>>],
+        ctx: &RenderContext,
+        size: Option<(usize, usize)>,
+        is_animating: &mut bool,
+    ) -> Result<Vec<String>, std::fmt::Error> {
+        let (cols, rows) = match size {
+            Some((cols, rows)) => (Some(cols), rows.max(1)),
+            None => (None, DEFAULT_ROWS),
+        };
+        let style = ctx.default_style;
+        let bold = |out: &mut String| {
+            if ctx.color {
+                out.push_str("\u{001b}[1m");
+            }
+        };
+        let reset = |out: &mut String| {
+            if ctx.color {
+                out.push_str("\u{001b}[0m");
+            }
+        };
+        let separator = |label: &str| {
+            let width = cols.unwrap_or(40);
+            let mut line = format!("── {} ", label);
+            let used = display_width(&line);
+            line.extend(std::iter::repeat_n('─', width.saturating_sub(used)));
+            line
+        };
+
+        // Header
+        let mut header = String::new();
+        bold(&mut header);
+        header.push_str(title.unwrap_or("Progress"));
+        reset(&mut header);
+        header.push_str("  ");
+        format::write_duration(
+            &mut header,
+            clock::since(self.since),
+            style.duration_format,
+            &style.labels,
+        )?;
+        write!(header, " {}", style.labels.elapsed)?;
+        if self.paused {
+            header.push_str("  (paused)");
+            self.paused_drawn = true;
+        }
+        if self.input.is_some() {
+            write!(
+                header,
+                "  q: hide · p: pause · +/-: {} fps · ↑↓: scroll",
+                self.refresh_rate()
+            )?;
+        }
+        let mut counts = String::new();
+        write!(
+            counts,
+            "{} {} · {} finished · {} {}",
+            bars.len(),
+            style.labels.in_progress,
+            self.finished,
+            self.abandoned,
+            style.labels.abandoned,
+        )?;
+
+        // All bars, with their children indented below them
+        let mut bar_lines = vec![];
+        for (label, bars) in scope::sections(bars) {
+            if let Some(label) = label {
+                let mut header = String::new();
+                bold(&mut header);
+                write!(header, "{}:", label)?;
+                reset(&mut header);
+                bar_lines.push(header);
+            }
+            for bar in bars {
+                render_tree(&bar.lock(), 0, ctx, is_animating, &mut bar_lines)?;
+            }
+        }
+
+        // The log takes up at most a third of the screen, and the bars get the rest
+        let log_rows = self.log.len().min(rows / 3);
+        let fixed_rows = 3 + if log_rows > 0 { 1 + log_rows } else { 0 };
+        let bar_rows = rows.saturating_sub(fixed_rows);
+
+        let mut screen = vec![header, counts, separator("Bars")];
+        let total = bar_lines.len();
+        // When scrolled down, one row is used to show how many bars are above
+        self.scroll = if total > bar_rows {
+            self.scroll.min(total + 1 - bar_rows)
+        } else {
+            0
+        };
+        let mut visible_rows = bar_rows;
+        if self.scroll > 0 {
+            screen.push(format!("↑ {} more", self.scroll));
+            visible_rows = visible_rows.saturating_sub(1);
+        }
+        if self.scroll + visible_rows < total {
+            visible_rows = visible_rows.saturating_sub(1);
+        }
+        let below = total - (self.scroll + visible_rows).min(total);
+        screen.extend(bar_lines.drain(..).skip(self.scroll).take(visible_rows));
+        if below > 0 {
+            screen.push(format!("↓ {} more", below));
+        }
+        if log_rows > 0 {
+            // Keep the log at the bottom of the screen
+            while screen.len() + 1 + log_rows < rows {
+                screen.push(String::new());
+            }
+            screen.push(separator("Log"));
+            screen.extend(self.log.iter().skip(self.log.len() - log_rows).cloned());
+        }
+        screen.truncate(rows);
+        if let Some(cols) = cols {
+            for line in &mut screen {
+                if let std::borrow::Cow::Owned(truncated) = truncate_to_width(line, cols) {
+                    *line = truncated;
+                }
+            }
+        }
+        Ok(screen)
+    }
+}
+
+/// Renders a bar and all of its children, followed by how long each of them has been running.
+fn render_tree(
+    bar: &ProgressBarState,
+    depth: usize,
+    ctx: &RenderContext,
+    is_animating: &mut bool,
+    lines: &mut Vec<String>,
+) -> std::fmt::Result {
+    let style = bar.style.as_deref().unwrap_or(ctx.default_style);
+    let mut line = "  ".repeat(depth);
+    bar.render(&mut line, ctx, is_animating)?;
+    if !style.show_elapsed && !style.show_eta {
+        line.push_str("  ");
+        format::write_duration(
+            &mut line,
+            bar.elapsed(),
+            style.duration_format,
+            &style.labels,
+        )?;
+        if let Some(eta) = bar.eta() {
+            line.push_str(", ");
+            format::write_duration(&mut line, eta, style.duration_format, &style.labels)?;
+            write!(line, " {}", style.labels.remaining)?;
+        }
+    }
+    lines.push(line);
+    if let Some(nested) = &bar.nested {
+        for child in &nested.bars {
+            render_tree(&child.lock(), depth + 1, ctx, is_animating, lines)?;
+        }
+    }
+    Ok(())
+}
+
+/// Turns the rows of the dashboard into a frame, which redraws the whole screen.
+pub(crate) fn frame(screen: &[String]) -> String {
+    let mut frame = String::from("\u{001b}[H");
+    for (i, line) in screen.iter().enumerate() {
+        if i > 0 {
+            frame.push_str("\r\n");
+        }
+        frame.push_str(line);
+        // Clear the rest of the line, in case the previous frame had a longer line here
+        frame.push_str("\u{001b}[K");
+    }
+    // Clear the rows below the dashboard
+    frame.push_str("\u{001b}[0J");
+    frame
+}
+
+impl crate::ProgressBarManager {
+    /// Restores the normal screen, and returns the text which should be written to do so. See [`enter_fullscreen`].
+    ///
+    /// The lines which were logged while in fullscreen mode are printed on the normal screen, so that they are not lost.
+    pub(crate) fn leave_fullscreen(&mut self) -> Option<String> {
+        let fullscreen = self.fullscreen.take()?;
+        // Restore the terminal settings before anything else is written
+        drop(fullscreen.input);
+        if self.capture.is_some() {
+            return Some(String::new());
+        }
+        // Show the cursor and leave the alternate screen
+        let mut text = String::from("\u{001b}[?25h\u{001b}[?1049l");
+        let skipped = fullscreen.logged - fullscreen.log.len();
+        if skipped > 0 {
+            let _ = writeln!(text, "… {} earlier lines", skipped);
+        }
+        for line in &fullscreen.log {
+            text.push_str(line);
+            text.push('\n');
+        }
+        Some(text)
+    }
+}
+
+/// Takes over the whole terminal to display a dashboard of all bars, until the returned guard is dropped.
+///
+/// The dashboard uses the alternate screen of the terminal, like full-screen programs such as `less` and `htop`.
+/// It shows a header with the [title](crate::set_title) and how many bars are running, finished and abandoned,
+/// followed by every bar and all of its nested bars, with how long each of them has been running and is estimated to take.
+/// At the bottom is a log of finished bars, and of lines printed using [`print_line`].
+///
+/// When the guard is dropped (or the program exits), the normal screen is restored exactly as it was,
+/// and the lines of the log are printed on it. This is a good fit for jobs which run for hours.
+///
+/// If stdin is a terminal, the dashboard can be controlled using the keyboard:
+///
+/// * `q` leaves fullscreen mode, and the bars are drawn as usual from then on. The program keeps running.
+/// * `p` pauses drawing the dashboard, so that it can be read or copied. Pressing `p` again resumes it.
+/// * `+` and `-` double and halve how often the dashboard is redrawn.
+/// * The up and down arrow keys scroll the list of bars, if it does not fit on the screen.
+///
+/// Keys are read by a background thread, and are not echoed while in fullscreen mode. Don't read from stdin at the same time.
+/// If the process is killed by a signal, for example by pressing Ctrl+C, the terminal is left in fullscreen mode,
+/// which can be fixed by running `reset`.
+///
+/// While in fullscreen mode, text printed to stdout using `println` is drawn over by the dashboard, so use [`print_line`] instead.
+/// Nothing happens if the output is not a terminal, or in accessible mode (see [`crate::set_accessible_mode`]).
+///
+/// ```no_run
+/// use headway::ProgressBar;
+///
+/// let dashboard = headway::enter_fullscreen();
+/// for (bar, shard) in ProgressBar::new().split_each(0..16) {
+///     bar.set_message(format!("shard {}", shard));
+///     for _ in bar.wrap(0..1000) {}
+///     headway::print_line(format!("shard {} done", shard));
+/// }
+/// drop(dashboard);
+/// ```
+pub fn enter_fullscreen() -> FullscreenGuard {
+    let mut manager = MANAGER.lock();
+    if !manager.interactive_output || manager.accessible || manager.fullscreen.is_some() {
+        return FullscreenGuard { id: None };
+    }
+    let id = NEXT_FULLSCREEN_ID.fetch_add(1, Ordering::Relaxed);
+    manager.fullscreen = Some(Fullscreen {
+        id,
+        input: Input::start(),
+        hidden: false,
+        paused: false,
+        paused_drawn: false,
+        scroll: 0,
+        refresh_rate: None,
+        log: VecDeque::new(),
+        logged: 0,
+        since: clock::now(),
+        finished: 0,
+        abandoned: 0,
+    });
+    if manager.capture.is_none() {
+        // Remove the bars from the normal screen, then switch to the alternate screen and hide the cursor
+        output::enqueue(
+            "\u{001b}[0J\u{001b}[?1049h\u{001b}[?25l",
+            Some((String::new(), 0)),
+        );
+    }
+    manager.ensure_thread_started();
+    let _ = manager.tick();
+    drop(manager);
+    output::write_pending();
+    FullscreenGuard { id: Some(id) }
+}
+
+/// Guard returned by [`enter_fullscreen`].
+///
+/// The normal screen is restored when this guard is dropped, unless the user has already left fullscreen mode by pressing `q`.
+pub struct FullscreenGuard {
+    /// The id of the [`Fullscreen`] this guard entered, if any
+    id: Option<u64>,
+}
+
+impl Drop for FullscreenGuard {
+    fn drop(&mut self) {
+        if self.id.is_none() {
+            return;
+        }
+        let mut manager = MANAGER.lock();
+        if manager.fullscreen.as_ref().map(|f| f.id) != self.id {
+            return;
+        }
+        if let Some(restore) = manager.leave_fullscreen() {
+            // Replace any dashboard which has not been written yet
+            output::enqueue(&restore, Some((String::new(), 0)));
+            let _ = manager.tick();
+            drop(manager);
+            output::write_pending();
+        }
+    }
+}
+
+/// Prints a line of text above the bars.
+///
+/// This works like `println`, except that in fullscreen mode (see [`enter_fullscreen`]) the line is added to the log at the bottom of the dashboard instead.
+///
+/// ```
+/// headway::print_line("Connected to the database");
+/// ```
+pub fn print_line(text: impl Into<String>) {
+    let text = text.into();
+    let mut manager = MANAGER.lock();
+    if let Some(fullscreen) = &mut manager.fullscreen {
+        fullscreen.log(&text);
+        return;
+    }
+    drop(manager);
+    println!("{}", text);
+}
@@ -0,0 +1,253 @@
+//! Helpers for common ways of using progress bars.
+
+use std::future::Future;
+use std::task::Poll;
+
+use crate::{Color, ProgressBar, ProgressBarIterator};
+
+impl ProgressBar {
+    /// Collects all items of an iterator while counting them, and then shows the progress of processing them.
+    ///
+    /// A very common pattern is to first discover some work to do, for example by walking a directory tree,
+    /// and then process all the discovered items. While discovering items the total amount is not known, so the bar is displayed
+    /// as indeterminate together with the number of items found so far. Once all items have been found, the bar
+    /// switches to showing the progress of processing them.
+    ///
+    /// Note that the whole iterator is consumed before this function returns.
+    ///
+    /// See also [`crate::scan_then_process`].
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let files = std::fs::read_dir(".").unwrap().flatten();
+    /// let p = ProgressBar::new().with_message("Indexing");
+    /// for file in p.scan_then_process(files) {
+    ///     // Process the file...
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn scan_then_process<It: Iterator>(
+        self,
+        it: It,
+    ) -> ProgressBarIterator<std::vec::IntoIter<It::Item>> {
+        self.set_phase_message(self.labels().scanning);
+        let items = it.inspect(|_| self.inc()).collect::<Vec<_>>();
+        self.set_phase_message("");
+        self.set_position(0);
+        self.wrap(items.into_iter())
+    }
+
+    /// Runs an operation which may fail up to `attempts` times, showing the progress of each attempt.
+    ///
+    /// The bar is split into one part per attempt (see [`Self::split_sized`]), and the current attempt is displayed next to the message (`attempt 2/5`).
+    /// Failed attempts are marked as abandoned, and if an attempt succeeds, the bar is colored green.
+    ///
+    /// The closure receives a bar for the current attempt. The bar has a length of 1 by default, but you can change it to report more detailed progress.
+    /// The bar is automatically finished or abandoned depending on the result of the closure.
+    ///
+    /// Returns the result of the first successful attempt, or the error of the last attempt if all of them failed.
+    ///
+    /// See also [`crate::retry_with_progress`].
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_message("Connecting");
+    /// let result = p.retry_with_progress(5, |attempt_bar| {
+    ///     attempt_bar.set_length(10);
+    ///     for _ in 0..10 {
+    ///         attempt_bar.inc();
+    ///     }
+    ///     Ok::<_, std::io::Error>("connected")
+    /// });
+    /// assert_eq!(result.unwrap(), "connected");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attempts` is zero.
+    pub fn retry_with_progress<T, E>(
+        self,
+        attempts: usize,
+        mut f: impl FnMut(&ProgressBar) -> Result<T, E>,
+    ) -> Result<T, E> {
+        assert!(attempts > 0, "attempts must be at least 1");
+        let label = self.labels().attempt;
+        let mut nester = self.split_sized();
+        let mut attempt = 1;
+        loop {
+            let mut bar = nester.take(1);
+            bar.set_phase_message(format!("{} {}/{}", label, attempt, attempts));
+            match f(&bar) {
+                Ok(value) => {
+                    nester.bar.set_color(Color::Green);
+                    bar.finish();
+                    return Ok(value);
+                }
+                Err(err) => {
+                    bar.abandon();
+                    if attempt == attempts {
+                        return Err(err);
+                    }
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Awaits all futures concurrently, counting each future which completes as one unit of progress, like `3/10`.
+    ///
+    /// This is useful for fanning out to many tasks whose internals don't report any progress. The bar is split using
+    /// [`Self::split_summed`], with one child of length 1 per future. The futures are polled on the current task,
+    /// like with `futures::future::join_all`, so to run them in parallel, pass in the join handles of spawned tasks instead.
+    ///
+    /// Returns the outputs of the futures, in the same order as the futures. If the returned future is dropped before it completes,
+    /// the futures which have not completed yet are marked as abandoned.
+    ///
+    /// See also [`crate::join_with_progress`].
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use std::time::Duration;
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let p = ProgressBar::new().with_message("Fetching");
+    /// let pages = (0..10).map(|i| async move {
+    ///     tokio::time::sleep(Duration::from_millis(10 * i)).await;
+    ///     format!("page {}", i)
+    /// });
+    /// let pages = p.join_with_progress(pages).await;
+    /// assert_eq!(pages[3], "page 3");
+    /// # });
+    /// ```
+    pub async fn join_with_progress<F: Future>(
+        self,
+        futures: impl IntoIterator<Item = F>,
+    ) -> Vec<F::Output> {
+        let nester = self.split_summed();
+        let mut tasks = futures
+            .into_iter()
+            .map(|future| (Box::pin(future), Some(nester.take().with_length(1))))
+            .collect::<Vec<_>>();
+        let mut outputs = tasks.iter().map(|_| None).collect::<Vec<_>>();
+        let mut remaining = tasks.len();
+        std::future::poll_fn(|cx| {
+            for ((future, bar), output) in tasks.iter_mut().zip(&mut outputs) {
+                // Completed futures must not be polled again
+                if bar.is_none() {
+                    continue;
+                }
+                if let Poll::Ready(value) = future.as_mut().poll(cx) {
+                    *output = Some(value);
+                    bar.take().unwrap().finish();
+                    remaining -= 1;
+                }
+            }
+            if remaining == 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        outputs.into_iter().flatten().collect()
+    }
+
+    /// Runs a closure which does not report any progress, showing an indeterminate bar while it runs.
+    ///
+    /// The bar is finished when the closure returns. If the closure panics, the bar is abandoned instead, with the panic message as the reason.
+    ///
+    /// See also [`crate::blocking`].
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let sum = ProgressBar::new()
+    ///     .with_prefix("Resolving")
+    ///     .blocking(|| (0..1_000_000u64).sum::<u64>());
+    /// assert_eq!(sum, 499_999_500_000);
+    /// ```
+    pub fn blocking<R>(self, f: impl FnOnce() -> R) -> R {
+        let mut bar = self;
+        // If the closure panics, the bar is abandoned when it is dropped during unwinding
+        let result = f();
+        bar.finish();
+        result
+    }
+}
+
+/// Collects all items of an iterator while counting them, and then shows the progress of processing them.
+///
+/// Equivalent to `ProgressBar::new().scan_then_process(it)`. See [`ProgressBar::scan_then_process`].
+///
+/// ```
+/// let entries = std::fs::read_dir(".").unwrap().flatten();
+/// for entry in headway::scan_then_process(entries) {
+///     // Process the entry...
+/// }
+/// ```
+#[track_caller]
+pub fn scan_then_process<It: IntoIterator>(
+    it: It,
+) -> ProgressBarIterator<std::vec::IntoIter<It::Item>> {
+    ProgressBar::new().scan_then_process(it.into_iter())
+}
+
+/// Runs an operation which may fail up to `attempts` times, showing the progress of each attempt.
+///
+/// Equivalent to `ProgressBar::new().retry_with_progress(attempts, f)`. See [`ProgressBar::retry_with_progress`].
+///
+/// ```
+/// let mut failures = 0;
+/// let result = headway::retry_with_progress(3, |_attempt_bar| {
+///     if failures < 2 {
+///         failures += 1;
+///         Err("connection refused")
+///     } else {
+///         Ok(42)
+///     }
+/// });
+/// assert_eq!(result, Ok(42));
+/// ```
+#[track_caller]
+pub fn retry_with_progress<T, E>(
+    attempts: usize,
+    f: impl FnMut(&ProgressBar) -> Result<T, E>,
+) -> Result<T, E> {
+    ProgressBar::new().retry_with_progress(attempts, f)
+}
+
+/// Awaits all futures concurrently, counting each future which completes as one unit of progress.
+///
+/// Equivalent to `ProgressBar::new().join_with_progress(futures)`. See [`ProgressBar::join_with_progress`].
+///
+/// ```
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let handles = (0..4).map(|i| tokio::spawn(async move { i * i }));
+/// let squares = headway::join_with_progress(handles).await;
+/// assert_eq!(squares.len(), 4);
+/// # });
+/// ```
+#[track_caller]
+pub fn join_with_progress<F: Future>(
+    futures: impl IntoIterator<Item = F>,
+) -> impl Future<Output = Vec<F::Output>> {
+    // Not an async fn, since the bar would then be created when the future is first polled, and the location of the caller would be lost
+    ProgressBar::new().join_with_progress(futures)
+}
+
+/// Runs a closure which does not report any progress, showing an indeterminate bar with the given message while it runs.
+///
+/// Equivalent to `ProgressBar::new().with_message(message).blocking(f)`. See [`ProgressBar::blocking`].
+///
+/// ```
+/// let graph = headway::blocking("resolving dependencies", || {
+///     // An expensive operation...
+///     vec!["serde", "rand"]
+/// });
+/// ```
+#[track_caller]
+pub fn blocking<R>(message: impl Into<String>, f: impl FnOnce() -> R) -> R {
+    ProgressBar::new().with_message(message).blocking(f)
+}
@@ -0,0 +1,79 @@
+//! Widgets for displaying progress bars in [iced](https://docs.rs/iced), enabled by the `iced` feature.
+//!
+//! The same progress reporting code can then drive both a terminal and a desktop app.
+//! Use [`crate::on_frame`] to receive the state of all bars from the background thread, and display the latest frame in the view:
+//!
+//! ```no_run
+//! use std::sync::{Arc, Mutex};
+//! use headway::RenderedFrame;
+//! use iced_widget::core::Element;
+//!
+//! struct App {
+//!     latest: Arc<Mutex<Option<RenderedFrame>>>,
+//! }
+//!
+//! impl App {
+//!     fn new() -> Self {
+//!         let latest: Arc<Mutex<Option<RenderedFrame>>> = Default::default();
+//!         let l = latest.clone();
+//!         headway::on_frame(move |frame| *l.lock().unwrap() = Some(frame.clone()));
+//!         Self { latest }
+//!     }
+//!
+//!     fn view<Message: 'static>(&self) -> Element<'_, Message, iced_widget::Theme, iced_widget::Renderer> {
+//!         match &*self.latest.lock().unwrap() {
+//!             Some(frame) => headway::iced::frame(frame),
+//!             None => iced_widget::text("Idle").into(),
+//!         }
+//!     }
+//! }
+//! ```
+
+use ::iced_widget::core::text::Renderer as TextRenderer;
+use ::iced_widget::core::{Element, Padding};
+use ::iced_widget::{progress_bar, text, Column, Theme};
+
+use crate::{RenderedBar, RenderedFrame};
+
+/// Spacing between bars, and the indentation of nested bars, in pixels
+const SPACING: f32 = 4.0;
+const INDENT: f32 = 16.0;
+
+/// A column containing all bars in a frame, including nested bars. See the [module documentation](self).
+pub fn frame<'a, Message: 'a, Renderer: TextRenderer + 'a>(
+    frame: &RenderedFrame,
+) -> Element<'a, Message, Theme, Renderer> {
+    let mut column = Column::new().spacing(SPACING);
+    if let Some(title) = frame.title() {
+        column = column.push(text(title.to_owned()));
+    }
+    for b in frame.bars() {
+        column = column.push(bar(b));
+    }
+    column.into()
+}
+
+/// A column containing a bar with its description above it, and its nested bars indented below it.
+///
+/// To display a tree of [`BarNode`](crate::core::BarNode)s, render it using [`Renderer::render_tree`](crate::core::Renderer::render_tree) first.
+pub fn bar<'a, Message: 'a, Renderer: TextRenderer + 'a>(
+    bar: &RenderedBar,
+) -> Element<'a, Message, Theme, Renderer> {
+    let mut column = Column::new()
+        .spacing(SPACING)
+        .push(text(bar.plain_text().to_owned()))
+        .push(progress_bar(
+            0.0..=1.0,
+            bar.progress().unwrap_or(0.0) as f32,
+        ));
+    if !bar.children().is_empty() {
+        let mut children = Column::new()
+            .spacing(SPACING)
+            .padding(Padding::ZERO.left(INDENT));
+        for child in bar.children() {
+            children = children.push(self::bar(child));
+        }
+        column = column.push(children);
+    }
+    column.into()
+}
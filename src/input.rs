@@ -0,0 +1,153 @@
+//! Keyboard input for the fullscreen dashboard, see [`enter_fullscreen`](crate::enter_fullscreen).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+/// An action requested by pressing a key while the dashboard is displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Leave fullscreen mode, and display the bars as usual (`q`)
+    Hide,
+    /// Stop or resume redrawing the dashboard (`p`)
+    TogglePause,
+    /// Double the refresh rate (`+`)
+    Faster,
+    /// Halve the refresh rate (`-`)
+    Slower,
+    /// Scroll the list of bars by the given number of lines (arrow keys)
+    Scroll(isize),
+}
+
+/// Reads keys from stdin on a separate thread, and sends them to the manager as [`Command`]s.
+///
+/// While this is alive, the terminal is in non-canonical mode, so that keys are received as soon as they are pressed, and are not echoed.
+/// The terminal is restored when this is dropped.
+pub(crate) struct Input {
+    commands: mpsc::Receiver<Command>,
+    /// Tells the input thread to stop
+    stop: Arc<AtomicBool>,
+    /// The terminal settings from before input was started
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+impl Input {
+    /// Starts reading keys, if stdin is a terminal.
+    #[cfg(unix)]
+    pub(crate) fn start() -> Option<Input> {
+        use is_terminal::IsTerminal;
+
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        // SAFETY: termios is a plain C struct, which is filled in by tcgetattr
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        // SAFETY: The pointer is valid for the duration of the call
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return None;
+        }
+        let mut raw = original;
+        // Signals like Ctrl+C keep working, since ISIG is left as it is
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: The pointer is valid for the duration of the call
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return None;
+        }
+
+        let (sender, commands) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_thread = stop.clone();
+        std::thread::spawn(move || read_keys(sender, &stop_thread));
+        Some(Input {
+            commands,
+            stop,
+            original,
+        })
+    }
+
+    /// Keys can only be read on unix platforms.
+    #[cfg(not(unix))]
+    pub(crate) fn start() -> Option<Input> {
+        None
+    }
+
+    /// The commands which have been received since this was last called.
+    pub(crate) fn commands(&self) -> mpsc::TryIter<'_, Command> {
+        self.commands.try_iter()
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // SAFETY: The pointer is valid for the duration of the call
+        #[cfg(unix)]
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Reads keys until [`Input`] is dropped.
+///
+/// Stdin is polled with a timeout, so that the thread notices when it should stop even if no key is pressed.
+#[cfg(unix)]
+fn read_keys(sender: mpsc::Sender<Command>, stop: &AtomicBool) {
+    let mut buffer = [0u8; 64];
+    while !stop.load(Ordering::Relaxed) {
+        let mut poll = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: The pointer is valid for the duration of the call
+        let ready = unsafe { libc::poll(&mut poll, 1, 100) };
+        if ready <= 0 || stop.load(Ordering::Relaxed) {
+            continue;
+        }
+        // SAFETY: The buffer is valid for writes of its length
+        let read =
+            unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read <= 0 {
+            // Stdin was closed
+            return;
+        }
+        for command in parse_keys(&buffer[..read as usize]) {
+            if sender.send(command).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Translates the bytes of key presses to commands. Other keys are ignored.
+#[cfg(unix)]
+fn parse_keys(mut bytes: &[u8]) -> Vec<Command> {
+    let mut commands = vec![];
+    while let Some((&byte, rest)) = bytes.split_first() {
+        bytes = rest;
+        let command = match byte {
+            b'q' | b'Q' => Command::Hide,
+            b'p' | b'P' | b' ' => Command::TogglePause,
+            b'+' | b'=' => Command::Faster,
+            b'-' | b'_' => Command::Slower,
+            // Arrow keys are sent as `ESC [ A` (or `ESC O A` in application cursor mode)
+            0x1b => match bytes {
+                [b'[' | b'O', key, rest @ ..] => {
+                    bytes = rest;
+                    match key {
+                        b'A' => Command::Scroll(-1),
+                        b'B' => Command::Scroll(1),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+        commands.push(command);
+    }
+    commands
+}
@@ -0,0 +1,47 @@
+use crate::{text, ProgressBarManager, MANAGER};
+
+/// Prefix of every line written in journal mode, so that progress can be filtered with e.g. `journalctl -g '^progress:'`
+const LINE_PREFIX: &str = "progress: ";
+
+/// Occasionally prints the status of the bars as plain text when stdout is not a terminal, for services whose output ends up in a log.
+///
+/// By default, bars which are not drawn in a terminal are only printed once they are finished, which makes long running tasks
+/// look like they have hung. In journal mode, the status of all bars is instead printed at most every 10 seconds (and only if it has changed),
+/// and finished and abandoned bars are printed once, like
+///
+/// ```text
+/// progress: Downloading 45% (45/100): assets.tar.gz
+/// ```
+///
+/// Every line starts with `progress: ` and contains no control characters or escape codes, so that it can be read and filtered
+/// by tools like `journalctl` and syslog.
+/// When stdout is a terminal, the bars are drawn as usual.
+///
+/// Journal mode is enabled by default if the `JOURNAL_STREAM` environment variable is set, which systemd does for services whose output goes to the journal.
+///
+/// ```
+/// headway::set_journal_output(true);
+/// ```
+pub fn set_journal_output(enabled: bool) {
+    MANAGER.lock().journal_output = enabled;
+}
+
+impl ProgressBarManager {
+    /// Prints the given lines of finished bars, and occasionally the status of the other bars. See [`set_journal_output`].
+    pub(crate) fn journal_report(&mut self, finished: &str) -> std::fmt::Result {
+        let mut out = String::new();
+        let report = self.plain_report()?;
+        for line in finished
+            .lines()
+            .chain(report.iter().flat_map(|r| r.lines()))
+        {
+            out.push_str(LINE_PREFIX);
+            out.push_str(&text::strip_control(line));
+            out.push('\n');
+        }
+        if !out.is_empty() {
+            crate::output::enqueue(&out, None);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{ProgressBar, MANAGER};
+
+/// The words displayed next to progress bars.
+///
+/// By default all labels are in English. Applications which are translated to other languages can replace them,
+/// and use them by setting a style with [`Style::with_labels`](crate::Style::with_labels).
+///
+/// ```
+/// use headway::{Labels, ProgressBar, Style};
+///
+/// let swedish = Labels::new()
+///     .with_queued("i kö")
+///     .with_stalled("har stannat")
+///     .with_abandoned("avbruten")
+///     .with_in_progress("pågår");
+/// headway::set_default_style(Style::new().with_labels(swedish));
+///
+/// let p = ProgressBar::new().with_length(100);
+/// p.set_backlog(12);
+/// ```
+///
+/// With these labels the bar renders as
+///
+/// ```text
+/// ▕                    ▏ 0/100 (+12 i kö)
+/// ```
+#[derive(Clone, Debug)]
+pub struct Labels {
+    pub(crate) queued: String,
+    pub(crate) added: String,
+    pub(crate) redone: String,
+    pub(crate) stalled: String,
+    pub(crate) abandoned: String,
+    pub(crate) failed: String,
+    pub(crate) in_progress: String,
+    pub(crate) scanning: String,
+    pub(crate) attempt: String,
+    pub(crate) hours: String,
+    pub(crate) minutes: String,
+    pub(crate) seconds: String,
+    pub(crate) elapsed: String,
+    pub(crate) remaining: String,
+    pub(crate) active: String,
+    pub(crate) out_of: String,
+    pub(crate) about: String,
+    pub(crate) per_second: String,
+    pub(crate) more: String,
+    /// Singular and plural forms of seconds, minutes and hours
+    pub(crate) humanized_units: [[String; 2]; 3],
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Labels {
+    /// The default, English, labels.
+    pub fn new() -> Self {
+        Self {
+            queued: "queued".to_string(),
+            added: "added".to_string(),
+            redone: "redone".to_string(),
+            stalled: "stalled".to_string(),
+            abandoned: "abandoned".to_string(),
+            failed: "failed".to_string(),
+            in_progress: "in progress".to_string(),
+            scanning: "scanning".to_string(),
+            attempt: "attempt".to_string(),
+            hours: "h".to_string(),
+            minutes: "m".to_string(),
+            seconds: "s".to_string(),
+            elapsed: "elapsed".to_string(),
+            remaining: "left".to_string(),
+            active: "active".to_string(),
+            out_of: "of".to_string(),
+            about: "about".to_string(),
+            per_second: "it/s".to_string(),
+            more: "more".to_string(),
+            humanized_units: [
+                ["second".to_string(), "seconds".to_string()],
+                ["minute".to_string(), "minutes".to_string()],
+                ["hour".to_string(), "hours".to_string()],
+            ],
+        }
+    }
+
+    /// Displayed after the number of queued items, like `(+12 queued)`. See [`ProgressBar::set_backlog`].
+    pub fn with_queued(mut self, label: impl Into<String>) -> Self {
+        self.queued = label.into();
+        self
+    }
+
+    /// Displayed after the number of items added to a bar, like `(+20 added)`. See [`Style::with_length_growth`](crate::Style::with_length_growth).
+    pub fn with_added(mut self, label: impl Into<String>) -> Self {
+        self.added = label.into();
+        self
+    }
+
+    /// Displayed after the number of items which were redone, like `(↺ 20 redone)`. See [`ProgressBar::rewind`].
+    pub fn with_redone(mut self, label: impl Into<String>) -> Self {
+        self.redone = label.into();
+        self
+    }
+
+    /// Displayed before the time a bar has been stalled, like `stalled 45s`. See [`Style::with_stall_threshold`](crate::Style::with_stall_threshold).
+    pub fn with_stalled(mut self, label: impl Into<String>) -> Self {
+        self.stalled = label.into();
+        self
+    }
+
+    /// Displayed for abandoned bars in plain text output, like in accessible mode. See [`crate::set_accessible_mode`].
+    pub fn with_abandoned(mut self, label: impl Into<String>) -> Self {
+        self.abandoned = label.into();
+        self
+    }
+
+    /// Displayed after the number of abandoned children of a bar, like `(3 failed)`. See [`ProgressBar::split_summed`].
+    pub fn with_failed(mut self, label: impl Into<String>) -> Self {
+        self.failed = label.into();
+        self
+    }
+
+    /// Displayed instead of a percentage for bars without a length in plain text output.
+    pub fn with_in_progress(mut self, label: impl Into<String>) -> Self {
+        self.in_progress = label.into();
+        self
+    }
+
+    /// Phase message used while items are being collected. See [`ProgressBar::scan_then_process`].
+    pub fn with_scanning(mut self, label: impl Into<String>) -> Self {
+        self.scanning = label.into();
+        self
+    }
+
+    /// Displayed before the attempt number, like `attempt 2/5`. See [`ProgressBar::retry_with_progress`].
+    pub fn with_attempt(mut self, label: impl Into<String>) -> Self {
+        self.attempt = label.into();
+        self
+    }
+
+    /// Displayed after the time a bar has been running, like `1m12s elapsed`. See [`Style::with_elapsed`](crate::Style::with_elapsed).
+    pub fn with_elapsed(mut self, label: impl Into<String>) -> Self {
+        self.elapsed = label.into();
+        self
+    }
+
+    /// Displayed after the estimated remaining time, like `3m12s left`. See [`Style::with_eta`](crate::Style::with_eta).
+    pub fn with_remaining(mut self, label: impl Into<String>) -> Self {
+        self.remaining = label.into();
+        self
+    }
+
+    /// Displayed after the rate of progress, like `1.2k it/s`. See [`Style::with_rate`](crate::Style::with_rate).
+    pub fn with_per_second(mut self, label: impl Into<String>) -> Self {
+        self.per_second = label.into();
+        self
+    }
+
+    /// Displayed after the number of bars which did not fit on the screen, like `… 12 more`. See [`set_max_lines`](crate::set_max_lines).
+    pub fn with_more(mut self, label: impl Into<String>) -> Self {
+        self.more = label.into();
+        self
+    }
+
+    /// Displayed before the fraction of the time a bar was making progress, like `active 80% of 5m`. See [`Style::with_activity`](crate::Style::with_activity).
+    pub fn with_active(mut self, label: impl Into<String>) -> Self {
+        self.active = label.into();
+        self
+    }
+
+    /// Displayed before the total time in `active 80% of 5m`. See [`Style::with_activity`](crate::Style::with_activity).
+    pub fn with_out_of(mut self, label: impl Into<String>) -> Self {
+        self.out_of = label.into();
+        self
+    }
+
+    /// Sets the words used for [`DurationFormat::Humanized`](crate::DurationFormat::Humanized), like `about 3 minutes`.
+    ///
+    /// Each unit is given as a singular and a plural form.
+    ///
+    /// ```
+    /// use headway::Labels;
+    ///
+    /// let labels = Labels::new().with_humanized_units(
+    ///     "ungefär",
+    ///     ["sekund", "sekunder"],
+    ///     ["minut", "minuter"],
+    ///     ["timme", "timmar"],
+    /// );
+    /// ```
+    pub fn with_humanized_units(
+        mut self,
+        about: impl Into<String>,
+        seconds: [&str; 2],
+        minutes: [&str; 2],
+        hours: [&str; 2],
+    ) -> Self {
+        self.about = about.into();
+        self.humanized_units = [seconds, minutes, hours].map(|unit| unit.map(str::to_string));
+        self
+    }
+
+    /// Sets the units used when displaying durations in the compact format, like `3m12s`.
+    pub fn with_duration_units(
+        mut self,
+        hours: impl Into<String>,
+        minutes: impl Into<String>,
+        seconds: impl Into<String>,
+    ) -> Self {
+        self.hours = hours.into();
+        self.minutes = minutes.into();
+        self.seconds = seconds.into();
+        self
+    }
+}
+
+impl ProgressBar {
+    /// The labels used by this bar, taking the default style into account.
+    pub(crate) fn labels(&self) -> Labels {
+        let style = self.state.as_ref().and_then(|s| s.lock().style.clone());
+        match style {
+            Some(style) => style.labels.clone(),
+            None => MANAGER.lock().default_style.labels.clone(),
+        }
+    }
+}
@@ -114,10 +114,15 @@
 //! Printing to `stderr` has the potential to mess things up. However, if you flush `stdout` before you print to `stderr` then things should work properly.
 //! If a child process prints to `stdout`, this also has the potential to mess things up.
 //!
+//! Bars can be drawn to `stderr` instead, by calling [`init_with`] with [`Target::Stderr`] before the first bar is created.
+//! Printing to `stdout` is then no longer coordinated with the bars.
+//! Any other writer can be used as well, see [`DrawTarget`].
+//!
 //! ## Abandoning bars
 //!
 //! If you abandon a bar without finishing it (for example because a worker thread crashed), then the bar
 //! will draw angry red marks to draw your attention. You can also explicitly abandon a bar using [`ProgressBar::abandon`].
+//! A panic in one thread never affects bars in other threads, they will keep rendering as usual.
 //!
 //! ```should_panic
 //! use headway::ProgressBarIterable;
@@ -151,65 +156,332 @@
 //! ```
 #![doc=include_str!("../images/indeterminate.html")]
 //!
+//! ## Accessibility
+//!
+//! Rapidly redrawn progress bars are very noisy for users of e.g. screen readers.
+//! Setting the `HEADWAY_ACCESSIBLE` environment variable (or calling [`set_accessible_mode`]) disables all animations
+//! and instead occasionally prints the progress as plain text.
+//! Applications which want to present the progress in some other way, like on a braille display, can use [`on_frame`].
+//!
+//! Colors are disabled if the `NO_COLOR` environment variable is set, or if `CLICOLOR` is set to `0`.
+//! Setting `CLICOLOR_FORCE` to something other than `0` enables colors even when the output is not a terminal.
+//!
+//! When the output is not a terminal, bars are only printed once they are finished (unless [`force_interactive`] is used). Services running under systemd
+//! instead occasionally print the status of all bars as plain text, so that long running tasks show up in the journal. See [`set_journal_output`].
+//!
+//! ## Threading model
+//!
+//! All types in this crate are [`Send`] and [`Sync`] (iterators are if the wrapped iterator is).
+//! Every method that updates a bar takes `&self`, so a single bar can be shared between threads, either by reference
+//! or wrapped in an [`Arc`](std::sync::Arc). Only finishing or abandoning a bar requires ownership (or `&mut`).
+//!
+//! Output is written to the terminal by a background thread. Updating a bar never waits for the terminal, even if it is slow
+//! (for example over SSH). If the terminal cannot keep up, intermediate frames are skipped. Finishing or abandoning a bar
+//! waits until its final state has been written, so that it is never lost when the program exits.
+//! Bars which are still in progress when the program exits (for example because they are owned by detached threads)
+//! are drawn a final time as the program exits, so that the terminal is left in a consistent state.
+//!
+//! Each bar has a lock, which is taken briefly by every update. With the `parking_lot` feature enabled,
+//! the locks from [parking_lot](https://docs.rs/parking_lot) are used instead of the ones from the standard library.
+//!
+//! ```
+//! use headway::ProgressBar;
+//!
+//! let p = ProgressBar::new().with_length(400);
+//! std::thread::scope(|s| {
+//!     for _ in 0..4 {
+//!         s.spawn(|| {
+//!             for _ in 0..100 {
+//!                 p.inc();
+//!             }
+//!         });
+//!     }
+//! });
+//! assert_eq!(p.position(), 400);
+//! ```
+//!
+//! Nesters can also be moved to, or shared with, other threads. For example, [`ProgressBarSummedNester::take`] only requires `&self`,
+//! so worker threads can create their own child bars.
+//!
+//! ```
+//! use headway::ProgressBar;
+//!
+//! let nester = ProgressBar::new().split_summed();
+//! std::thread::scope(|s| {
+//!     for _ in 0..4 {
+//!         s.spawn(|| {
+//!             for _ in nester.take().wrap(0..100) {}
+//!         });
+//!     }
+//! });
+//! ```
+//!
+//! Iterators wrapping types which cannot be sent between threads can of course not be sent between threads either.
+//!
+//! ```compile_fail
+//! use headway::ProgressBarIterable;
+//! use std::rc::Rc;
+//!
+//! let it = vec![Rc::new(1)].into_iter().progress();
+//! std::thread::spawn(move || for _ in it {});
+//! ```
+//!
+//! ## Testing
+//!
+//! Use [`test_capture`] to prevent progress bars from writing to stdout during unit tests, and to inspect what would have been drawn.
+//! To test the output exactly as it would be drawn in a terminal, use a [`testing::MemoryTarget`].
+//! Layouts which depend on the size of the terminal can be made deterministic using [`set_terminal_size_override`],
+//! and animations and times using [`set_clock`].
+//!
 //! ## Styling
 //!
-//! It is currently not possible to style bars in any way.
+//! Bars can be styled using a [`Style`], either for individual bars using [`ProgressBar::set_style`] or for all bars using [`set_default_style`].
+//! There are several presets to choose from, like [`Style::ascii`], [`Style::minimal`] and [`Style::cargo`].
+//! The words displayed next to bars can be translated using [`Labels`].
+//! The text after a bar, like `42/100`, can be replaced entirely using [`ProgressBar::set_formatter`].
+//! The whole line can also be laid out using a template like `"{prefix} {bar:30} {pos}/{len} {eta} {msg}"`, see [`Style::with_template`].
+//!
+//! ```
+//! use headway::{ProgressBar, Style};
+//!
+//! headway::set_default_style(Style::cargo());
+//! let p = ProgressBar::new().with_prefix("Compiling").with_message("headway v0.1.2");
+//! ```
+//!
+//! ## Interoperability
+//!
+//! With the `prodash` feature enabled, [`ProgressBar`] implements the progress traits from [prodash](https://docs.rs/prodash),
+//! which are used by for example [gitoxide](https://docs.rs/gix). Progress reported by such libraries is then displayed like any other bar.
+//! Children added by the library are displayed as nested bars, and are marked as finished when the library drops them.
+//!
+//! With the `tokio` feature enabled, [`ProgressBar::watch`] returns a [tokio watch channel](https://docs.rs/tokio/latest/tokio/sync/watch/index.html)
+//! which receives the progress of the bar. This can be used to drive a progress widget in a GUI from the same code that reports progress in a terminal.
+//! Bars can also be linked to a [`CancellationToken`](https://docs.rs/tokio-util/latest/tokio_util/sync/struct.CancellationToken.html) using [`ProgressBar::link_cancellation_token`],
+//! so that cancelling a task abandons its bar, and cancelling the bar cancels the task.
+//! To display whole trees of bars in a GUI, the `egui` and `iced` features enable adapters in the `headway::egui` and `headway::iced` modules.
+//!
+//! For building custom progress trees and renderers, the building blocks behind [`ProgressBar`] are available in the [`core`] module.
+//!
+//! With the `rayon` feature enabled, [`ProgressBar::split_each_par`] splits a bar into one nested bar for each item of a [rayon](https://docs.rs/rayon) parallel iterator.
+//!
+//! With the `log` feature enabled, [`set_log_output`] sends the status of the bars to the [log](https://docs.rs/log) crate when the output is not a terminal,
+//! so that it follows the log filtering and formatting of the application.
+//!
+//! With the `serde` feature enabled, the [`BarTiming`] of each bar in a [`RenderedFrame`] can be serialized, for example to feed the duration of each phase of a build into analytics.
 //!
 //! ## Alternative crates
 //!
 //! * [Indicatif](https://docs.rs/indicatif/latest/indicatif/) - A crate which supports progress bars and spinners and lots of styling.
 //!    However it is less ergonomic, especially when working with multiple progress bars. It also interacts poorly with simultaneous printing to stdout.
 
-use lazy_static::lazy_static;
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Write;
 use std::hash::{Hash, Hasher};
 use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
+mod capture;
+mod changes;
+mod clock;
+mod config;
+pub mod core;
+mod created;
+#[cfg(feature = "egui")]
+pub mod egui;
+mod format;
+mod frame;
+mod fullscreen;
+mod helpers;
+#[cfg(feature = "iced")]
+pub mod iced;
+mod input;
+mod journal;
+mod labels;
+#[cfg(feature = "log")]
+mod logging;
+mod output;
+mod panic;
+#[cfg(feature = "prodash")]
+mod prodash;
+mod profile;
 mod progressbar;
+#[cfg(feature = "rayon")]
+mod rayon;
+mod scope;
+mod selftest;
+mod sink;
 mod splitting;
-pub use progressbar::{ProgressBar, ProgressBarIterable, ProgressBarIterator};
-use is_terminal::IsTerminal;
+mod status;
+mod style;
+mod sync;
+mod template;
+mod terminal;
+pub mod testing;
+mod text;
+mod theme;
+#[cfg(feature = "tokio")]
+mod tokio;
+mod trace;
+mod watch;
+mod watchdog;
+pub use capture::{test_capture, CaptureGuard};
+pub use changes::{on_change, BarChange, ChangeStream};
+pub use clock::{set_clock, Clock, ManualClock};
+pub use config::{init_with, Config, DrawTarget, Target};
+pub use created::{on_bar_created, BarCreated};
+pub use format::{CountFormat, DurationFormat, PlainFormat};
+pub use frame::{on_frame, set_frame_filter, BarTiming, RenderedBar, RenderedFrame, SplitKind};
+use frame::{FrameFilter, FrameHook};
+pub use fullscreen::{enter_fullscreen, print_line, FullscreenGuard};
+pub use helpers::{blocking, join_with_progress, retry_with_progress, scan_then_process};
+pub use journal::set_journal_output;
+pub use labels::Labels;
+#[cfg(feature = "log")]
+pub use logging::set_log_output;
+pub use output::{set_output_budget, set_synchronized_output};
+pub use profile::TimingProfile;
+pub use progressbar::{
+    FieldContext, ProgressBar, ProgressBarChunkedIterator, ProgressBarEnumerateIterator,
+    ProgressBarIterable, ProgressBarIterator, ScopedMessage,
+};
+pub use scope::scope_label;
+pub use selftest::selftest;
+pub use sink::ProgressSink;
 pub use splitting::*;
-
-use std::{
-    io::stdout,
-    sync::{Arc, Mutex},
+#[cfg(unix)]
+pub use status::send_status_to_socket;
+pub use status::{set_window_title_progress, write_status_file};
+pub use style::{set_default_style, AnimationPhase, Color, Style, Threshold};
+pub use terminal::{
+    clear_terminal_size_override, force_interactive, set_terminal_size_override, terminal_size,
 };
+pub use text::truncate_to_width;
+pub use theme::Theme;
+pub use trace::{export_trace, set_trace_recording};
+pub use watchdog::Stall;
+use watchdog::StallWatcher;
+
+use std::sync::{Arc, LazyLock, Weak};
+use sync::Mutex;
 
 const BAR_FILLED: char = '█';
 const BAR_EMPTY: char = ' ';
 const BAR_ABANDONED: char = 'X';
-const BAR_PARTIALLY_FILLED: [char; 9] = [BAR_EMPTY, '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+const BAR_MARKER: char = '│';
+const BAR_PARTIALLY_FILLED: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
 const BAR_LEFT_BORDER: char = '▕';
 const BAR_RIGHT_BORDER: char = '▏';
 // const BAR_UNKNOWN: char = '░';
 // const BAR_UNKNOWN_ANIM: [char; 4] = ['░', '▒', '▓', '█'];
 
-lazy_static! {
-    pub(crate) static ref MANAGER: Arc<Mutex<ProgressBarManager>> =
-        Arc::new(Mutex::new(ProgressBarManager {
-            bars: vec![],
-            thread_started: false,
-            interactive_output: stdout().is_terminal(),
-            reference_time: Instant::now(),
-        }));
-}
+/// How often the status of all bars is printed in accessible mode, see [`set_accessible_mode`]
+const ACCESSIBLE_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+/// If writing a frame to the terminal takes longer than this, the frame rate is reduced
+const WRITE_LATENCY_BUDGET: Duration = Duration::from_millis(5);
+/// When the frame rate is reduced, frames are spaced so that at most 1/N of the time is spent writing them
+const SLOW_TERMINAL_WRITE_FRACTION: u32 = 8;
+/// Identifier of the next created bar, see [`ProgressBarState::id`]
+static NEXT_BAR_ID: AtomicU64 = AtomicU64::new(0);
+/// How often the [`manager_thread`] checks if the bars have changed, unless a higher refresh rate has been set using [`init_with`]
+const POLL_PERIOD: Duration = Duration::from_millis(20);
+/// Maximum number of frames waiting to be passed to the callbacks registered using [`on_frame`]
+const MAX_PENDING_FRAMES: usize = 100;
+/// How long it takes for the bar to rescale when its length grows, see [`Style::with_length_growth`]
+const LENGTH_GROWTH_ANIMATION: Duration = Duration::from_millis(600);
+/// How long the number of added items is displayed after the length grows, see [`Style::with_length_growth`]
+const LENGTH_GROWTH_HINT: Duration = Duration::from_secs(3);
+/// Gaps between progress updates at least this long count as idle time, see [`ProgressBarState::active_time`]
+const IDLE_THRESHOLD: Duration = Duration::from_secs(1);
+/// The rate of progress of a bar is measured over this window by default, see [`ProgressBarState::per_sec`]
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+/// Progress is sampled at most this often to measure the rate, see [`ProgressBarState::rate_samples`]
+const RATE_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+/// Estimates of the remaining time are capped at this many seconds (about a year), since a very slow rate would otherwise overflow a [`Duration`]
+const MAX_ETA_SECONDS: f64 = 3.2e7;
+
+// Compile time checks for the guarantees in the "Threading model" section of the documentation.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[allow(dead_code)]
+    fn check_threading_model() {
+        assert_send_sync::<ProgressBar>();
+        assert_send_sync::<ProgressBarWeightedNester>();
+        assert_send_sync::<ProgressBarSizedNester>();
+        assert_send_sync::<ProgressBarSummedNester>();
+        assert_send_sync::<CaptureGuard>();
+        assert_send_sync::<Stall>();
+        assert_send_sync::<RenderedFrame>();
+        assert_send_sync::<ProgressBarIterator<std::ops::Range<usize>>>();
+        assert_send_sync::<ProgressBarIterator<std::vec::IntoIter<String>>>();
+        assert_send_sync::<ProgressBarEnumerateIterator<std::ops::Range<usize>>>();
+    }
+};
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+pub(crate) static MANAGER: LazyLock<Arc<Mutex<ProgressBarManager>>> = LazyLock::new(|| {
+    // SAFETY: The handler catches all panics, so it never unwinds across the FFI boundary
+    unsafe {
+        libc::atexit(exit_handler);
+    }
+    panic::install_hook();
+    Arc::new(Mutex::new(ProgressBarManager {
+        bars: vec![],
+        thread_started: false,
+        interactive_output: terminal::interactive_from_env() || config::config().is_terminal(),
+        color_override: terminal::color_from_env(),
+        reference_time: clock::now(),
+        capture: None,
+        default_style: Style::default(),
+        accessible: std::env::var_os("HEADWAY_ACCESSIBLE")
+            .is_some_and(|v| !v.is_empty() && v != "0"),
+        #[cfg(feature = "log")]
+        log_output: false,
+        #[cfg(feature = "log")]
+        pending_log: vec![],
+        journal_output: std::env::var_os("JOURNAL_STREAM").is_some(),
+        last_plain_report: None,
+        last_plain_report_text: String::new(),
+        pollers: vec![],
+        frame_hooks: vec![],
+        has_frame_hooks: false,
+        change_hooks: vec![],
+        has_change_hooks: false,
+        pending_changes: vec![],
+        creation_hooks: vec![],
+        keyed_bars: HashMap::new(),
+        frame_filter: None,
+        render_cache: HashMap::new(),
+        bar_hashes: HashMap::new(),
+        trace_history: None,
+        pending_frames: vec![],
+        title: None,
+        window_title_progress: false,
+        window_title: None,
+        terminal_size_override: None,
+        retain_finished: false,
+        max_lines: None,
+        reserved_bottom_lines: 0,
+        drawn_lines: 0,
+        fullscreen: None,
+        exiting: false,
+        defer_frame: false,
+    }))
+});
+
+/// The time which the timestamps of [`BarTiming`] are relative to. Initialized when the first bar is created.
+pub(crate) static TIMELINE_ORIGIN: LazyLock<Instant> = LazyLock::new(clock::now);
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Hash)]
 enum LifecycleState {
+    #[default]
     InProgress,
     Completed,
     Abandoned,
 }
 
-impl Default for LifecycleState {
-    fn default() -> Self {
-        Self::InProgress
-    }
-}
-
 #[derive(Clone)]
 struct NestedBars {
     bars: Vec<Arc<Mutex<ProgressBarState>>>,
@@ -221,25 +493,269 @@ enum NestedMeta {
     Weighted(Vec<f64>),
     Sized(Vec<f64>),
     Summed,
+    Custom(Arc<dyn core::Aggregate>),
+    Calibrated(Vec<profile::CalibratedWeight>),
+}
+
+impl NestedBars {
+    /// How much of the parent each child makes up, for splits where this is known up front.
+    fn planned_weights(&self) -> Option<Cow<'_, [f64]>> {
+        match &self.meta {
+            NestedMeta::Sized(weights) | NestedMeta::Weighted(weights) => {
+                Some(Cow::Borrowed(weights))
+            }
+            NestedMeta::Calibrated(weights) => Some(Cow::Owned(profile::calibrated_weights(
+                weights,
+                &profile::measured_durations(&self.bars),
+            ))),
+            NestedMeta::Summed | NestedMeta::Custom(_) => None,
+        }
+    }
+}
+
+/// An increase of the length of a bar, see [`Style::with_length_growth`].
+#[derive(Clone)]
+struct LengthGrowth {
+    /// The length before it grew
+    previous: usize,
+    /// Number of items added, including earlier increases which are still being displayed
+    added: usize,
+    /// When the length last grew
+    at: Instant,
+}
+
+/// Estimates the progress within a chunk, for bars which are only updated once per chunk.
+#[derive(Clone)]
+struct ChunkInterpolation {
+    /// Number of items in each chunk
+    chunk: usize,
+    /// Time when the position was last updated
+    last_update: Instant,
+    /// Smoothed number of items processed per second
+    rate: Option<f64>,
+}
+
+/// Fills a bar over a known duration, see [`ProgressBar::auto_advance_over`].
+#[derive(Clone)]
+struct AutoAdvance {
+    /// Position when the bar started advancing
+    start: f64,
+    /// Position where the bar stops, until it is finished
+    end: f64,
+    /// Time when the bar started advancing
+    started: Instant,
+    duration: Duration,
+}
+
+impl AutoAdvance {
+    /// How much of the duration has passed, between 0 and 1.
+    fn fraction(&self) -> f64 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            (clock::since(self.started).as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+        }
+    }
+
+    /// The position the bar should have by now, which may be fractional.
+    fn position(&self) -> f64 {
+        self.start + (self.end - self.start) * self.fraction()
+    }
 }
 
-#[derive(Clone, Default)]
+/// How the rate of progress of a bar is smoothed, see [`ProgressBar::set_rate_smoothing`] and [`ProgressBar::set_rate_window`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RateSmoothing {
+    /// The average rate over a sliding window of this length
+    Window(Duration),
+    /// An exponential moving average, where progress made this long ago has half the weight of current progress
+    HalfLife(Duration),
+}
+
+/// A callback registered using [`ProgressBar::set_formatter`]
+type Formatter = Arc<dyn Fn(&core::BarSnapshot, &mut String) + Send + Sync>;
+
+/// A callback registered using [`ProgressBar::add_extra_field`]
+type ExtraField = Arc<dyn Fn(&progressbar::FieldContext) -> String + Send + Sync>;
+
+#[derive(Clone)]
 struct ProgressBarState {
     pub length: Option<usize>,
     pub position: usize,
+    /// A second counter displayed next to the position, see [`ProgressBar::set_secondary_position`]
+    pub secondary_position: Option<usize>,
+    /// Number of items waiting to be processed, see [`ProgressBar::set_backlog`]
+    pub backlog: Option<usize>,
+    /// Total amount the position has been moved backwards to redo work, see [`ProgressBar::rewind`]
+    pub rewound: usize,
+    /// Set while work is being redone after [`ProgressBar::rewind`], as the position after the rewind and the highest position before it.
+    /// Progress up to the highest position is not counted in the rate, since those items have already been counted once.
+    pub redoing: Option<(usize, usize)>,
     pub message: Option<String>,
+    /// The message displayed on the line of this bar, and when it was last changed, if the style limits how often it changes.
+    /// Only used for top-level bars, see [`Style::with_message_interval`]
+    pub displayed_message: Option<(Option<String>, Instant)>,
+    pub prefix: Option<String>,
+    pub style: Option<Arc<Style>>,
+    pub color: Option<Color>,
     pub nested: Option<NestedBars>,
     pub lifecycle: LifecycleState,
+    /// Last time the bar made progress, or was otherwise updated
+    pub last_activity: Instant,
+    /// Secondary message, see [`ProgressBar::set_phase_message`]
+    pub phase_message: Option<String>,
+    /// Number of times [`ProgressBar::heartbeat`] has been called
+    pub heartbeats: usize,
+    /// Callbacks registered using [`ProgressBar::on_stall`]
+    pub stall_watchers: Vec<StallWatcher>,
+    /// Why the bar was abandoned, see [`ProgressBar::abandon_with_error`]
+    pub abandon_reason: Option<String>,
+    /// True if the bar has been abandoned by something other than its handle, see [`Stall::abandon`] and [`abandon_all`]
+    pub abandoned_externally: bool,
+    /// True if dropping the bar should finish it rather than abandon it.
+    /// Used for bars handed out to libraries which signal completion by dropping their progress handles.
+    pub finish_on_drop: bool,
+    /// Number of extra handles to this bar returned by [`ProgressBar::get_or_create`].
+    /// Dropping a handle only ends the bar once no other handles are left.
+    pub shared_handles: usize,
+    /// Every position and length since the changes were last collected, see [`ChangeStream::Raw`]
+    pub change_log: Vec<(usize, Option<usize>)>,
+    /// The position and length when the changes were last collected, see [`on_change`]
+    pub reported: (usize, Option<usize>),
+    /// The bar this bar was split from, for children of weighted splits. See [`ProgressBar::set_weight`].
+    pub parent: Weak<Mutex<ProgressBarState>>,
+    /// How the parts of a split bar which were never finished are displayed once the bar has ended
+    pub completion_policy: CompletionPolicy,
+    /// Unique identifier, used to track bars between frames
+    pub id: u64,
+    /// Time the bar was created
+    pub created: Instant,
+    /// Time the bar was finished or abandoned
+    pub finished_at: Option<Instant>,
+    /// Total time the bar was stalled, not including the current stall, see [`ProgressBarState::account_stall`]
+    pub stalled: Duration,
+    /// The time of the last activity before the current stall, if the bar is stalled
+    pub stalled_since: Option<Instant>,
+    /// Last time the position changed, see [`ProgressBarState::record_progress`]
+    pub last_progress: Instant,
+    /// Total time spent without making progress, not including the time since the last progress
+    pub idle: Duration,
+    /// The position at different times during the last [`RATE_WINDOW`], oldest first, see [`ProgressBarState::per_sec`]
+    pub rate_samples: VecDeque<(Instant, usize)>,
+    /// How the rate is smoothed, if it has been configured. Also used for the [`Self::eta`] if set.
+    pub rate_smoothing: Option<RateSmoothing>,
+    /// The label of the [`scope_label`] the bar was created in
+    pub label: Option<Arc<str>>,
+    /// The smoothed rate up until the newest of the [`Self::rate_samples`], when using [`RateSmoothing::HalfLife`]
+    pub smoothed_rate: Option<f64>,
+    /// Time the bar started making progress, and how much progress it had made at that point, see [`ProgressBarState::record_progress`]
+    pub started: Option<(Instant, f64)>,
+    /// Positions marked using [`ProgressBar::add_marker`], with their labels
+    pub markers: Vec<(usize, String)>,
+    /// The most recent increase of the length, see [`Style::with_length_growth`]
+    pub length_growth: Option<LengthGrowth>,
+    /// True if the bar displays how much work remains rather than how much has been done, see [`ProgressBar::set_remaining`]
+    pub draining: bool,
+    /// Callbacks which append text to the line, see [`ProgressBar::add_extra_field`]
+    pub extra_fields: Vec<ExtraField>,
+    /// Writes the text after the bar instead of the position and length, see [`ProgressBar::set_formatter`]
+    pub formatter: Option<Formatter>,
+    /// Set if the position is only updated in chunks, see [`ProgressBarIterable::progress_chunked`]
+    pub chunks: Option<ChunkInterpolation>,
+    /// Set while the bar is filled over a known duration, see [`ProgressBar::auto_advance_over`]
+    pub auto_advance: Option<AutoAdvance>,
+    /// Channels created using [`ProgressBar::watch`]
+    #[cfg(feature = "tokio")]
+    pub watchers: Vec<Arc<::tokio::sync::watch::Sender<f64>>>,
+    /// Tokens which are cancelled when the bar is, see [`ProgressBar::link_cancellation_token`]
+    #[cfg(feature = "tokio")]
+    pub cancellation_tokens: Vec<tokio_util::sync::CancellationToken>,
+}
+
+impl Default for ProgressBarState {
+    fn default() -> Self {
+        LazyLock::force(&TIMELINE_ORIGIN);
+        Self {
+            length: None,
+            position: 0,
+            secondary_position: None,
+            backlog: None,
+            rewound: 0,
+            redoing: None,
+            message: None,
+            displayed_message: None,
+            prefix: None,
+            style: None,
+            color: None,
+            nested: None,
+            lifecycle: LifecycleState::default(),
+            last_activity: clock::now(),
+            phase_message: None,
+            heartbeats: 0,
+            stall_watchers: vec![],
+            abandon_reason: None,
+            abandoned_externally: false,
+            finish_on_drop: false,
+            shared_handles: 0,
+            change_log: vec![],
+            reported: (0, None),
+            completion_policy: CompletionPolicy::default(),
+            parent: Weak::new(),
+            id: NEXT_BAR_ID.fetch_add(1, Ordering::Relaxed),
+            created: clock::now(),
+            finished_at: None,
+            started: None,
+            stalled: Duration::ZERO,
+            stalled_since: None,
+            last_progress: clock::now(),
+            idle: Duration::ZERO,
+            rate_samples: VecDeque::new(),
+            rate_smoothing: None,
+            label: scope::current_label(),
+            smoothed_rate: None,
+            markers: vec![],
+            length_growth: None,
+            draining: false,
+            extra_fields: vec![],
+            formatter: None,
+            chunks: None,
+            auto_advance: None,
+            #[cfg(feature = "tokio")]
+            watchers: vec![],
+            #[cfg(feature = "tokio")]
+            cancellation_tokens: vec![],
+        }
+    }
 }
 
 impl ProgressBarState {
     fn hash_state(&self, state: &mut impl Hasher) {
         self.length.hash(state);
         self.position.hash(state);
+        self.secondary_position.hash(state);
+        self.backlog.hash(state);
+        self.rewound.hash(state);
         self.message.hash(state);
+        self.phase_message.hash(state);
+        self.heartbeats.hash(state);
+        self.markers.hash(state);
+        self.abandon_reason.hash(state);
+        self.lifecycle.hash(state);
+        self.prefix.hash(state);
+        self.style.as_ref().map(Arc::as_ptr).hash(state);
+        self.color.hash(state);
+        self.displayed_message.as_ref().map(|(m, _)| m).hash(state);
+        self.draining.hash(state);
+        self.completion_policy.hash(state);
         if let Some(nested) = &self.nested {
+            // Weights can be changed using [`ProgressBar::set_weight`], which changes the fraction of the parent
+            if let NestedMeta::Weighted(weights) | NestedMeta::Sized(weights) = &nested.meta {
+                for w in weights {
+                    w.to_bits().hash(state);
+                }
+            }
             for b in &nested.bars {
-                b.lock().unwrap().hash_state(state);
+                b.lock().hash_state(state);
             }
         }
     }
@@ -251,13 +767,17 @@ impl ProgressBarState {
             let mut total_progress = 0.0;
             let mut total_abandoned = 0.0;
             let mut total_in_progress = 0.0;
+            // The part of the length which has been taken by children
+            let mut total_taken = 0.0;
             match &nested.meta {
-                NestedMeta::Sized(weights) | NestedMeta::Weighted(weights) => {
+                NestedMeta::Sized(_) | NestedMeta::Weighted(_) | NestedMeta::Calibrated(_) => {
+                    let weights = nested.planned_weights().unwrap();
                     for (w, bar) in weights.iter().zip(&nested.bars) {
                         let (mut progress, mut in_progress, abandoned, lower_len, upper_len) =
-                            bar.lock().unwrap().progress_count();
+                            bar.lock().progress_count();
 
                         total_lower_len += w;
+                        total_taken += w;
                         total_upper_len = total_upper_len.map(|x| x + w);
 
                         if upper_len.is_none() {
@@ -275,13 +795,13 @@ impl ProgressBarState {
                                 in_progress = 1.0 - abandoned;
                             }
                         }
-                        total_progress += (progress as f64) * w;
-                        total_abandoned += (abandoned as f64) * w;
-                        total_in_progress += (in_progress as f64) * w;
+                        total_progress += progress * w;
+                        total_abandoned += abandoned * w;
+                        total_in_progress += in_progress * w;
                     }
 
                     match nested.meta {
-                        NestedMeta::Weighted(_) => {
+                        NestedMeta::Weighted(_) | NestedMeta::Calibrated(_) => {
                             // A weighted split is based on fractions. So a natural default is that the whole bar has a size of 1
                             total_lower_len = total_lower_len.max(1.0);
                             total_upper_len = total_upper_len.map(|x| x.max(1.0));
@@ -296,21 +816,38 @@ impl ProgressBarState {
                         _ => {}
                     }
                 }
+                NestedMeta::Custom(aggregate) => {
+                    let children = nested
+                        .bars
+                        .iter()
+                        .map(|bar| bar.lock().snapshot())
+                        .collect::<Vec<_>>();
+                    // The aggregate decides the length too, so the length of this bar is not taken into account
+                    let s = aggregate.combine(&children);
+                    return (
+                        s.completed,
+                        s.in_progress,
+                        s.abandoned,
+                        s.length_lower,
+                        s.length_upper,
+                    );
+                }
                 NestedMeta::Summed => {
                     for bar in &nested.bars {
                         let (progress, in_progress, abandoned, lower_len, upper_len) =
-                            bar.lock().unwrap().progress_count();
+                            bar.lock().progress_count();
 
                         if progress + in_progress + abandoned > 0.0 && upper_len.is_none() {
                             total_upper_len = None;
                         }
                         total_lower_len += lower_len;
+                        total_taken += lower_len;
                         let upper_len = upper_len.unwrap_or(lower_len);
                         total_upper_len = total_upper_len.map(|x| x + upper_len);
 
-                        total_progress += progress * lower_len as f64;
-                        total_abandoned += abandoned * lower_len as f64;
-                        total_in_progress += in_progress * lower_len as f64;
+                        total_progress += progress * lower_len;
+                        total_abandoned += abandoned * lower_len;
+                        total_in_progress += in_progress * lower_len;
                     }
 
                     if let Some(length) = self.length {
@@ -330,6 +867,23 @@ impl ProgressBarState {
                 total_in_progress /= total_lower_len;
             }
 
+            if self.lifecycle != LifecycleState::InProgress {
+                // Parts of the bar which were never taken by a child. Children which are still in progress keep their own state.
+                let untaken = if total_lower_len > 0.0 {
+                    (1.0 - total_taken / total_lower_len).max(0.0)
+                } else {
+                    1.0
+                };
+                match self.completion_policy {
+                    CompletionPolicy::Abandon => total_abandoned += untaken,
+                    CompletionPolicy::Complete => {
+                        total_progress += untaken + total_abandoned;
+                        total_abandoned = 0.0;
+                    }
+                    CompletionPolicy::Hold => {}
+                }
+            }
+
             (
                 total_progress,
                 total_in_progress,
@@ -348,7 +902,7 @@ impl ProgressBarState {
                         0
                     };
                     (
-                        clamped_pos as f64 / length as f64,
+                        self.interpolated_position().min(length as f64) / length as f64,
                         0.0,
                         abandoned_length as f64 / length as f64,
                         length as f64,
@@ -394,24 +948,231 @@ impl ProgressBarState {
         }
     }
 
-    fn progress(&self) -> Option<f64> {
-        let (progress, _in_progress, _abandoned, lower_len, upper_len) = self.progress_count();
-        if let Some(upper_len) = upper_len {
-            if upper_len > 0.0 {
-                Some((progress * lower_len / (upper_len as f64)).clamp(0.0, 1.0))
+    fn snapshot(&self) -> core::BarSnapshot {
+        let (completed, in_progress, abandoned, length_lower, length_upper) = self.progress_count();
+        core::BarSnapshot {
+            completed,
+            in_progress,
+            abandoned,
+            length_lower,
+            length_upper,
+        }
+    }
+
+    /// The position of the bar, including the estimated progress within the current chunk. See [`ChunkInterpolation`].
+    ///
+    /// Bars which are filled over a known duration include the fraction of the current item. See [`AutoAdvance`].
+    fn interpolated_position(&self) -> f64 {
+        let position = match &self.chunks {
+            Some(ChunkInterpolation {
+                chunk,
+                last_update,
+                rate: Some(rate),
+            }) if self.lifecycle == LifecycleState::InProgress => {
+                // Never estimate that the whole chunk is done, since that would make the bar jump backwards if the chunk is slow
+                let within_chunk = (clock::since(*last_update).as_secs_f64() * rate)
+                    .min(chunk.saturating_sub(1) as f64);
+                self.position as f64 + within_chunk
+            }
+            _ => self.position as f64,
+        };
+        match &self.auto_advance {
+            Some(auto_advance) if self.lifecycle == LifecycleState::InProgress => {
+                position.max(auto_advance.position())
+            }
+            _ => position,
+        }
+    }
+
+    /// Advances the position by a chunk of items, and updates the estimated rate. See [`ChunkInterpolation`].
+    fn advance_chunk(&mut self, items: usize) {
+        let now = clock::now();
+        if let Some(chunks) = &mut self.chunks {
+            let elapsed = now.duration_since(chunks.last_update).as_secs_f64();
+            if elapsed > 0.0 {
+                let rate = items as f64 / elapsed;
+                chunks.rate = Some(match chunks.rate {
+                    Some(r) => r * 0.7 + rate * 0.3,
+                    None => rate,
+                });
+            }
+            chunks.last_update = now;
+        }
+        self.position += items;
+        self.record_progress();
+    }
+
+    /// Sets the length of the bar, and remembers how much it grew so that the change can be displayed.
+    fn set_length(&mut self, length: Option<usize>) {
+        let now = clock::now();
+        if let (Some(previous), Some(length)) = (self.length, length) {
+            if length > previous {
+                // Keep counting if the length grew again while the previous increase is still displayed
+                let (start, added) = match &self.length_growth {
+                    Some(g) if now.duration_since(g.at) < LENGTH_GROWTH_HINT => {
+                        (g.previous.min(previous), g.added + length - previous)
+                    }
+                    _ => (previous, length - previous),
+                };
+                self.length_growth = Some(LengthGrowth {
+                    previous: start,
+                    added,
+                    at: now,
+                });
+            }
+        }
+        self.length = length;
+        self.log_change();
+        self.last_activity = now;
+    }
+
+    /// The length the bar is drawn with while it rescales after the length has grown, see [`Style::with_length_growth`].
+    ///
+    /// None if the bar is not rescaling.
+    fn animated_length(&self, style: &Style) -> Option<f64> {
+        let growth = self
+            .length_growth
+            .as_ref()
+            .filter(|_| style.length_growth)?;
+        let length = self.length? as f64;
+        let t = clock::since(growth.at).as_secs_f64() / LENGTH_GROWTH_ANIMATION.as_secs_f64();
+        if t >= 1.0 || self.lifecycle != LifecycleState::InProgress {
+            return None;
+        }
+        // Ease out, so that the bar starts moving quickly and then settles
+        let t = 1.0 - (1.0 - t) * (1.0 - t);
+        Some(growth.previous as f64 + (length - growth.previous as f64) * t)
+    }
+
+    /// Marks the bar as active after its position has changed.
+    ///
+    /// The first time this happens the start of the work is recorded, which is used for the [`Self::eta`] of the bar.
+    /// If the bar was touched after it was created (e.g. by [`ProgressBar::wrap`] setting its length), that is taken to be when the work started.
+    /// Otherwise, the time the first item took is unknown, and the rate is only measured from this point on.
+    fn record_progress(&mut self) {
+        self.log_change();
+        let now = clock::now();
+        let gap = now.saturating_duration_since(self.last_progress);
+        if gap >= IDLE_THRESHOLD {
+            self.idle += gap;
+        }
+        self.last_progress = now;
+        self.sample_rate(now);
+        if self.started.is_none() && self.position > 0 {
+            self.started = Some(if self.last_activity > self.created {
+                (self.last_activity, 0.0)
             } else {
-                Some(0.0)
+                (now, self.progress().unwrap_or(0.0))
+            });
+        }
+        self.last_activity = now;
+    }
+
+    /// The position used for measuring the rate, which does not count items which are being redone. See [`Self::redoing`].
+    fn rate_position(&self) -> usize {
+        match self.redoing {
+            Some((_, high)) => self.position.max(high),
+            None => self.position,
+        }
+    }
+
+    /// Records the current position, for measuring the rate of progress. See [`Self::per_sec`].
+    fn sample_rate(&mut self, now: Instant) {
+        // The redone work is done, or the position was reset to before the rewind
+        if self
+            .redoing
+            .is_some_and(|(low, high)| self.position >= high || self.position < low)
+        {
+            self.redoing = None;
+        }
+        let rate_position = self.rate_position();
+        if self
+            .rate_samples
+            .back()
+            .is_some_and(|&(_, position)| rate_position < position)
+        {
+            // The position was reset, so the old samples say nothing about the current rate
+            self.rate_samples.clear();
+            self.smoothed_rate = None;
+        }
+        if self
+            .rate_samples
+            .back()
+            .is_some_and(|&(time, _)| now.saturating_duration_since(time) < RATE_SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        match self.rate_smoothing {
+            Some(RateSmoothing::HalfLife(half_life)) => {
+                // Only the newest sample is needed, since the older ones are part of the smoothed rate
+                if let Some(&(time, position)) = self.rate_samples.back() {
+                    self.smoothed_rate = Some(smooth_rate(
+                        self.smoothed_rate,
+                        rate_position - position,
+                        now.saturating_duration_since(time),
+                        half_life,
+                    ));
+                }
+                self.rate_samples.clear();
+                self.rate_samples.push_back((now, rate_position));
             }
-        } else {
-            None
+            Some(RateSmoothing::Window(window)) => self.push_rate_sample(now, window),
+            None => self.push_rate_sample(now, RATE_WINDOW),
+        }
+    }
+
+    /// Adds a sample of the position, and forgets the samples which are older than the window.
+    fn push_rate_sample(&mut self, now: Instant, window: Duration) {
+        self.rate_samples.push_back((now, self.rate_position()));
+        // Keep the newest sample from before the window, so that the window is always covered
+        while self.rate_samples.len() > 2
+            && now.saturating_duration_since(self.rate_samples[1].0) >= window
+        {
+            self.rate_samples.pop_front();
         }
     }
 
+    /// The number of items processed per second.
+    ///
+    /// While the bar is in progress, this is smoothed as configured by [`Self::rate_smoothing`], up until now.
+    /// By default, this is the average over the last [`RATE_WINDOW`].
+    /// Otherwise it is the average rate over the time the bar was making progress.
+    fn per_sec(&self) -> Option<f64> {
+        if self.lifecycle != LifecycleState::InProgress {
+            let (start, _) = self.started?;
+            let elapsed = self
+                .last_progress
+                .saturating_duration_since(start)
+                .as_secs_f64();
+            return (elapsed > 0.0).then(|| self.position as f64 / elapsed);
+        }
+        if let Some(RateSmoothing::HalfLife(half_life)) = self.rate_smoothing {
+            // Include the time since the last sample, so that the rate decays while no progress is made
+            let &(time, position) = self.rate_samples.back()?;
+            let elapsed = clock::since(time);
+            return (self.smoothed_rate.is_some() || !elapsed.is_zero()).then(|| {
+                smooth_rate(
+                    self.smoothed_rate,
+                    self.rate_position().saturating_sub(position),
+                    elapsed,
+                    half_life,
+                )
+            });
+        }
+        let &(time, position) = self.rate_samples.front()?;
+        let elapsed = clock::since(time).as_secs_f64();
+        (elapsed > 0.0).then(|| self.rate_position().saturating_sub(position) as f64 / elapsed)
+    }
+
+    fn progress(&self) -> Option<f64> {
+        self.snapshot().progress()
+    }
+
     fn visit_completed(&self, visitor: &mut impl FnMut(bool, &ProgressBarState)) -> bool {
         if let Some(nested) = &self.nested {
             let mut completed = true;
             for b in &nested.bars {
-                completed &= b.lock().unwrap().visit_completed(visitor);
+                completed &= b.lock().visit_completed(visitor);
             }
             visitor(completed, self);
             completed
@@ -423,242 +1184,2092 @@ impl ProgressBarState {
         }
     }
 
+    /// True if the manager holds the only reference to this bar, meaning that it has been finished or abandoned.
+    fn is_dropped(&self, this: &Arc<Mutex<ProgressBarState>>) -> bool {
+        Arc::strong_count(this) + self.nested_strong_count() == 1
+    }
+
     /// Number of external references to the children of this bar.
     fn nested_strong_count(&self) -> usize {
         if let Some(nested) = &self.nested {
             nested
                 .bars
                 .iter()
-                .map(|b| (Arc::strong_count(b) - 1) + b.lock().unwrap().nested_strong_count())
+                .map(|b| (Arc::strong_count(b) - 1) + b.lock().nested_strong_count())
                 .sum::<usize>()
         } else {
             0
         }
     }
 
-    fn message(&self) -> Option<String> {
-        // Message of first non-completed bar
-        // or last completed bar
-        let mut msg = None;
-        let all_completed = self.visit_completed(&mut |completed, bar| {
-            if !completed && msg.is_none() {
-                msg = bar.message.clone();
+    /// Last time this bar, or any of its children, were updated.
+    fn last_activity(&self) -> Instant {
+        let mut last = self.last_activity;
+        if let Some(nested) = &self.nested {
+            for b in &nested.bars {
+                last = last.max(b.lock().last_activity());
             }
-        });
-        if all_completed {
-            // Last completed bar
-            self.visit_completed(&mut |_, bar| {
-                if bar.message.is_some() {
-                    // TODO: Kinda suboptimal
-                    msg = bar.message.clone();
-                }
-            });
         }
-
-        msg
+        last
     }
 
-    fn render_indeterminate_bar(out: &mut String, steps: Range<usize>, reference_time: &Instant) {
-        let t = reference_time.elapsed().as_secs_f64();
-        for i in steps {
-            const BRIGHTNESS_STEPS: usize = 24;
-            let anim_index = ((((2.0 * t + (i as f64) * 0.7).sin() * 0.5 + 0.5)
-                * BRIGHTNESS_STEPS as f64)
-                .floor() as usize)
-                .clamp(0, BRIGHTNESS_STEPS - 1);
-
-            // SAFETY: Writes to strings cannot fail
-            write!(out, "\u{001b}[38;5;{}m{}", 232 + anim_index, BAR_FILLED).unwrap();
+    /// How long the bar has been stalled, if it has been stalled for longer than the style's threshold.
+    fn stalled_for(&self, style: &Style) -> Option<Duration> {
+        let threshold = style.stall_threshold?;
+        if self.lifecycle != LifecycleState::InProgress {
+            return None;
         }
-        out.push_str("\u{001b}[0m");
+        let elapsed = clock::since(self.last_activity());
+        (elapsed >= threshold).then_some(elapsed)
     }
 
-    fn render(
-        &self,
-        out: &mut String,
-        color: bool,
-        reference_time: &Instant,
-        is_animating: &mut bool,
-    ) -> std::fmt::Result {
-        let bar_width = 20;
-
-        let (progress_value, in_progress_value, abandoned_value, length_lower, length_upper) =
-            self.progress_count();
+    /// Keeps track of how long this bar and its children have been stalled in total, see [`Style::with_stall_threshold`].
+    ///
+    /// Called regularly by the manager thread. A stall is considered to last from the last activity before it, until the first activity after it.
+    fn account_stall(&mut self, default_style: &Style) {
+        let style = self.style.as_deref().unwrap_or(default_style);
+        if let Some(since) = self.stalled_since {
+            let last_activity = self.last_activity();
+            if last_activity > since || self.lifecycle != LifecycleState::InProgress {
+                let end = if last_activity > since {
+                    last_activity
+                } else {
+                    self.finished_at.unwrap_or_else(clock::now)
+                };
+                self.stalled += end.saturating_duration_since(since);
+                self.stalled_since = None;
+            }
+        }
+        if self.stalled_since.is_none() && self.stalled_for(style).is_some() {
+            self.stalled_since = Some(self.last_activity());
+        }
+        if let Some(nested) = &self.nested {
+            for bar in &nested.bars {
+                bar.lock().account_stall(default_style);
+            }
+        }
+    }
 
-        debug_assert!(progress_value <= 1.0);
-        debug_assert!(in_progress_value <= 1.0);
-        debug_assert!(abandoned_value <= 1.0);
-        debug_assert!(progress_value + in_progress_value + abandoned_value <= 1.0001);
+    /// Total time the bar has been stalled, including the current stall.
+    fn total_stalled(&self) -> Duration {
+        self.stalled + self.stalled_since.map_or(Duration::ZERO, clock::since)
+    }
 
-        if let Some(length_upper) = length_upper {
-            debug_assert!(length_lower <= length_upper);
+    /// How much of [`Self::elapsed`] the bar spent making progress.
+    ///
+    /// Gaps between progress updates which are at least [`IDLE_THRESHOLD`] long count as idle.
+    /// For bars which have been split, this is the sum of the active times of the children.
+    fn active_time(&self) -> Duration {
+        let elapsed = self.elapsed();
+        if let Some(nested) = &self.nested {
+            let active: Duration = nested.bars.iter().map(|b| b.lock().active_time()).sum();
+            return active.min(elapsed);
+        }
+        let end = self.finished_at.unwrap_or_else(clock::now);
+        let trailing = end.saturating_duration_since(self.last_progress);
+        let idle = if trailing >= IDLE_THRESHOLD {
+            self.idle + trailing
+        } else {
+            self.idle
+        };
+        elapsed.saturating_sub(idle)
+    }
 
-            let bounds_multiplier = if length_upper > 0.0 {
-                length_lower / length_upper
-            } else {
-                0.0
-            };
+    /// How long the bar has been running, or how long it ran for if it is no longer in progress.
+    fn elapsed(&self) -> Duration {
+        self.finished_at
+            .unwrap_or_else(clock::now)
+            .saturating_duration_since(self.created)
+    }
 
-            let filled_pos = progress_value * bounds_multiplier * bar_width as f64;
-            let mut filled_index = filled_pos.floor() as usize;
-            let mut in_progress_index =
-                ((progress_value + in_progress_value) * bounds_multiplier * bar_width as f64)
-                    .floor() as usize;
-            let abandoned_index =
-                ((1.0 - abandoned_value * bounds_multiplier) * bar_width as f64).floor() as usize;
-
-            out.push(BAR_LEFT_BORDER);
-            for _ in 0..filled_index {
-                out.push(BAR_FILLED);
-            }
-            if filled_index < abandoned_index {
-                let partially_filled_step = (filled_pos.fract() * 8.0).floor() as usize;
-                if partially_filled_step > 0 {
-                    filled_index += 1;
-                    in_progress_index = in_progress_index.max(filled_index);
-                    out.push(BAR_PARTIALLY_FILLED[partially_filled_step]);
-                }
+    /// Estimated time until the bar is finished, assuming it keeps progressing at the same average speed as since it started.
+    ///
+    /// For bars split into children with planned weights, this is the sum of the estimates of the children.
+    fn eta(&self) -> Option<Duration> {
+        if self.lifecycle != LifecycleState::InProgress {
+            return None;
+        }
+        if let Some(nested) = &self.nested {
+            if let Some(weights) = nested.planned_weights() {
+                return self.nested_eta(&nested.bars, &weights);
+            }
+        }
+        let p = self.progress()?;
+        if p <= 0.0 || p >= 1.0 {
+            return None;
+        }
+        if self.rate_smoothing.is_some() && self.nested.is_none() {
+            let remaining = self.length?.saturating_sub(self.position);
+            let per_sec = self.per_sec().filter(|&r| r > 0.0)?;
+            return Some(Duration::from_secs_f64(
+                (remaining as f64 / per_sec).min(MAX_ETA_SECONDS),
+            ));
+        }
+        match self.started {
+            Some((start, p0)) => {
+                let done = p - p0;
+                (done > 0.0).then(|| clock::since(start).mul_f64((1.0 - p) / done))
             }
+            None => Some(self.elapsed().mul_f64((1.0 - p) / p)),
+        }
+    }
 
-            let indeterminate_range = filled_index..in_progress_index;
-            *is_animating |= !indeterminate_range.is_empty();
-            Self::render_indeterminate_bar(out, indeterminate_range, reference_time);
+    /// Moves the position backwards, without treating it as a reset when measuring the rate.
+    ///
+    /// The rate samples are kept, and the items until the previous position are not counted in the rate again. See [`Self::redoing`].
+    /// Does nothing if the bar has already been finished or abandoned.
+    fn rewind(&mut self, n: usize) {
+        if self.lifecycle != LifecycleState::InProgress {
+            return;
+        }
+        let n = n.min(self.position);
+        let high = self.rate_position();
+        self.position -= n;
+        self.rewound += n;
+        self.redoing = Some((self.position, high));
+        self.log_change();
+        self.last_activity = clock::now();
+    }
 
-            for _ in in_progress_index..abandoned_index {
-                out.push(BAR_EMPTY);
+    /// Adds up the estimated time remaining for each child, assuming they run one after another.
+    ///
+    /// Children which cannot estimate their own time yet, usually because they have not started, are estimated
+    /// using their planned weight and the average time per unit of weight that the children have taken so far.
+    fn nested_eta(
+        &self,
+        bars: &[Arc<Mutex<ProgressBarState>>],
+        weights: &[f64],
+    ) -> Option<Duration> {
+        let mut remaining = Duration::ZERO;
+        let mut done = 0.0;
+        let mut unestimated = 0.0;
+        for (w, bar) in weights.iter().zip(bars) {
+            let bar = bar.lock();
+            let p = bar.progress().unwrap_or(0.0);
+            done += w * p;
+            if bar.lifecycle != LifecycleState::InProgress {
+                continue;
             }
-            if abandoned_index < bar_width {
-                if color {
-                    out.push_str("\u{001b}[31m");
-                }
-                for _ in abandoned_index..bar_width {
-                    out.push(BAR_ABANDONED);
-                }
-                if color {
-                    out.push_str("\u{001b}[0m");
-                }
+            match bar.eta() {
+                Some(eta) => remaining += eta,
+                None => unestimated += w * (1.0 - p),
             }
-            out.push(BAR_RIGHT_BORDER);
-        } else {
-            *is_animating = true;
-            out.push(BAR_LEFT_BORDER);
-            Self::render_indeterminate_bar(out, 0..bar_width, reference_time);
-            out.push(BAR_RIGHT_BORDER);
         }
-
-        // Check if it's a weighted nesting. Those we always display as percentages.
-        if !matches!(
-            self.nested,
-            Some(NestedBars {
-                meta: NestedMeta::Weighted(_),
-                ..
-            })
-        ) {
-            write!(out, " {}/", (progress_value * length_lower).floor())?;
-            if let Some(length_upper) = length_upper {
-                write!(out, "{}", length_upper)?;
-            } else {
-                write!(out, "?")?;
+        if unestimated > 0.0 {
+            if done <= 0.0 {
+                return None;
             }
-        } else if let Some(p) = self.progress() {
-            write!(out, " {}%", (p * 100.0).floor() as usize)?;
-        } else {
-            write!(out, " ?%")?;
+            let elapsed = clock::since(self.started_at().unwrap_or(self.created));
+            remaining += elapsed.mul_f64(unestimated / done);
         }
+        Some(remaining)
+    }
 
-        if let Some(msg) = self.message() {
-            write!(out, " {}", msg)?;
+    /// The earliest time this bar or any of its children started making progress.
+    fn started_at(&self) -> Option<Instant> {
+        let mut start = self.started.map(|(t, _)| t);
+        if let Some(nested) = &self.nested {
+            for bar in &nested.bars {
+                if let Some(t) = bar.lock().started_at() {
+                    start = Some(start.map_or(t, |s| s.min(t)));
+                }
+            }
         }
+        start
+    }
 
+    /// Writes the elapsed time and the estimated remaining time, if enabled by the style.
+    fn render_timing(&self, out: &mut String, style: &Style) -> std::fmt::Result {
+        if style.show_elapsed {
+            out.push(' ');
+            format::write_duration(out, self.elapsed(), style.duration_format, &style.labels)?;
+            write!(out, " {}", style.labels.elapsed)?;
+        }
+        if style.show_activity && self.lifecycle != LifecycleState::InProgress {
+            let elapsed = self.elapsed();
+            let active = self.active_time().as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+            write!(
+                out,
+                " {} {:.0}% {} ",
+                style.labels.active,
+                active * 100.0,
+                style.labels.out_of
+            )?;
+            format::write_duration(out, elapsed, style.duration_format, &style.labels)?;
+        }
+        if style.show_eta {
+            if let Some(eta) = self.eta() {
+                out.push(' ');
+                format::write_duration(out, eta, style.duration_format, &style.labels)?;
+                write!(out, " {}", style.labels.remaining)?;
+            }
+        }
+        if style.show_rate {
+            if let Some(per_sec) = self.per_sec().filter(|_| self.nested.is_none()) {
+                out.push(' ');
+                format::write_rate(out, per_sec);
+                write!(out, " {}", style.labels.per_second)?;
+            }
+        }
         Ok(())
     }
-}
 
-struct ProgressBarManager {
-    /// All currently visible bars
-    pub bars: Vec<Arc<Mutex<ProgressBarState>>>,
-    /// True if the [`manager_thread`] is running
-    pub thread_started: bool,
-    /// True if the output is a tty (terminal)
+    /// Writes the text of the fields added using [`ProgressBar::add_extra_field`].
+    ///
+    /// Fields are truncated so that the line does not get wider than `width`, if it is known.
+    fn render_extra_fields(&self, out: &mut String, width: Option<usize>) {
+        if self.extra_fields.is_empty() {
+            return;
+        }
+        let snapshot = self.snapshot();
+        let line_start = out.rfind('\n').map_or(0, |i| i + 1);
+        for field in &self.extra_fields {
+            // One column is used by the space before the field
+            let remaining_width =
+                width.map(|w| w.saturating_sub(text::display_width(&out[line_start..]) + 1));
+            let text = field(&progressbar::FieldContext {
+                snapshot: &snapshot,
+                remaining_width,
+            });
+            let text = match remaining_width {
+                Some(remaining) => text::truncate_to_width(&text, remaining),
+                None => Cow::Borrowed(text.as_str()),
+            };
+            if !text.is_empty() {
+                out.push(' ');
+                out.push_str(&text);
+            }
+        }
+    }
+
+    /// Writes why the bar was abandoned, like ` (abandoned: connection refused)`.
+    fn render_abandon_reason(&self, out: &mut String, style: &Style) -> std::fmt::Result {
+        match &self.abandon_reason {
+            Some(reason) if self.lifecycle == LifecycleState::Abandoned => {
+                write!(out, " ({}: {})", style.labels.abandoned, reason)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn message(&self) -> Option<String> {
+        match &self.displayed_message {
+            Some((message, _)) => message.clone(),
+            None => self.latest_message(),
+        }
+    }
+
+    /// The most recently set message of this bar or its children, ignoring [`Style::with_message_interval`].
+    fn latest_message(&self) -> Option<String> {
+        self.find_text(|bar| &bar.message)
+    }
+
+    /// Updates the displayed message to the most recent one, unless the style says that it was changed too recently.
+    fn settle_message(&mut self, now: Instant, default_style: &Style) {
+        let interval = self
+            .style
+            .as_deref()
+            .unwrap_or(default_style)
+            .message_interval;
+        if interval.is_zero() || self.lifecycle != LifecycleState::InProgress {
+            // Finished bars always display their final message
+            self.displayed_message = None;
+            return;
+        }
+        let latest = self.latest_message();
+        match &self.displayed_message {
+            Some((message, changed)) if *message == latest || now < *changed + interval => {}
+            _ => self.displayed_message = Some((latest, now)),
+        }
+    }
+
+    /// True if a more recent message is waiting to be displayed.
+    fn message_pending(&self) -> bool {
+        self.displayed_message
+            .as_ref()
+            .is_some_and(|(message, _)| *message != self.latest_message())
+    }
+
+    /// The message, followed by the phase message in parentheses.
+    fn full_message(&self) -> Option<String> {
+        match (self.message(), self.find_text(|bar| &bar.phase_message)) {
+            (Some(msg), Some(phase)) => Some(format!("{} ({})", msg, phase)),
+            (msg, phase) => msg.or(phase),
+        }
+    }
+
+    /// Total backlog of this bar and all its children, if any of them have a backlog.
+    fn backlog(&self) -> Option<usize> {
+        let mut backlog = self.backlog;
+        if let Some(nested) = &self.nested {
+            for b in &nested.bars {
+                if let Some(child) = b.lock().backlog() {
+                    backlog = Some(backlog.unwrap_or(0) + child);
+                }
+            }
+        }
+        backlog
+    }
+
+    /// Number of direct children which are still in progress, and number of direct children which have been abandoned.
+    pub(crate) fn child_counts(&self) -> (usize, usize) {
+        let mut active = 0;
+        let mut abandoned = 0;
+        if let Some(nested) = &self.nested {
+            for b in &nested.bars {
+                match b.lock().lifecycle {
+                    LifecycleState::InProgress => active += 1,
+                    LifecycleState::Abandoned => abandoned += 1,
+                    LifecycleState::Completed => {}
+                }
+            }
+        }
+        (active, abandoned)
+    }
+
+    /// Writes the number of abandoned children of a summed split, like ` (3 failed)`. See [`ProgressBar::split_summed`].
+    fn render_failed_children(
+        &self,
+        out: &mut String,
+        style: &Style,
+        color: bool,
+    ) -> std::fmt::Result {
+        if !matches!(
+            self.nested,
+            Some(NestedBars {
+                meta: NestedMeta::Summed,
+                ..
+            })
+        ) {
+            return Ok(());
+        }
+        let (_, abandoned) = self.child_counts();
+        if abandoned > 0 {
+            out.push_str(" (");
+            if color {
+                Color::Red.write_foreground(out);
+            }
+            format::write_count(out, abandoned as u64, style.count_format)?;
+            write!(out, " {}", style.labels.failed)?;
+            if color {
+                out.push_str("\u{001b}[0m");
+            }
+            out.push(')');
+        }
+        Ok(())
+    }
+
+    /// Total number of heartbeats of this bar and all its children.
+    fn heartbeats(&self) -> usize {
+        let mut heartbeats = self.heartbeats;
+        if let Some(nested) = &self.nested {
+            for b in &nested.bars {
+                heartbeats += b.lock().heartbeats();
+            }
+        }
+        heartbeats
+    }
+
+    fn prefix(&self) -> Option<String> {
+        self.find_text(|bar| &bar.prefix)
+    }
+
+    /// Finds the text that should be displayed for a nested bar.
+    fn find_text(&self, field: impl Fn(&ProgressBarState) -> &Option<String>) -> Option<String> {
+        // Text of first non-completed bar
+        // or last completed bar
+        let mut text = None;
+        let all_completed = self.visit_completed(&mut |completed, bar| {
+            if !completed && text.is_none() {
+                text = field(bar).clone();
+            }
+        });
+        if all_completed {
+            // Last completed bar
+            self.visit_completed(&mut |_, bar| {
+                if field(bar).is_some() {
+                    // TODO: Kinda suboptimal
+                    text = field(bar).clone();
+                }
+            });
+        }
+
+        text
+    }
+
+    fn render_indeterminate_bar(
+        out: &mut String,
+        steps: Range<usize>,
+        style: &Style,
+        reference_time: &Instant,
+        heartbeats: usize,
+        color: bool,
+    ) {
+        if !color || style.theme.indeterminate.is_empty() {
+            for _ in steps {
+                out.push(style.filled);
+            }
+            return;
+        }
+        if steps.is_empty() {
+            return;
+        }
+
+        for i in steps {
+            if let Some(c) = Self::indeterminate_color(style, reference_time, heartbeats, i) {
+                c.write_foreground(out);
+            }
+            out.push(style.filled);
+        }
+        out.push_str("\u{001b}[0m");
+    }
+
+    /// The color of the given cell of an indeterminate bar, picked from the palette of the theme. See [`Theme::with_indeterminate`].
+    ///
+    /// The phase of the animation depends on the style's [`AnimationPhase`].
+    fn indeterminate_color(
+        style: &Style,
+        reference_time: &Instant,
+        heartbeats: usize,
+        cell: usize,
+    ) -> Option<Color> {
+        let palette = &style.theme.indeterminate;
+        let steps = palette.len();
+        let (heartbeats, cell) = match style.animation_phase {
+            AnimationPhase::PerBar => (heartbeats, cell),
+            AnimationPhase::Synchronized => (0, cell),
+            AnimationPhase::Uniform => (0, 0),
+        };
+        // Every heartbeat nudges the animation forward a bit, to make it obvious that something is happening
+        let t = clock::since(*reference_time).as_secs_f64() + heartbeats as f64 * 0.5;
+        let anim_index = ((((2.0 * t + (cell as f64) * 0.7).sin() * 0.5 + 0.5) * steps as f64)
+            .floor() as usize)
+            .min(steps.saturating_sub(1));
+        palette.get(anim_index).copied()
+    }
+
+    /// Renders the bar as fixed-width columns, see [`PlainFormat::Columns`].
+    fn render_columns(&self, out: &mut String) -> std::fmt::Result {
+        let status = match self.lifecycle {
+            LifecycleState::InProgress => "running",
+            LifecycleState::Completed => "done",
+            LifecycleState::Abandoned => "abandoned",
+        };
+        write!(out, "{:<9} ", status)?;
+        let percent = self
+            .progress()
+            .map(|p| (p.clamp(0.0, 1.0) * 100.0).floor() as u64);
+        format::write_column(out, percent, 3)?;
+        out.push(' ');
+        let (progress_value, _, _, length_lower, length_upper) = self.progress_count();
+        let position = match self.nested {
+            Some(_) => length_upper.map(|_| (progress_value * length_lower).floor() as u64),
+            None => Some(self.position as u64),
+        };
+        format::write_column(out, position, 12)?;
+        out.push(' ');
+        format::write_column(out, length_upper.map(|l| l as u64), 12)?;
+        out.push(' ');
+        format::write_column(out, Some(self.elapsed().as_secs()), 8)?;
+        out.push(' ');
+        let eta = self.eta().map(|eta| eta.as_secs_f64().ceil() as u64);
+        format::write_column(out, eta, 8)?;
+        out.push(' ');
+        let text = match (self.prefix(), self.full_message()) {
+            (Some(prefix), Some(msg)) => format!("{} {}", prefix, msg),
+            (prefix, msg) => prefix.or(msg).unwrap_or_default(),
+        };
+        out.push_str(&text::strip_control(&text));
+        Ok(())
+    }
+
+    /// Renders the bar as plain text, without any colors or bar graphics.
+    fn render_plain(&self, out: &mut String, default_style: &Style) -> std::fmt::Result {
+        let style = self.style.as_deref().unwrap_or(default_style);
+        if style.plain_format == PlainFormat::Columns {
+            return self.render_columns(out);
+        }
+        if let Some(prefix) = self.prefix() {
+            write!(out, "{} ", prefix)?;
+        }
+        let (mut progress_value, _, abandoned_value, length_lower, length_upper) =
+            self.progress_count();
+        if self.draining {
+            progress_value = 1.0 - progress_value;
+        }
+        match self.progress() {
+            Some(p) => format::write_percentage(out, p),
+            None => out.push_str(&style.labels.in_progress),
+        }
+        if let Some(formatter) = &self.formatter {
+            let start = out.len();
+            out.push_str(" (");
+            formatter(&self.snapshot(), out);
+            if out.len() == start + 2 {
+                out.truncate(start);
+            } else {
+                out.push(')');
+            }
+        } else if !matches!(
+            self.nested,
+            Some(NestedBars {
+                meta: NestedMeta::Weighted(_) | NestedMeta::Calibrated(_),
+                ..
+            })
+        ) {
+            if let Some(length_upper) = length_upper {
+                out.push_str(" (");
+                format::write_count(
+                    out,
+                    (progress_value * length_lower).floor() as u64,
+                    style.count_format,
+                )?;
+                out.push('/');
+                format::write_count(out, length_upper as u64, style.count_format)?;
+                if self.draining {
+                    write!(out, " {}", style.labels.remaining)?;
+                }
+                out.push(')');
+            }
+        }
+        if let Some(backlog) = self.backlog() {
+            out.push_str(" (+");
+            format::write_count(out, backlog as u64, style.count_format)?;
+            write!(out, " {})", style.labels.queued)?;
+        }
+        self.render_failed_children(out, style, false)?;
+        self.render_rewound(out, style, false)?;
+        if let Some(secondary) = self.secondary_position {
+            out.push_str(" → ");
+            format::write_count(out, secondary as u64, style.count_format)?;
+            if secondary > 0 {
+                write!(out, " ({:.1}x)", self.position as f64 / secondary as f64)?;
+            }
+        }
+        self.render_timing(out, style)?;
+        if abandoned_value > 0.0 {
+            write!(out, " {}", style.labels.abandoned)?;
+        }
+        if let Some(msg) = self.full_message() {
+            write!(out, ": {}", msg)?;
+        }
+        self.render_abandon_reason(out, style)?;
+        self.render_extra_fields(out, None);
+        if let Some(stalled) = self.stalled_for(style) {
+            write!(out, " ({} ", style.labels.stalled)?;
+            format::write_duration(out, stalled, style.duration_format, &style.labels)?;
+            out.push(')');
+        }
+        Ok(())
+    }
+
+    /// Draws the bar itself, including its borders, using `bar_width` cells.
+    ///
+    /// Returns the fraction of the items which are done, and the lower and upper bounds of the length, for the text after the bar.
+    fn render_graphic(
+        &self,
+        out: &mut String,
+        ctx: &RenderContext,
+        style: &Style,
+        bar_width: usize,
+        is_animating: &mut bool,
+    ) -> Result<(f64, f64, Option<f64>), std::fmt::Error> {
+        let RenderContext {
+            color,
+            reference_time,
+            ..
+        } = *ctx;
+        let completed = self.lifecycle == LifecycleState::Completed;
+        let (
+            mut progress_value,
+            mut in_progress_value,
+            mut abandoned_value,
+            length_lower,
+            length_upper,
+        ) = self.progress_count();
+        // Draining bars display what remains, so the fill decreases as the work is done
+        if self.draining {
+            (progress_value, in_progress_value, abandoned_value) =
+                ((1.0 - progress_value).clamp(0.0, 1.0), 0.0, 0.0);
+        }
+        let count_value = progress_value;
+        // When the length grows, the bar shrinks smoothly to the new scale instead of jumping
+        if let Some(animated) = self.animated_length(style) {
+            let scale = length_lower / animated.max(1.0);
+            progress_value = (progress_value * scale).min(1.0);
+            in_progress_value = (in_progress_value * scale).min(1.0 - progress_value);
+            *is_animating = true;
+        }
+
+        // Chunked bars are redrawn often, so that the estimated progress within the chunk moves smoothly
+        *is_animating |= self.chunks.is_some() && self.lifecycle == LifecycleState::InProgress;
+        *is_animating |= self.lifecycle == LifecycleState::InProgress
+            && self
+                .auto_advance
+                .as_ref()
+                .is_some_and(|a| a.fraction() < 1.0);
+        // Keep redrawing until the most recent message has been displayed
+        *is_animating |= self.message_pending();
+
+        debug_assert!(progress_value <= 1.0);
+        debug_assert!(in_progress_value <= 1.0);
+        debug_assert!(abandoned_value <= 1.0);
+        debug_assert!(progress_value + in_progress_value + abandoned_value <= 1.0001);
+
+        if let Some(length_upper) = length_upper {
+            debug_assert!(length_lower <= length_upper);
+
+            let bounds_multiplier = if length_upper > 0.0 {
+                length_lower / length_upper
+            } else {
+                0.0
+            };
+
+            let filled_pos = progress_value * bounds_multiplier * bar_width as f64;
+            let mut filled_index = filled_pos.floor() as usize;
+            let in_progress_pos =
+                (progress_value + in_progress_value) * bounds_multiplier * bar_width as f64;
+            let mut in_progress_index = in_progress_pos.floor() as usize;
+            let abandoned_pos = (1.0 - abandoned_value * bounds_multiplier) * bar_width as f64;
+            let abandoned_index = abandoned_pos.floor() as usize;
+
+            let to_cell = |pos: f64| {
+                ((pos / length_upper * bar_width as f64).floor() as usize).min(bar_width - 1)
+            };
+            // Cells which contain a marker, see ProgressBar::add_marker
+            let mut marker_cells = self
+                .markers
+                .iter()
+                .filter(|_| length_upper > 0.0 && bar_width > 0)
+                .map(|&(pos, _)| to_cell(pos as f64))
+                .collect::<Vec<_>>();
+            // Cells covered by the child which is currently being worked on, see Style::with_segments
+            let mut active_cells = 0..0;
+            if let Some((ends, active)) = self
+                .segments()
+                .filter(|_| style.segments && length_upper > 0.0 && bar_width > 0)
+            {
+                marker_cells.extend(
+                    ends.iter()
+                        .take(ends.len().saturating_sub(1))
+                        .filter(|&&end| end < length_upper)
+                        .map(|&end| to_cell(end)),
+                );
+                if let Some(active) = active {
+                    let start = if active > 0 { ends[active - 1] } else { 0.0 };
+                    active_cells = to_cell(start)..to_cell(ends[active]).max(to_cell(start) + 1);
+                }
+            }
+            let cell = |i: usize, c: char| {
+                if marker_cells.contains(&i) {
+                    BAR_MARKER
+                } else {
+                    c
+                }
+            };
+
+            out.push_str(&style.left_border);
+            let tint = style
+                .completed_color
+                .filter(|_| completed)
+                .or(self.color)
+                .or(style.theme.filled)
+                .filter(|_| color);
+            if let Some(tint) = tint {
+                tint.write_foreground(out);
+            }
+            for i in 0..filled_index {
+                out.push(cell(i, style.filled));
+            }
+            if filled_index < abandoned_index && style.smooth_fill {
+                if let Some(partially_filled) = style.partial_fill_char(filled_pos.fract()) {
+                    // The rest of the cell is drawn as the background, if the in progress part covers it
+                    let in_progress_background =
+                        color && in_progress_pos >= (filled_index + 1) as f64;
+                    if in_progress_background {
+                        *is_animating = true;
+                        if let Some(c) = Self::indeterminate_color(
+                            style,
+                            reference_time,
+                            self.heartbeats(),
+                            filled_index,
+                        ) {
+                            c.write_background(out);
+                        }
+                    }
+                    filled_index += 1;
+                    in_progress_index = in_progress_index.max(filled_index);
+                    out.push(partially_filled);
+                    if in_progress_background {
+                        out.push_str("\u{001b}[49m");
+                    }
+                }
+            }
+            if tint.is_some() {
+                out.push_str("\u{001b}[0m");
+            }
+
+            let indeterminate_range = filled_index..in_progress_index;
+            *is_animating |= !indeterminate_range.is_empty();
+            Self::render_indeterminate_bar(
+                out,
+                indeterminate_range,
+                style,
+                reference_time,
+                self.heartbeats(),
+                color,
+            );
+            if style.smooth_fill
+                && in_progress_index < abandoned_index
+                && in_progress_pos.floor() as usize >= filled_index
+            {
+                if let Some(partially_in_progress) =
+                    style.partial_fill_char(in_progress_pos.fract())
+                {
+                    *is_animating = true;
+                    let tint = Self::indeterminate_color(
+                        style,
+                        reference_time,
+                        self.heartbeats(),
+                        in_progress_index,
+                    )
+                    .filter(|_| color);
+                    if let Some(tint) = tint {
+                        tint.write_foreground(out);
+                    }
+                    out.push(partially_in_progress);
+                    if tint.is_some() {
+                        out.push_str("\u{001b}[0m");
+                    }
+                    in_progress_index += 1;
+                }
+            }
+
+            for i in in_progress_index..abandoned_index {
+                let highlight = color && active_cells.contains(&i);
+                if highlight {
+                    out.push_str("\u{001b}[48;5;237m");
+                }
+                out.push(cell(i, style.empty));
+                if highlight {
+                    out.push_str("\u{001b}[49m");
+                }
+            }
+            let mut abandoned_start = abandoned_index;
+            if color
+                && style.smooth_fill
+                && in_progress_index <= abandoned_index
+                && abandoned_index < bar_width
+            {
+                if let Some(partially_abandoned) = style.partial_fill_char(abandoned_pos.fract()) {
+                    // Draw the part which is not abandoned using inverted colors, so that the rest of the cell gets the abandoned color
+                    out.push_str("\u{001b}[7m");
+                    style.theme.abandoned.write_foreground(out);
+                    write!(out, "{}\u{001b}[0m", partially_abandoned)?;
+                    abandoned_start += 1;
+                }
+            }
+            if abandoned_start < bar_width {
+                if color {
+                    style.theme.abandoned.write_foreground(out);
+                }
+                for i in abandoned_start..bar_width {
+                    out.push(cell(i, BAR_ABANDONED));
+                }
+                if color {
+                    out.push_str("\u{001b}[0m");
+                }
+            }
+            out.push_str(&style.right_border);
+        } else {
+            *is_animating = true;
+            out.push_str(&style.left_border);
+            Self::render_indeterminate_bar(
+                out,
+                0..bar_width,
+                style,
+                reference_time,
+                self.heartbeats(),
+                color,
+            );
+            out.push_str(&style.right_border);
+        }
+        Ok((count_value, length_lower, length_upper))
+    }
+
+    /// Renders the bar using the layout of a template, see [`Style::with_template`].
+    fn render_template(
+        &self,
+        out: &mut String,
+        ctx: &RenderContext,
+        style: &Style,
+        template: &template::Template,
+        bar_width: usize,
+        is_animating: &mut bool,
+    ) -> std::fmt::Result {
+        use template::{Field, Segment};
+
+        let (mut progress_value, _, _, length_lower, length_upper) = self.progress_count();
+        if self.draining {
+            progress_value = 1.0 - progress_value;
+        }
+        let mut text = String::new();
+        for segment in &template.segments {
+            let (field, width, right_align) = match segment {
+                Segment::Text(literal) => {
+                    out.push_str(literal);
+                    continue;
+                }
+                &Segment::Field {
+                    field,
+                    width,
+                    right_align,
+                } => (field, width, right_align),
+            };
+            text.clear();
+            match field {
+                Field::Bar(width) => {
+                    self.render_graphic(out, ctx, style, width.unwrap_or(bar_width), is_animating)?;
+                    continue;
+                }
+                Field::Prefix => text.push_str(self.prefix().as_deref().unwrap_or_default()),
+                Field::Message => text.push_str(&self.full_message().unwrap_or_default()),
+                Field::Position => format::write_count(
+                    &mut text,
+                    (progress_value * length_lower).floor() as u64,
+                    style.count_format,
+                )?,
+                Field::Length => match length_upper {
+                    Some(length) => {
+                        format::write_count(&mut text, length as u64, style.count_format)?
+                    }
+                    None => text.push('?'),
+                },
+                Field::Percent => match self.progress() {
+                    Some(p) => format::write_percentage(&mut text, p),
+                    None => text.push_str("?%"),
+                },
+                Field::Elapsed => format::write_duration(
+                    &mut text,
+                    self.elapsed(),
+                    style.duration_format,
+                    &style.labels,
+                )?,
+                Field::Eta => {
+                    if let Some(eta) = self.eta() {
+                        format::write_duration(
+                            &mut text,
+                            eta,
+                            style.duration_format,
+                            &style.labels,
+                        )?;
+                    }
+                }
+                Field::Rate => {
+                    if let Some(per_sec) = self.per_sec().filter(|_| self.nested.is_none()) {
+                        format::write_rate(&mut text, per_sec);
+                        write!(text, " {}", style.labels.per_second)?;
+                    }
+                }
+            }
+            let padding = " ".repeat(width.saturating_sub(text::display_width(&text)));
+            let bold = ctx.color && style.bold_prefix && field == Field::Prefix;
+            if right_align {
+                out.push_str(&padding);
+            }
+            if bold {
+                out.push_str("\u{001b}[1m");
+            }
+            out.push_str(&text);
+            if bold {
+                out.push_str("\u{001b}[0m");
+            }
+            if !right_align {
+                out.push_str(&padding);
+            }
+        }
+        *is_animating |= self.message_pending();
+
+        self.render_abandon_reason(out, style)?;
+        self.render_extra_fields(out, ctx.width);
+        self.render_stalled(out, style, ctx.color)
+    }
+
+    fn render(
+        &self,
+        out: &mut String,
+        ctx: &RenderContext,
+        is_animating: &mut bool,
+    ) -> std::fmt::Result {
+        let style = self.style.as_deref().unwrap_or(ctx.default_style);
+        let bar_width = match (style.auto_width, ctx.width) {
+            (Some((min, max)), Some(width)) => {
+                // Render the line without the bar first, to find out how much space is left for it
+                let mut probe = String::new();
+                self.render_line(&mut probe, ctx, 0, &mut false)?;
+                // The last column is left empty, since some terminals wrap the line when it is written to
+                width
+                    .saturating_sub(text::display_width(&probe) + 1)
+                    .clamp(min, max)
+            }
+            _ => style.bar_width,
+        };
+        self.render_line(out, ctx, bar_width, is_animating)
+    }
+
+    /// Renders the bar with the bar graphic `bar_width` cells wide. See [`Self::render`].
+    fn render_line(
+        &self,
+        out: &mut String,
+        ctx: &RenderContext,
+        bar_width: usize,
+        is_animating: &mut bool,
+    ) -> std::fmt::Result {
+        let color = ctx.color;
+        let style = self.style.as_deref().unwrap_or(ctx.default_style);
+        if let Some(template) = &style.template {
+            return self.render_template(out, ctx, style, template, bar_width, is_animating);
+        }
+
+        let prefix = self.prefix();
+        if prefix.is_some() || style.prefix_width > 0 {
+            if color && style.bold_prefix {
+                out.push_str("\u{001b}[1m");
+            }
+            // Padding is based on the display width, since wide characters take up two columns
+            let prefix = prefix.as_deref().unwrap_or_default();
+            let padding = style
+                .prefix_width
+                .saturating_sub(text::display_width(prefix));
+            out.extend(std::iter::repeat_n(' ', padding));
+            out.push_str(prefix);
+            if color && style.bold_prefix {
+                out.push_str("\u{001b}[0m");
+            }
+            out.push(' ');
+        }
+
+        let completed = self.lifecycle == LifecycleState::Completed;
+        if !style.completed_glyph.is_empty() {
+            if completed {
+                let tint = style.completed_color.filter(|_| color);
+                if let Some(tint) = tint {
+                    tint.write_foreground(out);
+                }
+                out.push_str(&style.completed_glyph);
+                if tint.is_some() {
+                    out.push_str("\u{001b}[0m");
+                }
+            } else {
+                let width = text::display_width(&style.completed_glyph);
+                out.extend(std::iter::repeat_n(' ', width));
+            }
+            out.push(' ');
+        }
+
+        if style.aggregate_bar_only && self.nested.is_none() {
+            if let Some(msg) = self.full_message() {
+                out.push_str(&msg);
+            }
+            return self.render_stalled(out, style, color);
+        }
+
+        let (count_value, length_lower, length_upper) =
+            self.render_graphic(out, ctx, style, bar_width, is_animating)?;
+
+        let number_color = style
+            .threshold_color(self.progress(), self.elapsed())
+            .filter(|_| color);
+        if let Some(number_color) = number_color {
+            number_color.write_foreground(out);
+        }
+        // Weighted nestings are always displayed as percentages, unless the text has been replaced by a formatter
+        if let Some(formatter) = &self.formatter {
+            let start = out.len();
+            out.push(' ');
+            formatter(&self.snapshot(), out);
+            // Avoid a trailing space if the formatter did not write anything
+            if out.len() == start + 1 {
+                out.truncate(start);
+            }
+        } else if !matches!(
+            self.nested,
+            Some(NestedBars {
+                meta: NestedMeta::Weighted(_) | NestedMeta::Calibrated(_),
+                ..
+            })
+        ) {
+            out.push(' ');
+            format::write_count(
+                out,
+                (count_value * length_lower).floor() as u64,
+                style.count_format,
+            )?;
+            out.push('/');
+            if let Some(length_upper) = length_upper {
+                format::write_count(out, length_upper as u64, style.count_format)?;
+            } else {
+                write!(out, "?")?;
+            }
+            if self.draining {
+                write!(out, " {}", style.labels.remaining)?;
+            }
+        } else if let Some(p) = self.progress() {
+            out.push(' ');
+            format::write_percentage(out, p);
+        } else {
+            write!(out, " ?%")?;
+        }
+        if number_color.is_some() {
+            out.push_str("\u{001b}[0m");
+        }
+
+        if let Some(growth) = &self.length_growth {
+            if style.length_growth
+                && clock::since(growth.at) < LENGTH_GROWTH_HINT
+                && self.lifecycle == LifecycleState::InProgress
+            {
+                out.push_str(" (+");
+                format::write_count(out, growth.added as u64, style.count_format)?;
+                write!(out, " {})", style.labels.added)?;
+            }
+        }
+
+        if let Some(backlog) = self.backlog() {
+            out.push_str(" (+");
+            format::write_count(out, backlog as u64, style.count_format)?;
+            write!(out, " {})", style.labels.queued)?;
+        }
+        self.render_failed_children(out, style, color)?;
+        self.render_rewound(out, style, color)?;
+
+        if let Some(secondary) = self.secondary_position {
+            out.push_str(" → ");
+            format::write_count(out, secondary as u64, style.count_format)?;
+            if secondary > 0 {
+                write!(out, " ({:.1}x)", self.position as f64 / secondary as f64)?;
+            }
+        }
+
+        self.render_timing(out, style)?;
+
+        if let Some(msg) = self.full_message() {
+            write!(out, " {}", msg)?;
+        }
+        self.render_abandon_reason(out, style)?;
+        self.render_extra_fields(out, ctx.width);
+
+        if self.lifecycle != LifecycleState::InProgress {
+            self.render_marker_labels(out, length_upper)?;
+        }
+
+        self.render_stalled(out, style, color)
+    }
+
+    /// Finishes or abandons this bar and all its children which are still in progress. See [`finish_all`] and [`abandon_all`].
+    fn end_recursive(&mut self, lifecycle: LifecycleState) {
+        if let Some(nested) = &self.nested {
+            for bar in &nested.bars {
+                bar.lock().end_recursive(lifecycle);
+            }
+        }
+        if self.lifecycle != LifecycleState::InProgress {
+            return;
+        }
+        match lifecycle {
+            LifecycleState::Completed => {
+                if let Some(length) = self.length {
+                    self.position = length;
+                }
+            }
+            LifecycleState::Abandoned => self.abandoned_externally = true,
+            LifecycleState::InProgress => {}
+        }
+        self.lifecycle = lifecycle;
+        self.finished_at.get_or_insert_with(clock::now);
+        #[cfg(feature = "tokio")]
+        self.notify_watchers();
+    }
+
+    /// The position in the bar where each child ends, and the index of the child which is currently being worked on.
+    ///
+    /// Only available for weighted and sized splits.
+    fn segments(&self) -> Option<(Vec<f64>, Option<usize>)> {
+        let nested = self.nested.as_ref()?;
+        let calibrated;
+        let weights = match &nested.meta {
+            NestedMeta::Weighted(weights) | NestedMeta::Sized(weights) => weights,
+            NestedMeta::Calibrated(weights) => {
+                calibrated = profile::calibrated_weights(
+                    weights,
+                    &profile::measured_durations(&nested.bars),
+                );
+                &calibrated
+            }
+            NestedMeta::Summed | NestedMeta::Custom(_) => return None,
+        };
+        let mut end = 0.0;
+        let ends = weights
+            .iter()
+            .map(|w| {
+                end += w;
+                end
+            })
+            .collect();
+        let active = nested
+            .bars
+            .iter()
+            .position(|b| b.lock().lifecycle == LifecycleState::InProgress);
+        Some((ends, active))
+    }
+
+    /// Lists the labels of all markers, like ` │50% verify`. Displayed once the bar is done.
+    fn render_marker_labels(&self, out: &mut String, length: Option<f64>) -> std::fmt::Result {
+        for (pos, label) in &self.markers {
+            if label.is_empty() {
+                continue;
+            }
+            write!(out, " {}", BAR_MARKER)?;
+            match length {
+                Some(length) if length > 0.0 => write!(
+                    out,
+                    "{}% ",
+                    ((*pos as f64 / length).min(1.0) * 100.0).floor() as usize
+                )?,
+                _ => {}
+            }
+            out.push_str(label);
+        }
+        Ok(())
+    }
+
+    /// Displays how much work has been redone, like `(↺ 20 redone)`. See [`ProgressBar::rewind`].
+    fn render_rewound(&self, out: &mut String, style: &Style, color: bool) -> std::fmt::Result {
+        if self.rewound == 0 {
+            return Ok(());
+        }
+        if color {
+            out.push_str("\u{001b}[2m");
+        }
+        out.push_str(" (↺ ");
+        format::write_count(out, self.rewound as u64, style.count_format)?;
+        write!(out, " {})", style.labels.redone)?;
+        if color {
+            out.push_str("\u{001b}[0m");
+        }
+        Ok(())
+    }
+
+    fn render_stalled(&self, out: &mut String, style: &Style, color: bool) -> std::fmt::Result {
+        if let Some(stalled) = self.stalled_for(style) {
+            if color {
+                out.push_str("\u{001b}[2m");
+            }
+            write!(out, " {} ", style.labels.stalled)?;
+            format::write_duration(out, stalled, style.duration_format, &style.labels)?;
+            if color {
+                out.push_str("\u{001b}[0m");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings shared by all bars when rendering a frame.
+struct RenderContext<'a> {
+    /// True if ANSI color codes may be used
+    color: bool,
+    /// An arbitrary fixed reference time, used for animations
+    reference_time: &'a Instant,
+    /// Style used by bars which do not have a style of their own
+    default_style: &'a Style,
+    /// Width of the terminal in columns, if known
+    width: Option<usize>,
+}
+
+struct ProgressBarManager {
+    /// All currently visible bars
+    pub bars: Vec<Arc<Mutex<ProgressBarState>>>,
+    /// True if the [`manager_thread`] is running
+    pub thread_started: bool,
+    /// True if the output is a tty (terminal)
     interactive_output: bool,
+    /// Whether to use colors regardless of [`Self::interactive_output`], see [`terminal::color_from_env`]
+    color_override: Option<bool>,
     /// An arbitrary fixed reference time
     reference_time: Instant,
+    /// If set, frames are recorded here instead of being written to the output. See [`test_capture`].
+    capture: Option<Arc<Mutex<Vec<String>>>>,
+    /// Style used by bars which do not have a style of their own
+    default_style: Style,
+    /// True if the output should be accessible, see [`set_accessible_mode`]
+    accessible: bool,
+    /// True if plain output should be sent to the `log` crate, see [`set_log_output`]
+    #[cfg(feature = "log")]
+    log_output: bool,
+    /// Lines which have been rendered for the `log` crate, but not yet logged. See [`logging::emit`].
+    #[cfg(feature = "log")]
+    pending_log: Vec<String>,
+    /// True if the status of the bars should occasionally be printed when the output is not a terminal, see [`set_journal_output`]
+    journal_output: bool,
+    /// Last time the status of all bars was printed in accessible mode
+    last_plain_report: Option<Instant>,
+    /// The last status printed in accessible mode
+    last_plain_report_text: String,
+    /// Functions which are called regularly by the [`manager_thread`], see [`add_poller`]
+    pollers: Vec<Poller>,
+    /// Callbacks registered using [`on_frame`]
+    frame_hooks: Vec<FrameHook>,
+    /// True if [`on_frame`] has ever been called
+    has_frame_hooks: bool,
+    /// Callbacks registered using [`on_change`]
+    change_hooks: Vec<changes::ChangeHook>,
+    /// True if [`on_change`] has ever been called
+    has_change_hooks: bool,
+    /// Changes which have been collected, but not yet passed to the [`Self::change_hooks`]
+    pending_changes: Vec<changes::ChangeBatch>,
+    /// Callbacks registered using [`on_bar_created`]
+    creation_hooks: Vec<created::CreationHook>,
+    /// Bars created using [`ProgressBar::get_or_create`], by their key
+    keyed_bars: HashMap<String, Weak<Mutex<ProgressBarState>>>,
+    /// Applied to all text before it is written, see [`set_frame_filter`]
+    frame_filter: Option<FrameFilter>,
+    /// The line rendered for each top-level bar in the last frame, by bar id, with the key it was rendered for.
+    ///
+    /// Rendering a bar walks its whole tree of nested bars several times, which is expensive when there are many bars.
+    /// The [`manager_thread`] already hashes the state of every bar to find out if anything has changed,
+    /// so bars whose hash is unchanged reuse their line from the last frame instead. Bars which are animated are never cached.
+    render_cache: HashMap<u64, (u64, String)>,
+    /// The state hash of each top-level bar, by bar id, computed by the last call to [`Self::hash_state`].
+    /// Used as the key of the [`Self::render_cache`] by the following [`Self::tick`].
+    bar_hashes: HashMap<u64, u64>,
+    /// Bars which have been removed, kept for [`export_trace`] if recording is enabled, see [`set_trace_recording`]
+    trace_history: Option<Vec<trace::TraceRecord>>,
+    /// Frames which have been rendered, but not yet passed to the [`Self::frame_hooks`]
+    pending_frames: Vec<RenderedFrame>,
+    /// Header displayed above all bars, see [`set_title`]
+    title: Option<String>,
+    /// True if the progress should be displayed in the window title, see [`set_window_title_progress`]
+    window_title_progress: bool,
+    /// The window title which is currently displayed, if it has been changed
+    window_title: Option<String>,
+    /// Size of the terminal to use instead of the detected size, see [`set_terminal_size_override`]
+    terminal_size_override: Option<(usize, usize)>,
+    /// True if finished bars should stay visible, see [`set_retain_finished`]
+    retain_finished: bool,
+    /// The maximum number of lines used to draw bars, see [`set_max_lines`]
+    max_lines: Option<usize>,
+    /// Number of lines at the bottom of the terminal which are used by the application, see [`reserve_bottom_lines`]
+    reserved_bottom_lines: usize,
+    /// Number of lines used by the last frame, see [`reserved_lines`]
+    drawn_lines: usize,
+    /// The dashboard which replaces the normal display, see [`enter_fullscreen`]
+    fullscreen: Option<fullscreen::Fullscreen>,
+    /// True if the process is exiting. All bars are then rendered a final time, as if they had been dropped. See [`exit_handler`].
+    exiting: bool,
+    /// True if [`Self::tick`] should only queue the output which must never be dropped, like finished bars,
+    /// and leave drawing the bars which are still in progress for later. See [`redraw`].
+    defer_frame: bool,
 }
 
 impl ProgressBarManager {
+    /// Starts the [`manager_thread`] unless it is already running.
+    fn ensure_thread_started(&mut self) {
+        if !self.thread_started {
+            self.thread_started = true;
+            thread::spawn(manager_thread);
+        }
+    }
+
+    /// True if bars must be rendered regularly, and not only when they are finished.
+    fn needs_thread(&self) -> bool {
+        #[cfg(feature = "log")]
+        if self.log_output {
+            return true;
+        }
+        self.interactive_output
+            || self.has_frame_hooks
+            || self.has_change_hooks
+            || self.journal_output
+    }
+
+    /// Applies the [`set_frame_filter`] filter to text which is about to be written.
+    fn filter_output(&self, text: &mut String) {
+        if let Some(filter) = self.frame_filter.as_ref().filter(|_| !text.is_empty()) {
+            *text = filter(text);
+        }
+    }
+
+    /// The number of lines which bars can be drawn on, if it is limited. See [`set_max_lines`] and [`reserve_bottom_lines`].
+    ///
+    /// The newline at the end of the last bar moves the cursor to the line below the bars, before it is moved back up to the first bar.
+    /// That line is counted as the first reserved line, so that drawing the bars never scrolls the terminal.
+    fn max_visible_lines(&self) -> Option<usize> {
+        let rows = self
+            .terminal_size_override
+            .or_else(terminal::detect_terminal_size)
+            .map(|(_, rows)| rows.saturating_sub(self.reserved_bottom_lines.max(1)));
+        match (rows, self.max_lines) {
+            (Some(rows), Some(max)) => Some(rows.min(max)),
+            (rows, max) => rows.or(max),
+        }
+    }
+
+    /// Time between frames, if it has been limited. See [`Config::with_refresh_rate`] and [`enter_fullscreen`].
+    fn frame_period(&self) -> Option<Duration> {
+        self.fullscreen
+            .as_ref()
+            .and_then(|f| f.frame_period())
+            .or_else(|| config::config().frame_period())
+    }
+
+    /// True if bars are rendered as plain text, without any bar graphics.
+    fn plain_output(&self) -> bool {
+        #[cfg(feature = "log")]
+        if self.log_output && !self.interactive_output {
+            return true;
+        }
+        self.accessible
+            || self.fullscreen.is_some()
+            || (self.journal_output && !self.interactive_output)
+    }
+
+    /// The status of all bars as plain text, one line per bar.
+    ///
+    /// Returns None if the last report was less than [`ACCESSIBLE_REPORT_INTERVAL`] ago, or if nothing has changed since then.
+    fn plain_report(&mut self) -> Result<Option<String>, std::fmt::Error> {
+        if self
+            .last_plain_report
+            .is_some_and(|t| t.elapsed() < ACCESSIBLE_REPORT_INTERVAL)
+        {
+            return Ok(None);
+        }
+        self.last_plain_report = Some(Instant::now());
+        let mut report = String::new();
+        if let Some(title) = self.title.as_ref().filter(|_| !self.bars.is_empty()) {
+            report.push_str(title);
+            report.push('\n');
+        }
+        for (label, bars) in scope::sections(&self.bars) {
+            if let Some(label) = label {
+                write_section_header(&mut report, &label, false, None);
+            }
+            for bar in bars {
+                bar.lock().render_plain(&mut report, &self.default_style)?;
+                report.push('\n');
+            }
+        }
+        if report == self.last_plain_report_text {
+            return Ok(None);
+        }
+        self.last_plain_report_text = report.clone();
+        Ok(Some(report))
+    }
+
     pub fn hash_state(&mut self) -> u64 {
         let mut hasher = DefaultHasher::new();
         hasher.write_usize(self.bars.len());
+        self.title.hash(&mut hasher);
+        // The layout depends on the size of the terminal, see [`Style::with_auto_width`] and [`set_max_lines`]
+        self.terminal_size_override
+            .or_else(terminal::detect_terminal_size)
+            .hash(&mut hasher);
+        if let Some(fullscreen) = &self.fullscreen {
+            fullscreen.hash_view(&mut hasher);
+        }
+        self.bar_hashes.clear();
         for bar in &self.bars {
-            let bar = bar.lock().unwrap();
-            bar.hash_state(&mut hasher);
+            let bar = bar.lock();
+            let mut bar_hasher = DefaultHasher::new();
+            bar.hash_state(&mut bar_hasher);
+            let bar_hash = bar_hasher.finish();
+            self.bar_hashes.insert(bar.id, bar_hash);
+            hasher.write_u64(bar_hash);
         }
         hasher.finish()
     }
 
-    pub fn tick(&mut self, out: &mut impl std::io::Write) -> std::io::Result<bool> {
+    /// Renders all bars, and queues the output to be written to stdout. See [`output`].
+    ///
+    /// Returns true if any bar is animating.
+    pub fn tick(&mut self) -> Result<bool, std::fmt::Error> {
+        let now = clock::now();
+        self.collect_changes();
+        for bar in &self.bars {
+            let mut bar = bar.lock();
+            bar.settle_message(now, &self.default_style);
+            bar.account_stall(&self.default_style);
+        }
+        // The normal screen must be restored before anything is printed on it
+        let leave_fullscreen = if self.exiting || self.fullscreen.as_ref().is_some_and(|f| f.hidden)
+        {
+            self.leave_fullscreen()
+        } else {
+            None
+        };
+        let show_window_title = self.window_title_progress
+            && self.fullscreen.is_none()
+            && !self.exiting
+            && self.interactive_output
+            && self.capture.is_none()
+            && !self.accessible;
+        let frame = (self.has_frame_hooks || show_window_title).then(|| {
+            RenderedFrame::new(
+                self.title.clone(),
+                &self.bars,
+                &self.reference_time,
+                &self.default_style,
+            )
+        });
+        let window_title = frame
+            .as_ref()
+            .filter(|_| show_window_title)
+            .map(status::window_title);
+        if let Some(frame) = frame.filter(|_| self.has_frame_hooks) {
+            push_frame(&mut self.pending_frames, frame);
+        }
+
+        // Only frames which are drawn below the cursor occupy any lines
+        self.drawn_lines = 0;
         let mut temp_output = String::new();
         let mut is_animating = false;
+        // Captured frames are meant to be inspected programmatically, so they are rendered without colors
+        let ctx = RenderContext {
+            color: self.color_override.unwrap_or(self.interactive_output) && self.capture.is_none(),
+            reference_time: &self.reference_time,
+            default_style: &self.default_style,
+            width: self
+                .terminal_size_override
+                .or_else(terminal::detect_terminal_size)
+                .map(|(cols, _)| cols),
+        };
 
+        // Finished bars stay in the managed region when retaining them, until cleared using [`clear_finished`]
+        let retain_finished = self.retain_finished
+            && self.interactive_output
+            && !self.accessible
+            && self.fullscreen.is_none()
+            && !self.exiting;
         let mut to_remove = 0;
-        for bar in &self.bars {
-            let b = bar.lock().unwrap();
-            if Arc::strong_count(bar) + b.nested_strong_count() == 1 {
+        for bar in self.bars.iter().take_while(|_| !retain_finished) {
+            let b = bar.lock();
+            if b.is_dropped(bar) || self.exiting {
                 // Only the manager has a reference to this bar. This means it has been dropped
                 // everywhere else, and we can safely render it a final time and then forget about it.
-                b.render(
-                    &mut temp_output,
-                    self.interactive_output,
-                    &self.reference_time,
-                    &mut is_animating,
-                )
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                // Piped output is meant for tools like `cut` when the style asks for columns, see [`PlainFormat::Columns`]
+                let columns = !self.interactive_output
+                    && b.style
+                        .as_deref()
+                        .unwrap_or(&self.default_style)
+                        .plain_format
+                        == PlainFormat::Columns;
+                if self.plain_output() || columns {
+                    b.render_plain(&mut temp_output, &self.default_style)
+                } else {
+                    b.render(&mut temp_output, &ctx, &mut is_animating)
+                }?;
                 temp_output.push('\n');
+                if let Some(fullscreen) = &mut self.fullscreen {
+                    fullscreen.count(b.lifecycle);
+                }
                 to_remove += 1;
             } else {
                 break;
             }
         }
-        self.bars.drain(0..to_remove);
+        trace::remember(&mut self.trace_history, self.bars.drain(0..to_remove));
+        // No frames are rendered once all bars are gone, so tell the hooks that nothing is visible anymore
+        if to_remove > 0 && self.bars.is_empty() && self.has_frame_hooks {
+            let frame = RenderedFrame::new(
+                self.title.clone(),
+                &self.bars,
+                &self.reference_time,
+                &self.default_style,
+            );
+            push_frame(&mut self.pending_frames, frame);
+        }
 
         if !self.interactive_output {
+            #[cfg(feature = "log")]
+            if self.log_output {
+                self.log_report(&temp_output)?;
+                return Ok(false);
+            }
+            if self.journal_output {
+                self.journal_report(&temp_output)?;
+                return Ok(false);
+            }
             // When we are not writing to a terminal, we only render progress bars when they are finished (or abandoned)
-            write!(out, "{}", &temp_output)?;
-            out.flush().unwrap();
+            self.filter_output(&mut temp_output);
+            if !temp_output.is_empty() {
+                output::enqueue(&temp_output, None);
+            }
             return Ok(is_animating);
         }
 
-        for bar in &self.bars {
-            bar.lock()
-                .unwrap()
-                .render(
-                    &mut temp_output,
-                    self.interactive_output,
-                    &self.reference_time,
-                    &mut is_animating,
-                )
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        if self.accessible {
+            // Never move the cursor. Instead we occasionally print the status of all bars as plain text.
+            if let Some(report) = self.plain_report()? {
+                temp_output.push_str(&report);
+            }
+            self.filter_output(&mut temp_output);
+            if let Some(restore) = leave_fullscreen {
+                temp_output.insert_str(0, &restore);
+            }
+
+            if let Some(capture) = &self.capture {
+                if !temp_output.is_empty() {
+                    capture.lock().push(temp_output);
+                }
+            } else if !temp_output.is_empty() {
+                output::enqueue(&temp_output, None);
+            }
+            return Ok(false);
+        }
+
+        if let Some(fullscreen) = &mut self.fullscreen {
+            // Finished bars are moved to the log pane of the dashboard
+            fullscreen.log(&temp_output);
+            if fullscreen.is_frozen() {
+                return Ok(false);
+            }
+            let size = self
+                .terminal_size_override
+                .or_else(terminal::detect_terminal_size);
+            let screen = fullscreen.render(
+                self.title.as_deref(),
+                &self.bars,
+                &ctx,
+                size,
+                &mut is_animating,
+            )?;
+            if let Some(capture) = &self.capture {
+                capture.lock().push(screen.join("\n") + "\n");
+            } else if !self.defer_frame {
+                output::enqueue("", Some((fullscreen::frame(&screen), 0)));
+            }
+            return Ok(is_animating);
+        }
+
+        // Finished bars are printed permanently above the other bars, and must never be dropped
+        self.filter_output(&mut temp_output);
+        let mut permanent = leave_fullscreen.unwrap_or_default() + &temp_output;
+        temp_output.clear();
+        // Where each line ends in the output, and whether it displays a bar
+        let mut line_ends = vec![];
+        if let Some(title) = self.title.as_ref().filter(|_| !self.bars.is_empty()) {
+            if ctx.color {
+                write!(temp_output, "\u{001b}[1m{}\u{001b}[0m", title).unwrap();
+            } else {
+                temp_output.push_str(title);
+            }
+            fit_last_line(&mut temp_output, 0, ctx.width);
             temp_output.push('\n');
+            line_ends.push((temp_output.len(), false));
+        }
+        // The hashes are only up to date if they were computed right before this frame
+        let bar_hashes = std::mem::take(&mut self.bar_hashes);
+        let mut render_cache = HashMap::with_capacity(self.bars.len());
+        // Bars created within the same [`scope_label`] are displayed together, below their label
+        for (label, bars) in scope::sections(&self.bars) {
+            if let Some(label) = label {
+                write_section_header(&mut temp_output, &label, ctx.color, ctx.width);
+                line_ends.push((temp_output.len(), false));
+            }
+            for bar in bars {
+                let b = bar.lock();
+                let line_start = temp_output.len();
+                if retain_finished && b.is_dropped(bar) {
+                    // Retained bars are greyed out. They are rendered without colors, since those would reset the dimming.
+                    let plain_ctx = RenderContext {
+                        color: false,
+                        ..ctx
+                    };
+                    if ctx.color {
+                        temp_output.push_str("\u{001b}[2m");
+                    }
+                    b.render(&mut temp_output, &plain_ctx, &mut false)?;
+                    if ctx.color {
+                        temp_output.push_str("\u{001b}[0m");
+                    }
+                } else {
+                    render_cached(
+                        &b,
+                        bar_hashes.get(&b.id).copied(),
+                        &mut temp_output,
+                        &ctx,
+                        &mut is_animating,
+                        &mut self.render_cache,
+                        &mut render_cache,
+                    )?;
+                }
+                // Lines which wrap would break the cursor movements used to redraw the bars
+                fit_last_line(&mut temp_output, line_start, ctx.width);
+                temp_output.push('\n');
+                line_ends.push((temp_output.len(), true));
+            }
+        }
+        self.render_cache = render_cache;
+
+        // Bars which do not fit are summarized on the last line, see [`set_max_lines`] and [`reserve_bottom_lines`]
+        if let Some(limit) = self.max_visible_lines().filter(|&l| line_ends.len() > l) {
+            let shown = limit.saturating_sub(1);
+            let hidden = line_ends[shown..].iter().filter(|(_, bar)| *bar).count();
+            temp_output.truncate(shown.checked_sub(1).map_or(0, |i| line_ends[i].0));
+            line_ends.truncate(shown);
+            if limit > 0 {
+                let start = temp_output.len();
+                write!(
+                    temp_output,
+                    "… {} {}",
+                    hidden, self.default_style.labels.more
+                )?;
+                fit_last_line(&mut temp_output, start, ctx.width);
+                temp_output.push('\n');
+                line_ends.push((temp_output.len(), false));
+            }
+        }
+        let mut lines = line_ends.len();
+        if self.frame_filter.is_some() {
+            self.filter_output(&mut temp_output);
+            lines = temp_output.matches('\n').count();
+        }
+        self.drawn_lines = lines;
+
+        if let Some(capture) = &self.capture {
+            permanent.push_str(&temp_output);
+            if !permanent.is_empty() {
+                capture.lock().push(permanent);
+            }
+            return Ok(is_animating);
         }
 
-        write!(out, "{}", &temp_output)?;
+        // Changing the window title is not idempotent, so it must never be dropped either
+        self.update_window_title(
+            window_title.filter(|_| !self.bars.is_empty()),
+            &mut permanent,
+        )?;
 
-        if !self.bars.is_empty() {
-            // Move to start of line N lines up
-            // Together with the clearing below, this will make sure that if something is printed to stdout it will first
-            // remove the progress bars and then print the text.
-            let prev_lines = self.bars.len();
-            write!(out, "\u{001b}[{}F", prev_lines)?;
-            out.flush().unwrap();
-            // then clear everything after the cursor to end of screen.
-            // DO NOT flush after this as that would remove the progress bars.
-            write!(out, "\u{001b}[0J")?;
+        if lines > 0 && !self.defer_frame {
+            // Move to start of line N lines up.
+            // The output queue clears the lines of the bars once the frame has been written.
+            // This will make sure that if something is printed to stdout it will first remove the progress bars and then print the text.
+            write!(temp_output, "\u{001b}[{}F", lines)?;
+            output::enqueue(&permanent, Some((temp_output, lines)));
         } else {
-            out.flush().unwrap();
+            output::enqueue(&permanent, None);
         }
 
         Ok(is_animating)
     }
+
+    /// Sets the title of the terminal window, see [`set_window_title_progress`].
+    ///
+    /// The original title is saved when the window title is first set, and restored when `title` is None.
+    fn update_window_title(&mut self, title: Option<String>, out: &mut String) -> std::fmt::Result {
+        if title == self.window_title {
+            return Ok(());
+        }
+        match (&self.window_title, &title) {
+            // Push the original title to the terminal's title stack
+            (None, Some(_)) => write!(out, "\u{001b}[22;0t")?,
+            // Pop the original title from the stack
+            (Some(_), None) => write!(out, "\u{001b}[23;0t")?,
+            _ => {}
+        }
+        if let Some(title) = &title {
+            write!(out, "\u{001b}]2;{}\u{0007}", title)?;
+        }
+        self.window_title = title;
+        Ok(())
+    }
+}
+
+/// Queues a frame to be passed to the [`ProgressBarManager::frame_hooks`].
+fn push_frame(pending_frames: &mut Vec<RenderedFrame>, frame: RenderedFrame) {
+    // Avoid unbounded memory usage if frames are produced faster than the hooks are invoked
+    if pending_frames.len() >= MAX_PENDING_FRAMES {
+        pending_frames.remove(0);
+    }
+    pending_frames.push(frame);
+}
+
+/// Makes the line which starts at `start` occupy exactly one row of the terminal. See [`text::fit_to_row`].
+fn fit_last_line(out: &mut String, start: usize, width: Option<usize>) {
+    if let Cow::Owned(fitted) = text::fit_to_row(&out[start..], width) {
+        out.truncate(start);
+        out.push_str(&fitted);
+    }
+}
+
+/// Writes the label of a group of bars, see [`scope_label`].
+///
+/// The line is fitted to the width like the lines of the bars, so that it never wraps.
+fn write_section_header(out: &mut String, label: &str, color: bool, width: Option<usize>) {
+    let start = out.len();
+    if color {
+        out.push_str("\u{001b}[1m");
+    }
+    out.push_str(label);
+    out.push(':');
+    if color {
+        out.push_str("\u{001b}[0m");
+    }
+    fit_last_line(out, start, width);
+    out.push('\n');
+}
+
+/// Updates an exponential moving average of a rate, with `items` having been processed during the last `elapsed` time.
+///
+/// The weight of the previous average depends on how long ago it was, so that updates can come at irregular intervals.
+fn smooth_rate(previous: Option<f64>, items: usize, elapsed: Duration, half_life: Duration) -> f64 {
+    let elapsed = elapsed.as_secs_f64();
+    let current = items as f64 / elapsed.max(f64::EPSILON);
+    match previous {
+        Some(previous) => {
+            let weight = 0.5f64.powf(elapsed / half_life.as_secs_f64().max(f64::EPSILON));
+            previous * weight + current * (1.0 - weight)
+        }
+        None => current,
+    }
+}
+
+/// Renders a bar, reusing its line from the previous frame if nothing which affects it has changed.
+///
+/// The `state_hash` is the hash of the bar computed by [`ProgressBarManager::hash_state`], if it is up to date.
+/// Lines which are reused, or which could be reused in the next frame, are moved from `previous` to `next`.
+/// See [`ProgressBarManager::render_cache`].
+fn render_cached(
+    bar: &ProgressBarState,
+    state_hash: Option<u64>,
+    out: &mut String,
+    ctx: &RenderContext,
+    is_animating: &mut bool,
+    previous: &mut HashMap<u64, (u64, String)>,
+    next: &mut HashMap<u64, (u64, String)>,
+) -> std::fmt::Result {
+    // Extra fields and formatters may display anything, so they must be rendered every frame
+    let Some(state_hash) =
+        state_hash.filter(|_| bar.extra_fields.is_empty() && bar.formatter.is_none())
+    else {
+        return bar.render(out, ctx, is_animating);
+    };
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u64(state_hash);
+    ctx.color.hash(&mut hasher);
+    ctx.width.hash(&mut hasher);
+    // Times, like the elapsed time and the estimated time left, are displayed with a resolution of one second
+    clock::since(*ctx.reference_time)
+        .as_secs()
+        .hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some((cached_key, line)) = previous.remove(&bar.id) {
+        if cached_key == key {
+            out.push_str(&line);
+            next.insert(bar.id, (key, line));
+            return Ok(());
+        }
+    }
+    let start = out.len();
+    let mut animating = false;
+    bar.render(out, ctx, &mut animating)?;
+    if animating {
+        *is_animating = true;
+    } else {
+        next.insert(bar.id, (key, out[start..].to_string()));
+    }
+    Ok(())
+}
+
+/// Enables or disables accessible output.
+///
+/// In accessible mode nothing is animated and the cursor is never moved to redraw bars.
+/// Instead, the status of all bars is occasionally (at most every 10 seconds) printed as a line of plain text, like
+///
+/// ```text
+/// Downloading 45% (45/100): assets.tar.gz
+/// ```
+///
+/// This is much more suitable for e.g. screen readers than rapidly changing progress bars.
+///
+/// Accessible mode is enabled by default if the `HEADWAY_ACCESSIBLE` environment variable is set to something other than `0`.
+pub fn set_accessible_mode(enabled: bool) {
+    MANAGER.lock().accessible = enabled;
+}
+
+/// Keeps finished and abandoned bars visible in the area where progress bars are drawn, greyed out, until [`clear_finished`] is called.
+///
+/// By default finished bars are printed once above the other bars, and are then no longer managed.
+/// Retaining them is useful for dashboard-like applications, which may clear the screen.
+/// This only has an effect when the output is a terminal, and not in accessible mode (see [`set_accessible_mode`]).
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// headway::set_retain_finished(true);
+/// for job in ["fetch", "build", "test"] {
+///     let p = ProgressBar::new().with_message(job);
+///     for _ in p.wrap(0..100) {}
+/// }
+/// // All three bars are still displayed
+/// headway::clear_finished();
+/// ```
+pub fn set_retain_finished(enabled: bool) {
+    MANAGER.lock().retain_finished = enabled;
+}
+
+/// Limits the number of lines used to draw bars, including titles and the labels of [`scope_label`] sections.
+///
+/// If there are more bars than fit, the last line instead displays how many bars were left out, like `… 12 more`.
+/// The bars are always limited to the height of the terminal, see also [`reserve_bottom_lines`]. Use None to remove the limit.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// headway::set_max_lines(Some(5));
+/// let bars = (0..20)
+///     .map(|i| ProgressBar::new().with_message(format!("worker {}", i)))
+///     .collect::<Vec<_>>();
+/// ```
+pub fn set_max_lines(max: Option<usize>) {
+    MANAGER.lock().max_lines = max;
+    redraw();
+}
+
+/// Keeps the given number of lines at the bottom of the terminal free for the application, for example to draw a prompt.
+///
+/// Bars are drawn starting at the cursor, and the cursor is moved back to the first line of the bars after each frame,
+/// so that text printed to stdout replaces the bars. Only the lines of the bars are cleared when they are redrawn,
+/// so anything the application draws on the lines below them is kept. Bars which would extend into the reserved lines are left out
+/// in the same way as for [`set_max_lines`]. The number of lines which are currently used can be read using [`reserved_lines`].
+///
+/// The line right below the last bar is always counted as the first reserved line, since the terminal would otherwise scroll when the last bar is drawn.
+pub fn reserve_bottom_lines(lines: usize) {
+    MANAGER.lock().reserved_bottom_lines = lines;
+    redraw();
+}
+
+/// The number of lines which bars occupied on the terminal in the last frame.
+///
+/// The lines start at the cursor position. Applications which draw their own content below the bars,
+/// like a prompt, can use this to find out where the bars end. This is zero when the output is not a terminal,
+/// and in accessible mode (see [`set_accessible_mode`]).
+///
+/// ```
+/// let lines = headway::reserved_lines();
+/// println!("The bars use {} lines", lines);
+/// ```
+pub fn reserved_lines() -> usize {
+    MANAGER.lock().drawn_lines
+}
+
+/// Renders all bars which are currently displayed, one per line, without writing anything to the terminal.
+///
+/// The lines are the same as in the terminal, including the title (see [`set_title`]) and the labels of [`scope_label`] sections,
+/// but without colors. See also [`ProgressBar::render_line`] for rendering a single bar.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// let p = ProgressBar::new().with_length(4).with_message("Copying");
+/// p.set_position(1);
+/// let status = headway::render_to_string();
+/// assert!(status.contains("1/4 Copying\n"));
+/// ```
+pub fn render_to_string() -> String {
+    let manager = MANAGER.lock();
+    let ctx = RenderContext {
+        color: false,
+        reference_time: &manager.reference_time,
+        default_style: &manager.default_style,
+        width: None,
+    };
+    let mut out = String::new();
+    if let Some(title) = manager.title.as_ref().filter(|_| !manager.bars.is_empty()) {
+        out.push_str(title);
+        out.push('\n');
+    }
+    for (label, bars) in scope::sections(&manager.bars) {
+        if let Some(label) = label {
+            write_section_header(&mut out, &label, false, None);
+        }
+        for bar in bars {
+            // SAFETY: Writes to strings cannot fail
+            bar.lock().render(&mut out, &ctx, &mut false).unwrap();
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Removes all finished and abandoned bars which have been retained using [`set_retain_finished`].
+pub fn clear_finished() {
+    {
+        let mut manager = MANAGER.lock();
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut manager.bars)
+            .into_iter()
+            .partition(|bar| bar.lock().is_dropped(bar));
+        manager.bars = kept;
+        trace::remember(&mut manager.trace_history, removed.into_iter());
+    }
+    redraw();
+}
+
+/// Marks every bar which is still in progress as finished, including nested bars, and draws the final state of all bars.
+///
+/// This is useful in shutdown handlers, to leave the terminal in a consistent state with a single call.
+/// Bars are still displayed until their [`ProgressBar`] handles have been dropped, but later updates cannot make them abandoned again.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// let workers = (0..4).map(|_| ProgressBar::new().with_length(100)).collect::<Vec<_>>();
+/// headway::finish_all();
+/// ```
+pub fn finish_all() {
+    {
+        let manager = MANAGER.lock();
+        for bar in &manager.bars {
+            bar.lock().end_recursive(LifecycleState::Completed);
+        }
+    }
+    redraw();
+}
+
+/// Marks every bar which is still in progress as abandoned, including nested bars, and draws the final state of all bars.
+///
+/// This is useful in error handlers, to leave the terminal in a consistent state with a single call.
+/// Bars are still displayed until their [`ProgressBar`] handles have been dropped, but later updates cannot make them finished again.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// let workers = (0..4).map(|_| ProgressBar::new().with_length(100)).collect::<Vec<_>>();
+/// // Something went wrong
+/// headway::abandon_all();
+/// ```
+pub fn abandon_all() {
+    {
+        let manager = MANAGER.lock();
+        for bar in &manager.bars {
+            bar.lock().end_recursive(LifecycleState::Abandoned);
+        }
+    }
+    redraw();
+}
+
+/// Sets a header which is displayed above all progress bars, like `Backing up 3 volumes`.
+///
+/// This gives the bars a clear context, especially when several of them are visible at the same time.
+/// The title is only displayed while there are visible bars. An empty string removes the title.
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// headway::set_title("Backing up 3 volumes");
+/// for (bar, volume) in ProgressBar::new().split_each(["home", "var", "srv"].into_iter()) {
+///     bar.set_message(volume);
+///     // ...
+/// }
+/// headway::set_title("");
+/// ```
+pub fn set_title(title: impl Into<String>) {
+    let title = title.into();
+    MANAGER.lock().title = if title.is_empty() { None } else { Some(title) };
+}
+
+/// A function called regularly by the [`manager_thread`]. It is removed when it returns false.
+type Poller = Box<dyn FnMut() -> bool + Send>;
+
+/// Registers a function which will be called regularly (roughly every 20 ms) by the [`manager_thread`].
+///
+/// The function is called without holding any locks. Once it returns false, it will not be called again.
+pub(crate) fn add_poller(poller: impl FnMut() -> bool + Send + 'static) {
+    let mut manager = MANAGER.lock();
+    manager.pollers.push(Box::new(poller));
+    manager.ensure_thread_started();
+}
+
+/// Renders all bars immediately, and waits until the output has been written.
+///
+/// This is used when bars are finished, so that their final state is always written before the program exits.
+/// When over the budget set using [`set_output_budget`], only finished bars are written, and the bars which are still in progress
+/// are drawn by the manager thread once the budget allows it.
+///
+/// Locks are always acquired in the order [`MANAGER`], individual bars.
+/// The `stdout` lock is never held at the same time as [`MANAGER`], so that a slow terminal never blocks other threads from updating bars.
+pub(crate) fn redraw() {
+    #[cfg(feature = "log")]
+    let log_lines;
+    {
+        let mut manager = MANAGER.lock();
+        manager.defer_frame = !output::within_budget();
+        manager.tick().unwrap();
+        manager.defer_frame = false;
+        #[cfg(feature = "log")]
+        {
+            log_lines = std::mem::take(&mut manager.pending_log);
+        }
+    }
+    output::write_pending();
+    #[cfg(feature = "log")]
+    logging::emit(log_lines);
+}
+
+/// Called when the process exits, for example when `main` returns while other threads still have bars.
+///
+/// Renders the last state of all remaining bars permanently, so that the cursor is not left in the middle of the bars,
+/// and restores the window title.
+extern "C" fn exit_handler() {
+    // Styles and formatters may run user code which panics, and unwinding out of this function would abort the process
+    let _ = std::panic::catch_unwind(draw_on_exit);
+}
+
+/// Draws the final frame for [`exit_handler`].
+///
+/// Other threads keep running while the process exits, and may be holding any lock. This never blocks on them,
+/// and instead gives up on the final frame if something it needs is busy.
+fn draw_on_exit() {
+    // Another thread may be holding the lock while being torn down, or this thread may be exiting while holding it.
+    // Give up after a while instead of blocking the exit forever.
+    let deadline = Instant::now() + Duration::from_millis(100);
+    let mut manager = loop {
+        if let Some(manager) = MANAGER.try_lock() {
+            break manager;
+        }
+        if Instant::now() > deadline {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    if manager.bars.is_empty() && manager.window_title.is_none() && manager.fullscreen.is_none() {
+        return;
+    }
+    // Rendering locks every bar, which would block forever if one of them is held
+    if any_locked(&manager.bars) {
+        return;
+    }
+    manager.exiting = true;
+    let _ = manager.tick();
+    #[cfg(feature = "log")]
+    let log_lines = std::mem::take(&mut manager.pending_log);
+    drop(manager);
+    #[cfg(feature = "log")]
+    logging::emit(log_lines);
+    // Writing to a slow terminal may take a while, but must not block the exit forever either
+    output::write_pending_until(Instant::now() + Duration::from_secs(1));
+}
+
+/// True if any of the bars, or any of their children, is locked.
+fn any_locked(bars: &[Arc<Mutex<ProgressBarState>>]) -> bool {
+    bars.iter().any(|bar| match bar.try_lock() {
+        Some(bar) => bar
+            .nested
+            .as_ref()
+            .is_some_and(|nested| any_locked(&nested.bars)),
+        None => true,
+    })
 }
 
 /// Thread which runs while progress bars are visible
@@ -666,25 +3277,94 @@ fn manager_thread() {
     let mut last_state = 0;
     let mut last_update = Instant::now();
     let mut is_animating = false;
+    let mut pending_stalls = vec![];
+    let mut pollers = vec![];
+    let mut frame_hooks = vec![];
+    let mut frames = vec![];
+    let mut change_hooks = vec![];
+    let mut changes = vec![];
+    #[cfg(feature = "log")]
+    let mut log_lines = vec![];
+    let mut frame_period = config::config().frame_period();
     loop {
-        {
-            let stdout = stdout();
-            let mut out = stdout.lock();
+        // Reduce the frame rate if the terminal can't keep up, e.g. over a slow remote connection
+        let latency = output::write_latency();
+        let min_period = if latency > WRITE_LATENCY_BUDGET {
+            latency * SLOW_TERMINAL_WRITE_FRACTION
+        } else {
+            Duration::ZERO
+        }
+        .max(output::budget_period())
+        .max(frame_period.unwrap_or_default());
 
-            let mut manager = MANAGER.lock().unwrap();
-            if manager.bars.is_empty() {
+        {
+            let mut manager = MANAGER.lock();
+            if manager.bars.is_empty()
+                && manager.pollers.is_empty()
+                && manager.pending_frames.is_empty()
+                && manager.pending_changes.is_empty()
+            {
                 manager.thread_started = false;
                 return;
             }
+            pollers.append(&mut manager.pollers);
+            if let Some(fullscreen) = &mut manager.fullscreen {
+                fullscreen.receive_commands();
+            }
+            // The refresh rate of the dashboard can be changed while it is displayed
+            frame_period = manager.frame_period();
 
             let h = manager.hash_state();
-            let update_period = if is_animating { 33 } else { 200 };
-            if h != last_state || last_update.elapsed() > Duration::from_millis(update_period) {
+            let update_period = if manager.accessible {
+                Duration::from_millis(1000)
+            } else if is_animating {
+                frame_period.unwrap_or(Duration::from_millis(33))
+            } else {
+                Duration::from_millis(200).max(frame_period.unwrap_or_default())
+            };
+            if (h != last_state || last_update.elapsed() > update_period)
+                && last_update.elapsed() >= min_period
+            {
                 last_state = h;
                 last_update = Instant::now();
-                is_animating = manager.tick(&mut out).unwrap();
+                is_animating = manager.tick().unwrap();
             }
+
+            for bar in &manager.bars {
+                watchdog::collect_stalls(bar, &mut pending_stalls);
+            }
+            frames.append(&mut manager.pending_frames);
+            frame_hooks.append(&mut manager.frame_hooks);
+            changes.append(&mut manager.pending_changes);
+            change_hooks.append(&mut manager.change_hooks);
+            #[cfg(feature = "log")]
+            log_lines.append(&mut manager.pending_log);
+        }
+
+        // Invoke callbacks without holding any locks, since they may want to interact with progress bars.
+        for stall in pending_stalls.drain(..) {
+            stall.invoke();
         }
-        thread::sleep(Duration::from_millis(20));
+        pollers.retain_mut(|poll| poll());
+        for frame in frames.drain(..) {
+            for hook in &mut frame_hooks {
+                hook(&frame);
+            }
+        }
+        changes::invoke_hooks(&mut change_hooks, &changes);
+        changes.clear();
+        #[cfg(feature = "log")]
+        logging::emit(std::mem::take(&mut log_lines));
+
+        let mut manager = MANAGER.lock();
+        manager.pollers.append(&mut pollers);
+        // Keep hooks registered while we were invoking them after the existing ones
+        frame_hooks.append(&mut manager.frame_hooks);
+        std::mem::swap(&mut manager.frame_hooks, &mut frame_hooks);
+        change_hooks.append(&mut manager.change_hooks);
+        std::mem::swap(&mut manager.change_hooks, &mut change_hooks);
+        drop(manager);
+
+        thread::sleep(POLL_PERIOD.min(frame_period.unwrap_or(POLL_PERIOD)));
     }
 }
@@ -0,0 +1,50 @@
+use crate::{ProgressBarManager, MANAGER};
+
+/// The target of the log records, which can be used to filter them
+const LOG_TARGET: &str = "progress";
+
+/// Sends the status of the bars to the [log](https://docs.rs/log) crate instead of writing it to stdout, when stdout is not a terminal.
+///
+/// Finished and abandoned bars are logged once, and the status of the other bars is logged at most every 10 seconds, as plain text like
+///
+/// ```text
+/// Downloading 45% (45/100): assets.tar.gz
+/// ```
+///
+/// Records are logged at the `info` level with the target `progress`, so they obey the log filtering and formatting of the application.
+/// When stdout is a terminal, the bars are drawn as usual.
+///
+/// Requires the `log` feature.
+///
+/// ```
+/// headway::set_log_output(true);
+/// ```
+pub fn set_log_output(enabled: bool) {
+    let mut manager = MANAGER.lock();
+    manager.log_output = enabled;
+    if manager.needs_thread() && !manager.bars.is_empty() {
+        manager.ensure_thread_started();
+    }
+}
+
+/// Sends lines collected by [`ProgressBarManager::log_report`] to the log.
+///
+/// Must be called without holding any locks, since the logger may print progress bars or create new ones.
+pub(crate) fn emit(lines: Vec<String>) {
+    for line in lines {
+        log::info!(target: LOG_TARGET, "{}", line);
+    }
+}
+
+impl ProgressBarManager {
+    /// Collects the given lines of finished bars, and occasionally the status of the other bars, to be logged by [`emit`].
+    /// See [`set_log_output`].
+    pub(crate) fn log_report(&mut self, finished: &str) -> std::fmt::Result {
+        self.pending_log
+            .extend(finished.lines().map(str::to_string));
+        if let Some(report) = self.plain_report()? {
+            self.pending_log.extend(report.lines().map(str::to_string));
+        }
+        Ok(())
+    }
+}
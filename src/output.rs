@@ -0,0 +1,263 @@
+use std::io::Write;
+use std::sync::{mpsc, LazyLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{config, Target};
+use crate::sync::{Condvar, Mutex};
+
+/// Output which has been rendered, but not yet written to stdout.
+///
+/// Writing to a slow terminal (e.g. over SSH or a serial link) can take a long time.
+/// To avoid stalling the [`manager_thread`](crate::manager_thread), output is written by a separate thread.
+/// If frames are rendered faster than they can be written, all but the latest frame are dropped.
+struct OutputQueue {
+    /// Text which must always be written, like finished bars. Written before the frame.
+    permanent: String,
+    /// The latest frame of the bars which are still in progress, ending with the cursor at the start of the frame,
+    /// and the number of lines it occupies
+    frame: Option<(String, usize)>,
+    /// True if the [`writer_thread`] is running
+    thread_started: bool,
+    /// Smoothed time it takes to write a frame to stdout
+    write_latency: Duration,
+    /// True if frames should be wrapped in synchronized output escape codes, see [`set_synchronized_output`]
+    synchronized: bool,
+    /// Smoothed number of bytes written each time output is written
+    write_size: f64,
+    /// When output was last written
+    last_write: Option<Instant>,
+    /// Maximum number of bytes per second to write, see [`set_output_budget`]
+    budget: Option<usize>,
+    /// Number of lines of the last frame which was written, if it has not been cleared yet
+    visible_lines: usize,
+}
+
+impl Default for OutputQueue {
+    fn default() -> Self {
+        Self {
+            permanent: String::new(),
+            frame: None,
+            thread_started: false,
+            write_latency: Duration::ZERO,
+            synchronized: crate::terminal::synchronized_output_from_env(),
+            write_size: 0.0,
+            last_write: None,
+            budget: None,
+            visible_lines: 0,
+        }
+    }
+}
+
+impl OutputQueue {
+    fn budget_period(&self) -> Duration {
+        match self.budget {
+            Some(budget) => Duration::from_secs_f64(self.write_size / budget.max(1) as f64),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+static OUTPUT: LazyLock<Mutex<OutputQueue>> = LazyLock::new(Default::default);
+static OUTPUT_AVAILABLE: LazyLock<Condvar> = LazyLock::new(Condvar::new);
+
+/// Queues output to be written to stdout.
+///
+/// The `permanent` text is always written, while `frame` replaces any previously queued frame which has not been written yet.
+/// The frame is given together with the number of lines it occupies, which are cleared before anything else is written.
+/// Frames which clear the screen themselves occupy no lines.
+pub(crate) fn enqueue(permanent: &str, frame: Option<(String, usize)>) {
+    let mut queue = OUTPUT.lock();
+    queue.permanent.push_str(permanent);
+    if frame.is_some() {
+        queue.frame = frame;
+    }
+    if !queue.thread_started {
+        queue.thread_started = true;
+        thread::spawn(writer_thread);
+    }
+    OUTPUT_AVAILABLE.notify_one();
+}
+
+/// Writes all queued output to stdout (or wherever the bars are drawn, see [`Config::with_target`](crate::Config::with_target)), blocking until it has been written.
+///
+/// Locks are acquired in the order `stdout`, [`OUTPUT`], so that output is always written in the order it was queued.
+pub(crate) fn write_pending() {
+    if let Some(target) = &config().draw_target {
+        // There is no way to know if other writers are buffered, so they are treated like stderr
+        let mut writer = target.target.lock();
+        write_pending_to(&mut *writer, true);
+        target.update_info(&**writer);
+        return;
+    }
+    match config().target {
+        Target::Stdout => write_pending_to(&mut std::io::stdout().lock(), false),
+        Target::Stderr => write_pending_to(&mut std::io::stderr().lock(), true),
+    }
+}
+
+/// Like [`write_pending`], but gives up at the deadline if the output is locked, for example by a thread which is printing.
+///
+/// Used when the process exits, where blocking would prevent the process from exiting. See [`crate::exit_handler`].
+pub(crate) fn write_pending_until(deadline: Instant) {
+    if let Some(target) = &config().draw_target {
+        loop {
+            if let Some(mut writer) = target.target.try_lock() {
+                write_pending_to(&mut *writer, true);
+                return;
+            }
+            if Instant::now() > deadline {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    // The standard streams cannot be locked without blocking, so they are written by another thread, which is abandoned at the deadline
+    let (done, written) = mpsc::channel();
+    let writer = thread::Builder::new().spawn(move || {
+        write_pending();
+        let _ = done.send(());
+    });
+    if writer.is_ok() {
+        let _ = written.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+fn write_pending_to(out: &mut impl Write, unbuffered: bool) {
+    let (permanent, frame, synchronized, visible_lines) = {
+        let mut queue = OUTPUT.lock();
+        (
+            std::mem::take(&mut queue.permanent),
+            queue.frame.take(),
+            queue.synchronized,
+            queue.visible_lines,
+        )
+    };
+    if permanent.is_empty() && frame.is_none() {
+        return;
+    }
+
+    // Unbuffered output cannot keep the clear buffered until the next write (see below).
+    // Instead the previous frame is cleared right before it is drawn over.
+    let clear = if unbuffered {
+        clear_frame(visible_lines)
+    } else {
+        String::new()
+    };
+
+    // Errors are ignored, there is nothing sensible to do if stdout is closed
+    let start = Instant::now();
+    let mut written = permanent.len();
+    let lines = frame.as_ref().map_or(0, |(_, lines)| *lines);
+    if let Some((frame, _)) = frame {
+        written += frame.len();
+        if synchronized {
+            // Ask the terminal to display the whole frame at once, to avoid flickering
+            let _ = write!(
+                out,
+                "\u{001b}[?2026h{}{}{}\u{001b}[?2026l",
+                clear, permanent, frame
+            );
+        } else {
+            let _ = write!(out, "{}{}{}", clear, permanent, frame);
+        }
+        let _ = out.flush();
+        if !unbuffered {
+            // Clear the lines of the bars.
+            // DO NOT flush after this as that would remove the progress bars.
+            // If something is printed to stdout, this will first remove the progress bars and then print the text.
+            let _ = write!(out, "{}", clear_frame(lines));
+        }
+    } else {
+        let _ = write!(out, "{}{}", clear, permanent);
+        let _ = out.flush();
+    }
+
+    let mut queue = OUTPUT.lock();
+    queue.visible_lines = lines;
+    queue.write_latency = queue.write_latency.mul_f64(0.8) + start.elapsed().mul_f64(0.2);
+    queue.write_size = if queue.write_size == 0.0 {
+        written as f64
+    } else {
+        queue.write_size * 0.8 + written as f64 * 0.2
+    };
+    queue.last_write = Some(start);
+}
+
+/// Clears the lines of a frame which starts at the cursor, and leaves the cursor at the start of its first line.
+///
+/// Only the lines of the frame itself are cleared, so that lines below it which are used by the application are kept,
+/// see [`reserve_bottom_lines`](crate::reserve_bottom_lines). This never contains a newline, since that would flush stdout.
+fn clear_frame(lines: usize) -> String {
+    let mut clear = String::new();
+    for line in 0..lines {
+        if line > 0 {
+            clear.push_str("\u{001b}[1E");
+        }
+        clear.push_str("\u{001b}[2K");
+    }
+    if lines > 1 {
+        clear.push_str(&format!("\u{001b}[{}F", lines - 1));
+    }
+    clear
+}
+
+/// The smoothed time it takes to write a frame to stdout.
+pub(crate) fn write_latency() -> Duration {
+    OUTPUT.lock().write_latency
+}
+
+/// The minimum time between frames needed to stay within the budget set using [`set_output_budget`].
+pub(crate) fn budget_period() -> Duration {
+    OUTPUT.lock().budget_period()
+}
+
+/// True if enough time has passed since output was last written, that another frame can be written within the budget.
+pub(crate) fn within_budget() -> bool {
+    let queue = OUTPUT.lock();
+    queue
+        .last_write
+        .is_none_or(|t| t.elapsed() >= queue.budget_period())
+}
+
+/// Limits how many bytes per second are written to the terminal, or removes the limit if `None`.
+///
+/// The frame rate is reduced so that the average amount of output stays within the budget.
+/// This makes the bars update less often when many bars are visible, since each frame is larger.
+/// It is useful for protecting log size quotas in CI, or keeping remote sessions responsive.
+/// Finished bars are always written, even if that exceeds the budget.
+///
+/// By default there is no limit.
+///
+/// ```
+/// // At most 4 KiB per second
+/// headway::set_output_budget(Some(4 * 1024));
+/// ```
+pub fn set_output_budget(bytes_per_second: Option<usize>) {
+    OUTPUT.lock().budget = bytes_per_second;
+}
+
+/// Enables or disables synchronized output.
+///
+/// When enabled, each frame is wrapped in escape codes which ask the terminal to display the whole frame at once
+/// (known as synchronized output, or DEC mode 2026). This avoids flickering, especially when many bars are visible.
+/// Terminals which do not support synchronized output should ignore these escape codes, but some display them as garbage.
+///
+/// Enabled by default only in terminals which are known to support it, like kitty, WezTerm, iTerm2, Ghostty, Alacritty, foot,
+/// Windows Terminal and the terminal of Visual Studio Code.
+pub fn set_synchronized_output(enabled: bool) {
+    OUTPUT.lock().synchronized = enabled;
+}
+
+/// Thread which writes queued output to stdout
+fn writer_thread() {
+    loop {
+        {
+            let mut queue = OUTPUT.lock();
+            while queue.permanent.is_empty() && queue.frame.is_none() {
+                queue = OUTPUT_AVAILABLE.wait(queue);
+            }
+        }
+        write_pending();
+    }
+}
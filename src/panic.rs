@@ -0,0 +1,52 @@
+use std::cell::RefCell;
+use std::panic::PanicHookInfo;
+use std::thread;
+
+thread_local! {
+    /// The message of the most recent panic on this thread
+    static PANIC_MESSAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook which remembers the panic message of each thread, before calling the previous hook.
+///
+/// Bars which are dropped while their thread is unwinding use the message to explain why they were abandoned.
+/// If the application replaces the hook afterwards, bars are still abandoned, just without the message.
+pub(crate) fn install_hook() {
+    // Changing the hook while panicking is not allowed
+    if thread::panicking() {
+        return;
+    }
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = describe(info);
+        // Ignore the panic if the thread is being torn down, and the thread local is already gone
+        let _ = PANIC_MESSAGE.try_with(|m| *m.borrow_mut() = Some(message));
+        previous(info);
+    }));
+}
+
+/// The panic message of the current thread, if it is currently unwinding from a panic.
+pub(crate) fn current_message() -> Option<String> {
+    if !thread::panicking() {
+        return None;
+    }
+    PANIC_MESSAGE
+        .try_with(|m| m.borrow().clone())
+        .ok()
+        .flatten()
+}
+
+/// Formats the panic like `panicked at src/main.rs:10:5: index out of bounds`, using only the first line of the message.
+fn describe(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("Box<dyn Any>");
+    let message = message.lines().next().unwrap_or("");
+    match info.location() {
+        Some(location) => format!("panicked at {}: {}", location, message),
+        None => format!("panicked: {}", message),
+    }
+}
@@ -0,0 +1,155 @@
+//! Implementations of the [prodash](https://docs.rs/prodash) progress traits, enabled by the `prodash` feature.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ::prodash::messages::MessageLevel;
+use ::prodash::progress::{Id, Step, StepShared, UNKNOWN};
+use ::prodash::{Count, NestedProgress, Progress, Unit};
+
+use crate::clock;
+use crate::sync::Mutex;
+use crate::{add_poller, LifecycleState, NestedBars, NestedMeta, ProgressBar, ProgressBarState};
+
+impl Count for ProgressBar {
+    fn set(&self, step: Step) {
+        self.set_position(step);
+    }
+
+    fn step(&self) -> Step {
+        self.state.as_ref().map(|s| s.lock().position).unwrap_or(0)
+    }
+
+    fn inc_by(&self, step: Step) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.position += step;
+            state.record_progress();
+        }
+    }
+
+    /// Returns a counter which is copied to the position of the bar by the background thread.
+    ///
+    /// The bar follows the counter whenever it changes, until the bar is finished or all clones of the counter have been dropped.
+    /// Updates made using [`Count::set`] are not reflected in the counter.
+    fn counter(&self) -> StepShared {
+        let counter = Arc::new(AtomicUsize::new(self.step()));
+        let Some(state) = &self.state else {
+            return counter;
+        };
+
+        let state = Arc::downgrade(state);
+        let shared = counter.clone();
+        let mut last = shared.load(Ordering::Relaxed);
+        add_poller(move || {
+            let Some(state) = state.upgrade() else {
+                return false;
+            };
+            let mut state = state.lock();
+            // Stop following the counter once nobody else can update it
+            if state.lifecycle != LifecycleState::InProgress || Arc::strong_count(&shared) == 1 {
+                return false;
+            }
+            let value = shared.load(Ordering::Relaxed);
+            if value != last {
+                last = value;
+                state.position = value;
+                state.record_progress();
+            }
+            true
+        });
+        counter
+    }
+}
+
+impl Progress for ProgressBar {
+    /// Sets the length of the bar. The unit is ignored.
+    fn init(&mut self, max: Option<Step>, _unit: Option<Unit>) {
+        if let Some(state) = &self.state {
+            state.lock().set_length(max);
+        }
+    }
+
+    fn max(&self) -> Option<Step> {
+        self.state.as_ref().and_then(|s| s.lock().length)
+    }
+
+    fn set_max(&mut self, max: Option<Step>) -> Option<Step> {
+        let state = self.state.as_ref()?;
+        let mut state = state.lock();
+        let previous = state.length;
+        state.set_length(max);
+        previous
+    }
+
+    /// Sets the prefix of the bar.
+    fn set_name(&mut self, name: String) {
+        self.set_prefix(name);
+    }
+
+    fn name(&self) -> Option<String> {
+        self.state.as_ref().and_then(|s| s.lock().prefix.clone())
+    }
+
+    fn id(&self) -> Id {
+        UNKNOWN
+    }
+
+    /// Displays the message next to the bar.
+    ///
+    /// A failure message also marks the bar as abandoned, even if it is later finished.
+    fn message(&self, level: MessageLevel, message: String) {
+        self.set_message(message);
+        if level == MessageLevel::Failure {
+            if let Some(state) = &self.state {
+                let mut state = state.lock();
+                state.lifecycle = LifecycleState::Abandoned;
+                state.finished_at.get_or_insert_with(clock::now);
+                state.abandoned_externally = true;
+            }
+        }
+    }
+}
+
+impl NestedProgress for ProgressBar {
+    type SubProgress = ProgressBar;
+
+    /// Adds a nested bar. The parent displays the sum of the progress of all its children, like [`ProgressBar::split_summed`].
+    ///
+    /// The child is marked as finished when it is dropped.
+    ///
+    /// If the bar has already been split in another way, like using [`ProgressBar::split_weighted`], its children are kept
+    /// and a hidden bar is returned instead, see [`ProgressBar::hidden`].
+    fn add_child(&mut self, name: impl Into<String>) -> Self::SubProgress {
+        let Some(state) = &self.state else {
+            return ProgressBar::hidden();
+        };
+
+        let child = ProgressBarState {
+            prefix: Some(name.into()),
+            finish_on_drop: true,
+            ..Default::default()
+        };
+        let child = Arc::new(Mutex::new(child));
+        let mut state = state.lock();
+        match &mut state.nested {
+            Some(NestedBars {
+                bars,
+                meta: NestedMeta::Summed,
+            }) => bars.push(child.clone()),
+            // Replacing the split would lose the progress of the existing children
+            Some(_) => return ProgressBar::hidden(),
+            nested @ None => {
+                *nested = Some(NestedBars {
+                    bars: vec![child.clone()],
+                    meta: NestedMeta::Summed,
+                })
+            }
+        }
+        ProgressBar { state: Some(child) }
+    }
+
+    fn add_child_with_id(&mut self, name: impl Into<String>, _id: Id) -> Self::SubProgress {
+        self.add_child(name)
+    }
+}
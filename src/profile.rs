@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::sync::Mutex;
+use crate::{LifecycleState, ProgressBarState};
+
+/// How long each part of a job took, used to calibrate the weights of [`ProgressBar::split_calibrated`](crate::ProgressBar::split_calibrated).
+///
+/// Profiles can be saved to a file after a job has finished, and loaded again the next time it runs.
+/// The file is a simple text file with one line per part, containing the duration in seconds and the name separated by a tab.
+///
+/// ```no_run
+/// use headway::{ProgressBar, TimingProfile};
+///
+/// let profile = TimingProfile::load("build.profile");
+/// let mut p = ProgressBar::new().split_calibrated(profile);
+/// let fetch = p.take("fetch", 0.2);
+/// let compile = p.take("compile", 0.8);
+/// // ...
+/// p.profile().save("build.profile").unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimingProfile {
+    durations: BTreeMap<String, Duration>,
+}
+
+impl TimingProfile {
+    /// Creates an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a profile from a file previously written by [`Self::save`].
+    ///
+    /// If the file does not exist or cannot be read, an empty profile is returned. Malformed lines are ignored.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let mut profile = Self::new();
+        if let Ok(contents) = std::fs::read_to_string(path) {
+            for line in contents.lines() {
+                if let Some((seconds, name)) = line.split_once('\t') {
+                    if let Ok(seconds) = seconds.parse::<f64>() {
+                        if seconds.is_finite() && seconds >= 0.0 {
+                            profile.set(name, Duration::from_secs_f64(seconds));
+                        }
+                    }
+                }
+            }
+        }
+        profile
+    }
+
+    /// Writes the profile to a file, replacing any previous contents.
+    ///
+    /// Names containing newlines cannot be represented in the file, and are skipped.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut contents = String::new();
+        for (name, duration) in &self.durations {
+            if !name.contains('\n') {
+                contents.push_str(&format!("{}\t{}\n", duration.as_secs_f64(), name));
+            }
+        }
+        std::fs::write(path, contents)
+    }
+
+    /// How long the part with the given name took, if it is known.
+    pub fn get(&self, name: &str) -> Option<Duration> {
+        self.durations.get(name).copied()
+    }
+
+    /// Sets how long the part with the given name took.
+    pub fn set(&mut self, name: impl Into<String>, duration: Duration) {
+        self.durations.insert(name.into(), duration);
+    }
+}
+
+/// A child of a calibrated split, see [`ProgressBar::split_calibrated`](crate::ProgressBar::split_calibrated).
+#[derive(Clone)]
+pub(crate) struct CalibratedWeight {
+    /// The weight guessed by the user
+    pub estimate: f64,
+    /// The duration in seconds from the timing profile, if known
+    pub profiled: Option<f64>,
+}
+
+/// How long each child took, in seconds. None for children which have not been finished.
+///
+/// Children are assumed to run one after another, so a child is considered to have started when it was created,
+/// or when the previous child finished, whichever is later.
+pub(crate) fn measured_durations(bars: &[Arc<Mutex<ProgressBarState>>]) -> Vec<Option<f64>> {
+    let times = bars
+        .iter()
+        .map(|bar| {
+            let bar = bar.lock();
+            (bar.created, bar.finished_at, bar.lifecycle)
+        })
+        .collect::<Vec<_>>();
+
+    times
+        .iter()
+        .map(|&(created, finished_at, lifecycle)| {
+            let finished_at = finished_at.filter(|_| lifecycle == LifecycleState::Completed)?;
+            let started = times
+                .iter()
+                .filter_map(|&(_, t, _)| t)
+                .filter(|&t| t < finished_at)
+                .fold(created, |a, b| a.max(b));
+            Some((finished_at - started).as_secs_f64())
+        })
+        .collect()
+}
+
+/// The weight of each child, normalized so that they sum up to 1.
+///
+/// Children with a known duration (either measured or from the profile) use that as their weight.
+/// The estimates of the other children are scaled by how long the known children took compared to their estimates.
+pub(crate) fn calibrated_weights(
+    weights: &[CalibratedWeight],
+    measured: &[Option<f64>],
+) -> Vec<f64> {
+    let known = weights
+        .iter()
+        .zip(measured)
+        .map(|(w, m)| m.or(w.profiled))
+        .collect::<Vec<_>>();
+
+    let (known_total, estimate_total) = weights
+        .iter()
+        .zip(&known)
+        .filter_map(|(w, k)| k.map(|k| (k, w.estimate)))
+        .fold((0.0, 0.0), |(a, b), (k, e)| (a + k, b + e));
+    let scale = if estimate_total > 0.0 {
+        known_total / estimate_total
+    } else {
+        1.0
+    };
+
+    let mut result = weights
+        .iter()
+        .zip(&known)
+        .map(|(w, k)| k.unwrap_or(w.estimate * scale))
+        .collect::<Vec<_>>();
+    let total: f64 = result.iter().sum();
+    if total > 0.0 {
+        for w in &mut result {
+            *w /= total;
+        }
+    }
+    result
+}
@@ -1,11 +1,18 @@
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::clock;
+use crate::core::{Aggregate, BarSnapshot};
+use crate::sync::Mutex;
 use crate::{
-    manager_thread, LifecycleState, NestedBars, NestedMeta, ProgressBarSizedNester,
-    ProgressBarSummedNester, MANAGER,
+    add_poller, redraw, LifecycleState, NestedBars, NestedMeta, ProgressBarCalibratedNester,
+    ProgressBarCustomNester, ProgressBarSizedNester, ProgressBarSummedNester, Stall, StallWatcher,
+    MANAGER,
 };
-use crate::{ProgressBarState, ProgressBarWeightedNester};
+use crate::{
+    AutoAdvance, ChunkInterpolation, ProgressBarState, ProgressBarWeightedNester, RateSmoothing,
+};
+use crate::{Color, ProgressSink, Style, TimingProfile};
 
 /// A convenient progress bar.
 ///
@@ -16,11 +23,43 @@ pub struct ProgressBar {
 
 impl Drop for ProgressBar {
     fn drop(&mut self) {
-        self.abandon();
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            if state.shared_handles > 0 {
+                // Other handles returned by [`ProgressBar::get_or_create`] keep the bar going
+                state.shared_handles -= 1;
+                drop(state);
+                self.state = None;
+                return;
+            }
+        }
+        if self.state.as_ref().is_some_and(|s| s.lock().finish_on_drop) {
+            self.finish();
+        } else {
+            if let Some(message) = crate::panic::current_message() {
+                if let Some(state) = &self.state {
+                    state.lock().abandon_reason.get_or_insert(message);
+                }
+            }
+            self.abandon();
+        }
+    }
+}
+
+/// Two handles are equal if they refer to the same bar, see [`ProgressBar::get_or_create`].
+///
+/// Handles of bars which have been finished or abandoned are not equal to any other handle.
+impl PartialEq for ProgressBar {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.state, &other.state) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
     }
 }
 
 impl Default for ProgressBar {
+    #[track_caller]
     fn default() -> Self {
         Self::new()
     }
@@ -41,15 +80,62 @@ impl ProgressBar {
     /// }
     /// ```
     #[doc=include_str!("../images/message.html")]
+    #[track_caller]
     pub fn new() -> Self {
-        let mut manager = MANAGER.lock().unwrap();
+        let mut manager = MANAGER.lock();
         let state = Arc::new(Mutex::new(ProgressBarState::default()));
         manager.bars.push(state.clone());
-        if manager.interactive_output && !manager.thread_started {
-            manager.thread_started = true;
-            thread::spawn(manager_thread);
+        if manager.needs_thread() {
+            manager.ensure_thread_started();
         }
-        Self { state: Some(state) }
+        let hooks = manager.creation_hooks.clone();
+        drop(manager);
+        let bar = Self { state: Some(state) };
+        crate::created::run_hooks(&bar, std::panic::Location::caller(), hooks);
+        bar
+    }
+
+    /// Returns the bar which was created for the given key, or creates a new one if there is none.
+    ///
+    /// This deduplicates bars for tasks which can be reported from several places, like a download which is
+    /// requested by two parts of a program at the same time. Only one bar is displayed for each key while it is in progress.
+    /// Once the bar has been finished or abandoned, the next call creates a new bar for the key.
+    ///
+    /// The bar is shared by all handles returned for the key. Dropping a handle only abandons the bar if no other handles are left,
+    /// but calling [`Self::finish`] or [`Self::abandon`] on any of them ends the bar for all of them.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let a = ProgressBar::get_or_create("download:assets.tar.gz").with_length(100);
+    /// let b = ProgressBar::get_or_create("download:assets.tar.gz");
+    /// assert!(a == b);
+    /// assert_eq!(b.length(), Some(100));
+    /// ```
+    #[track_caller]
+    pub fn get_or_create(key: impl Into<String>) -> Self {
+        let key = key.into();
+        let mut manager = MANAGER.lock();
+        if let Some(state) = manager.keyed_bars.get(&key).and_then(|bar| bar.upgrade()) {
+            let mut locked = state.lock();
+            if locked.lifecycle == LifecycleState::InProgress {
+                locked.shared_handles += 1;
+                drop(locked);
+                return Self { state: Some(state) };
+            }
+        }
+        let state = Arc::new(Mutex::new(ProgressBarState::default()));
+        manager.bars.push(state.clone());
+        manager.keyed_bars.retain(|_, bar| bar.strong_count() > 0);
+        manager.keyed_bars.insert(key, Arc::downgrade(&state));
+        if manager.needs_thread() {
+            manager.ensure_thread_started();
+        }
+        let hooks = manager.creation_hooks.clone();
+        drop(manager);
+        let bar = Self { state: Some(state) };
+        crate::created::run_hooks(&bar, std::panic::Location::caller(), hooks);
+        bar
     }
 
     /// Creates a new progress bar which will never be rendered.
@@ -60,6 +146,45 @@ impl ProgressBar {
         Self { state: Some(state) }
     }
 
+    /// Creates a new bar which displays the sum of several existing bars.
+    ///
+    /// The existing bars are no longer displayed separately, but otherwise keep working like before.
+    /// This is useful if for example several libraries give you their own bars, but you only want to display a single line.
+    /// The merged bar behaves like a bar created using [`Self::split_summed`].
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let a = ProgressBar::new().with_length(100);
+    /// let b = ProgressBar::new().with_length(50);
+    /// let merged = ProgressBar::merge([&a, &b]).with_message("Downloading");
+    /// for _ in a.wrap(0..100) {}
+    /// for _ in b.wrap(0..50) {}
+    /// ```
+    pub fn merge<'a>(bars: impl IntoIterator<Item = &'a ProgressBar>) -> Self {
+        let children = bars
+            .into_iter()
+            .filter_map(|bar| bar.state.clone())
+            .collect::<Vec<_>>();
+
+        let mut manager = MANAGER.lock();
+        manager
+            .bars
+            .retain(|bar| !children.iter().any(|c| Arc::ptr_eq(bar, c)));
+        let state = Arc::new(Mutex::new(ProgressBarState {
+            nested: Some(NestedBars {
+                bars: children,
+                meta: NestedMeta::Summed,
+            }),
+            ..Default::default()
+        }));
+        manager.bars.push(state.clone());
+        if manager.needs_thread() {
+            manager.ensure_thread_started();
+        }
+        Self { state: Some(state) }
+    }
+
     /// Splits the bar into children of given proportions.
     ///
     /// This is useful if you have many tasks, but you only want to show a single progress bar.
@@ -88,7 +213,6 @@ impl ProgressBar {
             .as_ref()
             .expect("You cannot split a finished/abandoned progress bar")
             .lock()
-            .unwrap()
             .nested = Some(NestedBars {
             bars: vec![],
             meta: NestedMeta::Weighted(vec![]),
@@ -131,7 +255,6 @@ impl ProgressBar {
             .as_ref()
             .expect("You cannot split a finished/abandoned progress bar")
             .lock()
-            .unwrap()
             .nested = Some(NestedBars {
             bars: vec![],
             meta: NestedMeta::Sized(vec![]),
@@ -170,7 +293,6 @@ impl ProgressBar {
             .as_ref()
             .expect("You cannot split a finished/abandoned progress bar")
             .lock()
-            .unwrap()
             .nested = Some(NestedBars {
             bars: vec![],
             meta: NestedMeta::Summed,
@@ -178,6 +300,77 @@ impl ProgressBar {
         ProgressBarSummedNester { bar: self }
     }
 
+    /// Splits the bar into named children, with weights that are calibrated based on how long each child takes.
+    ///
+    /// This works like [`Self::split_weighted`], but the weights are only used as initial guesses.
+    /// If the profile contains the duration of a child from a previous run, that duration is used as its weight instead.
+    /// While running, each time a child finishes, its measured duration becomes its weight and the guesses for the remaining children are scaled to match.
+    /// This makes the parent bar advance close to linearly in time, even if the initial guesses are wrong.
+    ///
+    /// The children are assumed to run one after another. Use [`ProgressBarCalibratedNester::profile`] to get the updated profile, to save it for the next run.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use std::thread::sleep;
+    /// use headway::{ProgressBar, TimingProfile};
+    ///
+    /// let mut p = ProgressBar::new().split_calibrated(TimingProfile::new());
+    /// // The guesses say that downloading is the slow part, but actually unpacking takes longer
+    /// let download = p.take("download", 0.8);
+    /// let unpack = p.take("unpack", 0.2);
+    /// for _ in download.wrap(0..10) {
+    ///     sleep(Duration::from_millis(10));
+    /// }
+    /// for _ in unpack.wrap(0..10) {
+    ///     sleep(Duration::from_millis(40));
+    /// }
+    /// let profile = p.profile();
+    /// assert!(profile.get("unpack").unwrap() > profile.get("download").unwrap());
+    /// ```
+    pub fn split_calibrated(self, profile: TimingProfile) -> ProgressBarCalibratedNester {
+        self.state
+            .as_ref()
+            .expect("You cannot split a finished/abandoned progress bar")
+            .lock()
+            .nested = Some(NestedBars {
+            bars: vec![],
+            meta: NestedMeta::Calibrated(vec![]),
+        });
+        ProgressBarCalibratedNester {
+            bar: self,
+            profile,
+            names: vec![],
+        }
+    }
+
+    /// Splits the bar into children, and calculates the progress of the parent from the children using a custom [`Aggregate`].
+    ///
+    /// This can be used when none of the other split modes fit, for example if the slowest child should define the progress.
+    /// Any closure taking a slice of [`BarSnapshot`](crate::core::BarSnapshot)s can be used as an aggregate.
+    ///
+    /// ```
+    /// use headway::core::Slowest;
+    /// use headway::ProgressBar;
+    ///
+    /// // Upload to several mirrors in parallel. The upload is only done when the slowest mirror is done.
+    /// let p = ProgressBar::new().split_custom(Slowest);
+    /// let mirrors = (0..3).map(|_| p.take()).collect::<Vec<_>>();
+    /// for (i, mirror) in mirrors.into_iter().enumerate() {
+    ///     for _ in mirror.wrap(0..(10 * (i + 1))) {}
+    /// }
+    /// ```
+    pub fn split_custom(self, aggregate: impl Aggregate + 'static) -> ProgressBarCustomNester {
+        self.state
+            .as_ref()
+            .expect("You cannot split a finished/abandoned progress bar")
+            .lock()
+            .nested = Some(NestedBars {
+            bars: vec![],
+            meta: NestedMeta::Custom(Arc::new(aggregate)),
+        });
+        ProgressBarCustomNester { bar: self }
+    }
+
     /// Splits the bar into N bars, each representing an item in the iterator.
     ///
     /// This is useful if each item takes a long time and you want progress for it.
@@ -206,10 +399,85 @@ impl ProgressBar {
         it.map(move |v| (splitter.take(1), v))
     }
 
+    /// The label of the [`scope_label`](crate::scope_label) this bar was created in, if any.
+    pub fn label(&self) -> Option<String> {
+        self.state
+            .as_ref()?
+            .lock()
+            .label
+            .as_deref()
+            .map(str::to_string)
+    }
+
+    /// The fraction of the parent which this bar represents, if it is a child of a [weighted split](Self::split_weighted).
+    pub fn weight(&self) -> Option<f64> {
+        self.with_weight_in_parent(|weight| *weight)
+    }
+
+    /// Changes the fraction of the parent which this bar represents, if it is a child of a [weighted split](Self::split_weighted).
+    ///
+    /// This is useful when a phase turns out to take much longer (or shorter) than estimated, so that the parent does not sit at
+    /// 90% for half of the runtime. Like for [`ProgressBarWeightedNester::take`], if the weights of all children add up to more than 1,
+    /// they are normalized so that the parent is still complete when all children are.
+    ///
+    /// This has no effect on bars which are not children of a weighted split, or if the parent has already been dropped.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let mut p = ProgressBar::new().split_weighted();
+    /// let download = p.take(0.9).with_message("Downloading");
+    /// let unpack = p.take(0.1).with_message("Unpacking");
+    /// for _ in download.wrap(0..100) {}
+    ///
+    /// // Unpacking turned out to take as long as downloading, so let it fill half of the parent
+    /// unpack.set_weight(0.9);
+    /// assert_eq!(unpack.weight(), Some(0.9));
+    /// for _ in unpack.wrap(0..100) {}
+    /// ```
+    pub fn set_weight(&self, weight: f64) {
+        assert!(
+            weight.is_finite() && (0.0..=1.0).contains(&weight),
+            "weight must be between 0 and 1"
+        );
+        self.with_weight_in_parent(|w| *w = weight);
+    }
+
+    /// Calls the function with this bar's weight in its parent, if it is a child of a weighted split.
+    fn with_weight_in_parent<T>(&self, f: impl FnOnce(&mut f64) -> T) -> Option<T> {
+        let state = self.state.as_ref()?;
+        // The parent locks its children, so the lock of this bar must be released before the parent is locked
+        let parent = state.lock().parent.upgrade()?;
+        let mut parent = parent.lock();
+        match &mut parent.nested {
+            Some(NestedBars {
+                bars,
+                meta: NestedMeta::Weighted(weights),
+            }) => {
+                let index = bars.iter().position(|bar| Arc::ptr_eq(bar, state))?;
+                weights.get_mut(index).map(f)
+            }
+            _ => None,
+        }
+    }
+
+    /// Position of the bar, see [`Self::set_position`]
+    ///
+    /// For bars which have been split, this is not computed from the children.
+    pub fn position(&self) -> usize {
+        if let Some(state) = &self.state {
+            state.lock().position
+        } else {
+            panic!(
+                "This progress bar is finished. You can no longer retrieve information about it."
+            );
+        }
+    }
+
     /// Length of the bar, if it has been set
     pub fn length(&self) -> Option<usize> {
         if let Some(state) = &self.state {
-            state.lock().unwrap().length
+            state.lock().length
         } else {
             panic!(
                 "This progress bar is finished. You can no longer retrieve information about it."
@@ -217,143 +485,882 @@ impl ProgressBar {
         }
     }
 
-    /// Sets the length of this progress bar.
+    /// The number of items processed per second over the last few seconds, or None if the bar has not made progress yet.
     ///
-    /// This has no effect if the bar has already been finished or abandoned.
-    pub fn set_length(&self, len: usize) {
-        if let Some(state) = &self.state {
-            let mut state = state.lock().unwrap();
-            state.length = Some(len);
-        }
+    /// The rate drops towards zero when the bar stops making progress. How it is smoothed can be changed using [`Self::set_rate_smoothing`] and [`Self::set_rate_window`]. It is only known for bars which are updated directly,
+    /// and not for bars which have been split. To display the rate next to the bar, use [`Style::with_rate`].
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_length(1000);
+    /// for i in 1..=10 {
+    ///     p.set_position(i * 100);
+    ///     std::thread::sleep(std::time::Duration::from_millis(10));
+    /// }
+    /// assert!(p.per_sec().unwrap() > 0.0);
+    /// ```
+    pub fn per_sec(&self) -> Option<f64> {
+        let state = self.state.as_ref()?.lock();
+        state.nested.is_none().then(|| state.per_sec()).flatten()
     }
 
-    /// Sets the amount of progress this bar has made.
+    /// Smooths the rate of progress using an exponential moving average, where progress made `half_life` ago counts half as much as progress made now.
     ///
-    /// Should usually be between 0 and [`Self::length`].
+    /// By default, the rate is the average over the last 5 seconds, which jumps around for bursty workloads that for example process
+    /// items in large batches. A longer half-life gives a steadier rate, which reacts more slowly to real changes.
     ///
-    /// This has no effect if the bar has already been finished or abandoned.
-    pub fn set_position(&self, pos: usize) {
+    /// The smoothed rate is used both for [`Self::per_sec`] and for the estimated remaining time (see [`Style::with_eta`]),
+    /// which is otherwise based on the average rate since the bar started.
+    /// This has no effect on bars which have been split.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    /// use std::time::Duration;
+    ///
+    /// let p = ProgressBar::new()
+    ///     .with_style(Style::new().with_rate(true).with_eta(true))
+    ///     .with_rate_smoothing(Duration::from_secs(30));
+    /// for _ in p.wrap(0..100) {}
+    /// ```
+    pub fn set_rate_smoothing(&self, half_life: Duration) {
+        assert!(
+            !half_life.is_zero(),
+            "The half-life must be longer than zero"
+        );
         if let Some(state) = &self.state {
-            state.lock().unwrap().position = pos;
+            state.lock().rate_smoothing = Some(RateSmoothing::HalfLife(half_life));
         }
     }
 
-    /// Clears any message set using [`Self::set_message`] or [`Self::with_message`].
-    pub fn clear_message(&self) {
+    /// Equivalent to [`Self::set_rate_smoothing`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_rate_smoothing(self, half_life: Duration) -> Self {
+        self.set_rate_smoothing(half_life);
+        self
+    }
+
+    /// Measures the rate of progress as the average over a sliding window of the given length.
+    ///
+    /// By default, the window is 5 seconds. Like for [`Self::set_rate_smoothing`], the rate is then also used for
+    /// the estimated remaining time.
+    pub fn set_rate_window(&self, window: Duration) {
+        assert!(!window.is_zero(), "The window must be longer than zero");
         if let Some(state) = &self.state {
-            state.lock().unwrap().message = None;
+            state.lock().rate_smoothing = Some(RateSmoothing::Window(window));
         }
     }
 
-    /// Equivalent to [`Self::set_message`], but may be more ergonomic in some situations since it returns `self`.
-    pub fn with_message(self, message: impl Into<String>) -> Self {
-        self.set_message(message);
+    /// Equivalent to [`Self::set_rate_window`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_rate_window(self, window: Duration) -> Self {
+        self.set_rate_window(window);
         self
     }
 
-    /// Equivalent to [`Self::set_length`], but may be more ergonomic in some situations since it returns `self`.
-    pub fn with_length(self, length: usize) -> Self {
-        self.set_length(length);
-        self
+    /// Sets the length of this progress bar.
+    ///
+    /// This has no effect if the bar has already been finished or abandoned.
+    pub fn set_length(&self, len: usize) {
+        if let Some(state) = &self.state {
+            state.lock().set_length(Some(len));
+        }
     }
 
-    /// Sets a message which will show up next to the bar.
+    /// Sets the length of the bar once it becomes known, without having to remember to call [`Self::set_length`] later.
     ///
-    /// If the root bar has been split into multiple children, then the message that is displayed
-    /// is from the first bar that is not finished. Or if all bars are finished then the last bar with a message will be used.
-    pub fn set_message(&self, message: impl Into<String>) {
-        let m = message.into();
-        if m.is_empty() {
-            self.clear_message();
-        } else if let Some(state) = &self.state {
-            state.lock().unwrap().message = Some(m);
-        }
+    /// This is useful when the total is known shortly after the work starts, like from the headers of an HTTP response or a database `COUNT` query.
+    /// The callback is called regularly from a background thread until it returns a length, or until the bar is finished, abandoned or dropped.
+    /// It is called without holding any locks, but it should return quickly, since it delays the drawing of all bars.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use std::sync::{Arc, OnceLock};
+    ///
+    /// let total = Arc::new(OnceLock::new());
+    /// let p = ProgressBar::new().with_message("Downloading");
+    /// let t = total.clone();
+    /// p.set_length_lazy(move || t.get().copied());
+    ///
+    /// // Later, when the response headers have arrived
+    /// total.set(1_048_576).unwrap();
+    /// ```
+    pub fn set_length_lazy(&self, mut length: impl FnMut() -> Option<u64> + Send + 'static) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        let state = Arc::downgrade(state);
+        add_poller(move || {
+            let Some(state) = state.upgrade() else {
+                return false;
+            };
+            if state.lock().lifecycle != LifecycleState::InProgress {
+                return false;
+            }
+            // Avoid holding the lock while calling the callback
+            match length() {
+                Some(len) => {
+                    let mut state = state.lock();
+                    if state.lifecycle == LifecycleState::InProgress {
+                        state.length = Some(len as usize);
+                        state.last_activity = clock::now();
+                    }
+                    false
+                }
+                None => true,
+            }
+        });
     }
 
-    /// Increments the progress of this bar by 1.
+    /// Smoothly fills the bar over the given duration, for steps which take a known amount of time.
     ///
-    /// Usually it's more convenient to work with the iterator-wrapping functions like [`Self::wrap`]
-    pub fn inc(&self) {
-        if let Some(state) = &self.state {
-            state.lock().unwrap().position += 1;
+    /// This is useful for fixed waits, like rate limits or retry delays, which would otherwise look frozen.
+    /// The bar is advanced from a background thread, starting at its current position.
+    /// It stops at 99% until the bar is finished, in case the step takes longer than expected.
+    /// If the bar has no length, its length is set to 100.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use std::time::Duration;
+    ///
+    /// let mut p = ProgressBar::new().with_message("Waiting for rate limit");
+    /// p.auto_advance_over(Duration::from_millis(200));
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// p.finish();
+    /// ```
+    ///
+    /// Bars with a small length are filled smoothly too, while the position only counts whole items:
+    ///
+    /// ```
+    /// use headway::{ManualClock, ProgressBar};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// headway::set_clock(Some(clock.clone()));
+    ///
+    /// let p = ProgressBar::hidden().with_length(1);
+    /// p.auto_advance_over(Duration::from_secs(10));
+    /// clock.advance(Duration::from_secs(5));
+    /// assert_eq!(p.render_line(), "▕█████████▉          ▏ 0/1");
+    /// ```
+    pub fn auto_advance_over(&self, duration: Duration) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        {
+            let mut state = state.lock();
+            let length = *state.length.get_or_insert(100);
+            let start = state.position.min(length) as f64;
+            // The bar is filled smoothly, so even bars with a length of 1 move
+            state.auto_advance = Some(AutoAdvance {
+                start,
+                end: (length as f64 * 0.99).max(start),
+                started: clock::now(),
+                duration,
+            });
         }
+        let state = Arc::downgrade(state);
+        add_poller(move || {
+            let Some(state) = state.upgrade() else {
+                return false;
+            };
+            let mut state = state.lock();
+            if state.lifecycle != LifecycleState::InProgress {
+                return false;
+            }
+            let Some(auto_advance) = state.auto_advance.clone() else {
+                return false;
+            };
+            let position = auto_advance.position().floor() as usize;
+            if position > state.position {
+                state.position = position;
+                state.record_progress();
+            }
+            auto_advance.fraction() < 1.0
+        });
     }
 
-    /// Marks the bar as finished and sets the message.
+    /// Sets the amount of progress this bar has made.
     ///
-    /// Equivalent to first setting the message and then marking the bar as finished.
-    pub fn finish_with_message(&mut self, message: impl Into<String>) {
-        self.set_message(message);
-        self.finish();
+    /// Should usually be between 0 and [`Self::length`].
+    ///
+    /// This has no effect if the bar has already been finished or abandoned.
+    pub fn set_position(&self, pos: usize) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.position = pos;
+            state.record_progress();
+        }
     }
 
-    /// Abandons the progress bar.
+    /// Moves the position backwards by `n`, to redo work which has to be retried.
     ///
-    /// The remaining part of the progress bar will be colored red to indicate it will never be completed.
-    /// Progress bars are automatically marked as abandoned when they are dropped and they are only partially complete.
-    pub fn abandon(&mut self) {
+    /// Unlike setting a lower position using [`Self::set_position`], the rate of progress is not reset.
+    /// Redone items are not counted as throughput again, so the rate and the estimated time remaining only include new progress.
+    /// The total amount of redone work is displayed after the position, like `40/100 (↺ 20 redone)`.
+    ///
+    /// This has no effect if the bar has already been finished or abandoned.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_length(100).with_message("Uploading");
+    /// let mut chunk = 0;
+    /// let mut retried = false;
+    /// while chunk < 5 {
+    ///     p.set_position(chunk * 20 + 20);
+    ///     if chunk == 2 && !retried {
+    ///         // The upload of this chunk failed, so it has to be sent again
+    ///         retried = true;
+    ///         p.rewind(20);
+    ///         continue;
+    ///     }
+    ///     chunk += 1;
+    /// }
+    /// ```
+    ///
+    /// The rate only counts items the first time they are done:
+    ///
+    /// ```
+    /// use headway::{ManualClock, ProgressBar};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// headway::set_clock(Some(clock.clone()));
+    ///
+    /// let p = ProgressBar::hidden().with_length(100);
+    /// p.set_position(0);
+    /// clock.advance(Duration::from_secs(1));
+    /// p.set_position(40);
+    /// p.rewind(20);
+    /// clock.advance(Duration::from_secs(1));
+    /// p.set_position(40);
+    /// // 40 new items in 2 seconds
+    /// assert_eq!(p.per_sec(), Some(20.0));
+    /// ```
+    pub fn rewind(&self, n: usize) {
         if let Some(state) = &self.state {
-            let mut state = state.lock().unwrap();
-            state.lifecycle = LifecycleState::Abandoned;
+            state.lock().rewind(n);
         }
-        self.state = None;
-
-        let mut manager = MANAGER.lock().unwrap();
-        manager.tick(&mut std::io::stdout().lock()).unwrap();
     }
 
-    /// Marks the bar as finished.
+    /// Sets the number of items which are waiting to be processed.
     ///
-    /// If the bar has a length, the position of the bar will be set to [`Self::length`].
-    pub fn finish(&mut self) {
+    /// This is useful for producer-consumer pipelines, to be able to see where the bottleneck is.
+    /// The backlog is displayed after the position, like `40/100 (+12 queued)`.
+    /// For bars which have been split, the backlogs of all children are summed up.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let (sender, receiver) = std::sync::mpsc::channel();
+    /// let p = ProgressBar::new().with_length(100).with_message("Uploading");
+    /// for i in 0..100 {
+    ///     sender.send(i).unwrap();
+    ///     p.set_backlog(i + 1);
+    /// }
+    /// for (i, _item) in receiver.try_iter().enumerate() {
+    ///     p.inc();
+    ///     p.set_backlog(99 - i);
+    /// }
+    /// ```
+    pub fn set_backlog(&self, backlog: usize) {
         if let Some(state) = &self.state {
-            let mut state = state.lock().unwrap();
-            if let Some(length) = state.length {
-                state.position = length;
-            }
-            state.lifecycle = LifecycleState::Completed;
+            state.lock().backlog = Some(backlog);
         }
-        self.state = None;
-
-        let mut manager = MANAGER.lock().unwrap();
-        manager.tick(&mut std::io::stdout().lock()).unwrap();
     }
 
-    /// Wraps the bar around an iterator.
-    ///
-    /// If the iterator has a known length, the bar's length will be set to that length.
-    /// The iterator will headway the progress by 1 each step.
-    /// When reaching the end of the iterator, the bar will be marked as finished.
+    /// Marks a position in the bar, for example the boundary between two phases of a long task.
     ///
-    /// See also [`ProgressBarIterable::progress`] and [`ProgressBarIterable::progress_with`]
+    /// The marker is drawn as a thin line inside the bar. If the label is not empty, it is listed after the bar once the bar is finished or abandoned.
     ///
     /// ```
-    /// # use std::time::Duration;
-    /// # use std::thread::sleep;
     /// use headway::ProgressBar;
     ///
-    /// let p = ProgressBar::new().with_message("Calibrating flux capacitors");
-    /// for _ in p.wrap(0..100) {
-    ///     sleep(Duration::from_millis(20));
+    /// let mut p = ProgressBar::new().with_length(100).with_message("Downloading");
+    /// p.add_marker(50, "verify");
+    /// for i in 0..100 {
+    ///     if i == 50 {
+    ///         p.set_message("Verifying");
+    ///     }
+    ///     p.inc();
     /// }
+    /// p.finish();
     /// ```
     ///
-    #[doc=include_str!("../images/message.html")]
-    pub fn wrap<It: Iterator>(self, it: It) -> ProgressBarIterator<It> {
-        if let Some(upper_bound) = it.size_hint().1 {
-            self.set_length(upper_bound);
-        }
-        ProgressBarIterator {
-            progress: self,
-            inner: it,
+    /// While downloading, this renders as
+    ///
+    /// ```text
+    /// ▕█████     │         ▏ 25/100 Downloading
+    /// ```
+    ///
+    /// And once finished
+    ///
+    /// ```text
+    /// ▕██████████│█████████▏ 100/100 Verifying │50% verify
+    /// ```
+    pub fn add_marker(&self, position: usize, label: impl Into<String>) {
+        if let Some(state) = &self.state {
+            state.lock().markers.push((position, label.into()));
         }
     }
-}
 
-/// A progress bar that wraps an iterator.
-///
-/// You can wrap an iterator by either calling `.progress()` on an existing iterator,
-/// or by calling [`ProgressBar::wrap`] on an existing progress bar.
+    /// Appends a custom field after the standard fields of the bar, like the current memory usage or a cache hit rate.
+    ///
+    /// The callback is invoked every time the bar is drawn, with the current progress of the bar, and should return the text to display.
+    /// Nothing is displayed if it returns an empty string. Fields are displayed in the order they were added.
+    /// Fields which do not fit in the terminal are shortened using [`truncate_to_width`](crate::truncate_to_width).
+    /// To shorten the text in a smarter way, the callback can check [`FieldContext::remaining_width`].
+    ///
+    /// The callback is invoked while the bar is locked, so it must not interact with any progress bars.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let hits = Arc::new(AtomicUsize::new(0));
+    /// let p = ProgressBar::new().with_length(100);
+    /// let h = hits.clone();
+    /// p.add_extra_field(move |field| {
+    ///     let hit_rate = h.load(Ordering::Relaxed) as f64 / field.snapshot().position().max(1.0);
+    ///     format!("{:.0}% cached", hit_rate * 100.0)
+    /// });
+    /// for i in 0..100 {
+    ///     if i % 3 == 0 {
+    ///         hits.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    ///     p.inc();
+    /// }
+    /// ```
+    ///
+    /// ```text
+    /// ▕████████████████████▏ 100/100 34% cached
+    /// ```
+    pub fn add_extra_field(&self, field: impl Fn(&FieldContext) -> String + Send + Sync + 'static) {
+        if let Some(state) = &self.state {
+            state.lock().extra_fields.push(Arc::new(field));
+        }
+    }
+
+    /// Equivalent to [`Self::add_extra_field`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_extra_field(
+        self,
+        field: impl Fn(&FieldContext) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.add_extra_field(field);
+        self
+    }
+
+    /// Replaces the text displayed right after the bar, which is the position and length (like `42/100`) by default, or a percentage for weighted splits.
+    ///
+    /// The callback is invoked every time the bar is drawn, with the current progress of the bar, and appends the text to display to the string.
+    /// Nothing is displayed if it does not write anything. In plain text output, like in accessible mode, the text is displayed
+    /// in parentheses after the percentage. Other parts of the line, like the message and any [extra fields](Self::add_extra_field), are still displayed after it.
+    ///
+    /// The callback is invoked while the bar is locked, so it must not interact with any progress bars.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_length(2048);
+    /// p.set_formatter(|snapshot, out| {
+    ///     let kib = snapshot.position() / 1024.0;
+    ///     let total = snapshot.length_upper.unwrap_or(0.0) / 1024.0;
+    ///     out.push_str(&format!("{:.1}/{:.1} KiB", kib, total));
+    /// });
+    /// for _ in 0..1024 {
+    ///     p.inc();
+    /// }
+    /// ```
+    ///
+    /// ```text
+    /// ▕██████████          ▏ 1.0/2.0 KiB
+    /// ```
+    pub fn set_formatter(
+        &self,
+        formatter: impl Fn(&BarSnapshot, &mut String) + Send + Sync + 'static,
+    ) {
+        if let Some(state) = &self.state {
+            state.lock().formatter = Some(Arc::new(formatter));
+        }
+    }
+
+    /// Equivalent to [`Self::set_formatter`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_formatter(
+        self,
+        formatter: impl Fn(&BarSnapshot, &mut String) + Send + Sync + 'static,
+    ) -> Self {
+        self.set_formatter(formatter);
+        self
+    }
+
+    /// Stops displaying the backlog. See [`Self::set_backlog`].
+    pub fn clear_backlog(&self) {
+        if let Some(state) = &self.state {
+            state.lock().backlog = None;
+        }
+    }
+
+    /// Sets a second counter which is displayed next to the position of the bar.
+    ///
+    /// This is useful when there are two coupled metrics, like the number of bytes read and the number of bytes written by a compressor.
+    /// The secondary position is displayed after the position, together with the ratio between the two.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_length(5000).with_message("Compressing");
+    /// p.set_position(1200);
+    /// p.set_secondary_position(300);
+    /// ```
+    ///
+    /// Renders as
+    ///
+    /// ```text
+    /// ▕████▊               ▏ 1200/5000 → 300 (4.0x) Compressing
+    /// ```
+    ///
+    /// This has no effect if the bar has already been finished or abandoned.
+    pub fn set_secondary_position(&self, pos: usize) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.secondary_position = Some(pos);
+            state.last_activity = clock::now();
+        }
+    }
+
+    /// The secondary position of the bar, if it has been set. See [`Self::set_secondary_position`].
+    pub fn secondary_position(&self) -> Option<usize> {
+        if let Some(state) = &self.state {
+            state.lock().secondary_position
+        } else {
+            panic!(
+                "This progress bar is finished. You can no longer retrieve information about it."
+            );
+        }
+    }
+
+    /// Clears any message set using [`Self::set_message`] or [`Self::with_message`].
+    pub fn clear_message(&self) {
+        if let Some(state) = &self.state {
+            state.lock().message = None;
+        }
+    }
+
+    /// Equivalent to [`Self::set_message`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_message(self, message: impl Into<String>) -> Self {
+        self.set_message(message);
+        self
+    }
+
+    /// Equivalent to [`Self::set_length`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_length(self, length: usize) -> Self {
+        self.set_length(length);
+        self
+    }
+
+    /// Sets a message which will show up next to the bar.
+    ///
+    /// If the root bar has been split into multiple children, then the message that is displayed
+    /// is from the first bar that is not finished. Or if all bars are finished then the last bar with a message will be used.
+    pub fn set_message(&self, message: impl Into<String>) {
+        let m = message.into();
+        if m.is_empty() {
+            self.clear_message();
+        } else if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.message = Some(m);
+            state.last_activity = clock::now();
+        }
+    }
+
+    /// Replaces the message until the returned guard is dropped, and then restores the previous message.
+    ///
+    /// This is useful for temporary status text inside helper functions, which should not permanently overwrite the message set by the caller.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// fn verify(p: &ProgressBar) {
+    ///     let _message = p.scoped_message("Verifying signature");
+    ///     // ...
+    /// }
+    ///
+    /// let p = ProgressBar::new().with_message("Installing");
+    /// verify(&p);
+    /// // The message is "Installing" again
+    /// ```
+    pub fn scoped_message(&self, message: impl Into<String>) -> ScopedMessage<'_> {
+        let previous = self.state.as_ref().and_then(|s| s.lock().message.clone());
+        self.set_message(message);
+        ScopedMessage {
+            bar: self,
+            previous,
+        }
+    }
+
+    /// Sets a prefix which will show up before the bar.
+    ///
+    /// Prefixes are typically short verbs describing what the bar is doing, like `Downloading`.
+    /// See [`Style::with_prefix_width`] for how to align the prefixes of several bars.
+    ///
+    /// Nested bars pick which prefix to display in the same way as for [`Self::set_message`].
+    pub fn set_prefix(&self, prefix: impl Into<String>) {
+        let p = prefix.into();
+        if let Some(state) = &self.state {
+            state.lock().prefix = if p.is_empty() { None } else { Some(p) };
+        }
+    }
+
+    /// Equivalent to [`Self::set_prefix`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_prefix(self, prefix: impl Into<String>) -> Self {
+        self.set_prefix(prefix);
+        self
+    }
+
+    /// Sets the style of this bar.
+    ///
+    /// Bars without a style use the style set using [`crate::set_default_style`].
+    /// If the bar is nested inside another bar, the style of the root bar is used instead.
+    pub fn set_style(&self, style: Style) {
+        if let Some(state) = &self.state {
+            state.lock().style = Some(Arc::new(style));
+        }
+    }
+
+    /// Equivalent to [`Self::set_style`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_style(self, style: Style) -> Self {
+        self.set_style(style);
+        self
+    }
+
+    /// Sets the color of the filled part of the bar.
+    ///
+    /// This is useful for visually grouping related bars, without having to read their messages.
+    /// If the bar is nested inside another bar, the color of the root bar is used instead.
+    ///
+    /// ```
+    /// use headway::{Color, ProgressBar};
+    ///
+    /// let download = ProgressBar::new().with_color(Color::Cyan);
+    /// let extract = ProgressBar::new().with_color(Color::Magenta);
+    /// ```
+    pub fn set_color(&self, color: Color) {
+        if let Some(state) = &self.state {
+            state.lock().color = Some(color);
+        }
+    }
+
+    /// Equivalent to [`Self::set_color`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_color(self, color: Color) -> Self {
+        self.set_color(color);
+        self
+    }
+
+    /// Sets how many items remain, for bars which represent a shrinking quantity, like the number of items in a queue.
+    ///
+    /// The bar then displays the remaining items out of the largest number of items seen so far, like `4/10 left`,
+    /// and the fill decreases as the queue is drained. Items can be added to the queue while it is being drained,
+    /// and if the queue grows larger than it has been before, the length of the bar grows with it.
+    ///
+    /// When a bar is split, drained children count as done to the same degree as any other bar.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut queue = (0..10).collect::<VecDeque<_>>();
+    /// let p = ProgressBar::new().with_message("Processing queue");
+    /// while let Some(item) = queue.pop_front() {
+    ///     if item < 3 {
+    ///         // Processing an item can discover more work
+    ///         queue.push_back(item + 10);
+    ///     }
+    ///     p.set_remaining(queue.len());
+    /// }
+    /// ```
+    ///
+    /// ```text
+    /// ▕████████            ▏ 4/10 left Processing queue
+    /// ```
+    pub fn set_remaining(&self, remaining: usize) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            let length = state.length.unwrap_or(0).max(remaining);
+            state.draining = true;
+            state.set_length(Some(length));
+            state.position = length - remaining;
+            state.record_progress();
+        }
+    }
+
+    /// Increments the progress of this bar by 1.
+    ///
+    /// Usually it's more convenient to work with the iterator-wrapping functions like [`Self::wrap`]
+    pub fn inc(&self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.position += 1;
+            state.record_progress();
+        }
+    }
+
+    /// Registers a callback which is invoked if the bar does not make any progress for the given duration.
+    ///
+    /// The callback is invoked from a background thread, at most once each time the bar stalls.
+    /// If the bar is updated again (see [`Self::touch`]) and later stalls a second time, the callback will be invoked again.
+    /// This can be used to log diagnostics, or to give up on hung workers using [`Stall::abandon`].
+    ///
+    /// The callback is never invoked for hidden bars (see [`Self::hidden`]).
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use std::time::Duration;
+    ///
+    /// let p = ProgressBar::new().with_message("Waiting for worker");
+    /// p.on_stall(Duration::from_secs(30), |stall| {
+    ///     eprintln!("Worker has been stalled for {:?}, giving up", stall.duration());
+    ///     stall.abandon();
+    /// });
+    /// ```
+    pub fn on_stall(&self, timeout: Duration, callback: impl FnMut(&Stall) + Send + 'static) {
+        if let Some(state) = &self.state {
+            state.lock().stall_watchers.push(StallWatcher {
+                timeout,
+                callback: Arc::new(Mutex::new(Box::new(callback))),
+                fired_for_activity: None,
+            });
+            MANAGER.lock().ensure_thread_started();
+        }
+    }
+
+    /// Signals that the bar is still alive, for example during a long step which cannot report any progress.
+    ///
+    /// This resets the timer used for marking bars as stalled (see [`Self::touch`]), and nudges the animation of indeterminate bars forward.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_message("Restoring database");
+    /// p.set_phase_message("replaying write-ahead log");
+    /// for _ in 0..10 {
+    ///     // Do something slow which we cannot measure the progress of...
+    ///     p.heartbeat();
+    /// }
+    /// ```
+    pub fn heartbeat(&self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.heartbeats += 1;
+            state.last_activity = clock::now();
+        }
+    }
+
+    /// Sets a secondary message describing what the bar is currently doing.
+    ///
+    /// The phase message is displayed in parentheses after the regular message (see [`Self::set_message`]).
+    /// This is useful for long steps which have no countable progress, to still be able to tell what is going on.
+    /// Setting the phase message also counts as a [`Self::heartbeat`].
+    ///
+    /// An empty string clears the phase message.
+    pub fn set_phase_message(&self, message: impl Into<String>) {
+        let m = message.into();
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            state.phase_message = if m.is_empty() { None } else { Some(m) };
+            state.heartbeats += 1;
+            state.last_activity = clock::now();
+        }
+    }
+
+    /// Signals that the bar is still alive, even though it has not made any progress.
+    ///
+    /// This resets the timer used for marking bars as stalled. See [`Style::with_stall_threshold`].
+    pub fn touch(&self) {
+        if let Some(state) = &self.state {
+            state.lock().last_activity = clock::now();
+        }
+    }
+
+    /// Marks the bar as finished and sets the message.
+    ///
+    /// Equivalent to first setting the message and then marking the bar as finished.
+    pub fn finish_with_message(&mut self, message: impl Into<String>) {
+        self.set_message(message);
+        self.finish();
+    }
+
+    /// Abandons the progress bar.
+    ///
+    /// The remaining part of the progress bar will be colored red to indicate it will never be completed.
+    /// Progress bars are automatically marked as abandoned when they are dropped and they are only partially complete.
+    pub fn abandon(&mut self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            // A bar finished by finish_all stays finished
+            if state.lifecycle != LifecycleState::Completed {
+                state.lifecycle = LifecycleState::Abandoned;
+                state.finished_at.get_or_insert_with(clock::now);
+            }
+            #[cfg(feature = "tokio")]
+            state.notify_watchers();
+        }
+        self.state = None;
+
+        redraw();
+    }
+
+    /// Abandons the progress bar, and displays the error which caused it next to the bar.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let mut p = ProgressBar::new().with_message("Reading config");
+    /// if let Err(err) = std::fs::read("missing-config.toml") {
+    ///     p.abandon_with_error(&err);
+    /// }
+    /// ```
+    ///
+    /// ```text
+    /// ▕█████               ▏ 1/4 Reading config (abandoned: No such file or directory (os error 2))
+    /// ```
+    ///
+    /// Bars which are dropped while their thread is panicking are abandoned with the panic message in the same way.
+    pub fn abandon_with_error(&mut self, error: impl std::fmt::Display) {
+        if let Some(state) = &self.state {
+            state.lock().abandon_reason = Some(error.to_string());
+        }
+        self.abandon();
+    }
+
+    /// Marks the bar as finished.
+    ///
+    /// If the bar has a length, the position of the bar will be set to [`Self::length`].
+    pub fn finish(&mut self) {
+        if let Some(state) = &self.state {
+            let mut state = state.lock();
+            // A bar abandoned by a stall callback stays abandoned
+            if !state.abandoned_externally {
+                if let Some(length) = state.length {
+                    state.position = length;
+                }
+                state.log_change();
+                state.lifecycle = LifecycleState::Completed;
+                state.finished_at.get_or_insert_with(clock::now);
+            }
+            #[cfg(feature = "tokio")]
+            state.notify_watchers();
+        }
+        self.state = None;
+
+        redraw();
+    }
+
+    /// Wraps the bar around an iterator.
+    ///
+    /// If the iterator has a known length, the bar's length will be set to that length.
+    /// The iterator will headway the progress by 1 each step.
+    /// When reaching the end of the iterator, the bar will be marked as finished.
+    ///
+    /// See also [`ProgressBarIterable::progress`] and [`ProgressBarIterable::progress_with`]
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use std::thread::sleep;
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().with_message("Calibrating flux capacitors");
+    /// for _ in p.wrap(0..100) {
+    ///     sleep(Duration::from_millis(20));
+    /// }
+    /// ```
+    ///
+    #[doc=include_str!("../images/message.html")]
+    pub fn wrap<It: Iterator>(self, it: It) -> ProgressBarIterator<It> {
+        ProgressBarIterator::new(self, it)
+    }
+
+    /// Renders the bar as a single line of text, as it would be displayed in a terminal, but without colors.
+    ///
+    /// This does not write anything to the terminal. It is useful for embedding bars in other user interfaces,
+    /// in which case the bar can be created using [`Self::hidden`] so that it is not drawn by this crate as well.
+    /// See also [`crate::render_to_string`] for rendering all bars at once.
+    ///
+    /// Returns an empty string if the bar has been finished or abandoned.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::hidden().with_length(10).with_message("Indexing");
+    /// p.set_position(5);
+    /// assert_eq!(p.render_line(), "▕██████████          ▏ 5/10 Indexing");
+    /// ```
+    pub fn render_line(&self) -> String {
+        let Some(state) = &self.state else {
+            return String::new();
+        };
+        let manager = MANAGER.lock();
+        let ctx = crate::RenderContext {
+            color: false,
+            reference_time: &manager.reference_time,
+            default_style: &manager.default_style,
+            width: None,
+        };
+        let mut out = String::new();
+        // SAFETY: Writes to strings cannot fail
+        state.lock().render(&mut out, &ctx, &mut false).unwrap();
+        out
+    }
+}
+
+/// Information passed to the callbacks registered using [`ProgressBar::add_extra_field`].
+pub struct FieldContext<'a> {
+    pub(crate) snapshot: &'a BarSnapshot,
+    pub(crate) remaining_width: Option<usize>,
+}
+
+impl FieldContext<'_> {
+    /// The current progress of the bar.
+    pub fn snapshot(&self) -> &BarSnapshot {
+        self.snapshot
+    }
+
+    /// How many columns are left on the line, or None if the width of the output is not known, like when it is not a terminal.
+    ///
+    /// Text wider than this is truncated. Fields can use this to pick a shorter form when space is limited.
+    ///
+    /// ```
+    /// # let p = headway::ProgressBar::new();
+    /// p.add_extra_field(|field| match field.remaining_width() {
+    ///     Some(width) if width < 20 => "1.2G".to_string(),
+    ///     _ => "memory usage 1.2G".to_string(),
+    /// });
+    /// ```
+    pub fn remaining_width(&self) -> Option<usize> {
+        self.remaining_width
+    }
+}
+
+/// Guard returned by [`ProgressBar::scoped_message`].
+///
+/// The previous message of the bar is restored when this guard is dropped.
+pub struct ScopedMessage<'a> {
+    bar: &'a ProgressBar,
+    previous: Option<String>,
+}
+
+impl Drop for ScopedMessage<'_> {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(message) => self.bar.set_message(message),
+            None => self.bar.clear_message(),
+        }
+    }
+}
+
+/// A progress bar that wraps an iterator.
+///
+/// You can wrap an iterator by either calling `.progress()` on an existing iterator,
+/// or by calling [`ProgressBar::wrap`] on an existing progress bar.
 ///
 /// The values returned by the wrapped iterator are identical to the original iterator.
 ///
@@ -367,12 +1374,23 @@ impl ProgressBar {
 ///     sleep(Duration::from_millis(20));
 /// }
 /// ```
-pub struct ProgressBarIterator<It: Iterator> {
-    progress: ProgressBar,
+///
+/// The iterator can report its progress to any [`ProgressSink`], not just a [`ProgressBar`].
+pub struct ProgressBarIterator<It: Iterator, S: ProgressSink = ProgressBar> {
+    progress: S,
     inner: It,
 }
 
-impl<It: Iterator> Iterator for ProgressBarIterator<It> {
+impl<It: Iterator, S: ProgressSink> ProgressBarIterator<It, S> {
+    pub(crate) fn new(progress: S, inner: It) -> Self {
+        if let Some(upper_bound) = inner.size_hint().1 {
+            progress.set_len(upper_bound);
+        }
+        Self { progress, inner }
+    }
+}
+
+impl<It: Iterator, S: ProgressSink> Iterator for ProgressBarIterator<It, S> {
     type Item = It::Item;
 
     fn next(&mut self) -> Option<It::Item> {
@@ -386,13 +1404,15 @@ impl<It: Iterator> Iterator for ProgressBarIterator<It> {
     }
 }
 
-impl<T, It: ExactSizeIterator<Item = T>> ExactSizeIterator for ProgressBarIterator<It> {
+impl<T, It: ExactSizeIterator<Item = T>, S: ProgressSink> ExactSizeIterator
+    for ProgressBarIterator<It, S>
+{
     fn len(&self) -> usize {
         self.inner.len()
     }
 }
 
-impl<It: Iterator> ProgressBarIterator<It> {
+impl<It: Iterator, S: ProgressSink> ProgressBarIterator<It, S> {
     /// Sets the message of the progress bar.
     ///
     /// Equivalent to [`ProgressBar::set_message`].
@@ -425,15 +1445,228 @@ pub trait ProgressBarIterable: Iterator + Sized {
     /// When the iterator finishes, the bar will be marked as finished.
     ///
     /// This is equivalent to using [`ProgressBar::wrap`], but this function may be more ergonomic in some situations.
-    fn progress_with(self, bar: ProgressBar) -> ProgressBarIterator<Self>;
+    ///
+    /// Any [`ProgressSink`] can be used, not just a [`ProgressBar`].
+    fn progress_with<S: ProgressSink>(self, bar: S) -> ProgressBarIterator<Self, S>;
+
+    /// Show a progress bar while iterating, but only update it once every `chunk` items.
+    ///
+    /// This is useful for iterating over huge numbers of very cheap items, where even updating the bar for every item has a noticeable cost.
+    /// Between updates, the bar estimates the progress within the current chunk from how long previous chunks took, so that it still moves smoothly.
+    ///
+    /// ```
+    /// use headway::ProgressBarIterable;
+    ///
+    /// let sum: u64 = (0..1_000_000u32).progress_chunked(10_000).map(u64::from).sum();
+    /// ```
+    fn progress_chunked(self, chunk: usize) -> ProgressBarChunkedIterator<Self>
+    where
+        Self: ExactSizeIterator;
+
+    /// Show a progress bar while iterating, with a nested bar for each item.
+    ///
+    /// Each item is yielded together with a bar which represents that item, so that expensive items can report their own progress.
+    /// The nested bar must be finished when the item is done, for example by wrapping an iterator with it.
+    /// Like other bars, it is marked as abandoned if it is dropped before it is finished.
+    ///
+    /// This is equivalent to `ProgressBar::new().split_each(iterator)`. See [`ProgressBar::split_each`].
+    ///
+    /// ```
+    /// use headway::ProgressBarIterable;
+    ///
+    /// let files = ["a.txt", "b.txt", "c.txt"];
+    /// for (item_bar, file) in files.iter().progress_items() {
+    ///     item_bar.set_message(*file);
+    ///     for _chunk in item_bar.wrap(0..100) {
+    ///         // Process a part of the file...
+    ///     }
+    /// }
+    /// ```
+    fn progress_items(self) -> impl Iterator<Item = (ProgressBar, Self::Item)>;
+
+    /// Show a progress bar while iterating, and yield the index of each item like [`Iterator::enumerate`].
+    ///
+    /// The message of the bar is set to the item which is currently being processed, like `item 3/10`.
+    /// The message can be customized using [`ProgressBarEnumerateIterator::with_item_message`].
+    ///
+    /// ```
+    /// use headway::ProgressBarIterable;
+    ///
+    /// let files = ["a.txt", "b.txt", "c.txt"];
+    /// for (i, file) in files.iter().progress_enumerate() {
+    ///     // Process the file...
+    /// }
+    /// ```
+    fn progress_enumerate(self) -> ProgressBarEnumerateIterator<Self>;
 }
 
 impl<T, It: Iterator<Item = T>> ProgressBarIterable for It {
+    #[track_caller]
     fn progress(self) -> ProgressBarIterator<It> {
         self.progress_with(ProgressBar::new())
     }
 
-    fn progress_with(self, bar: ProgressBar) -> ProgressBarIterator<It> {
-        bar.wrap(self)
+    fn progress_with<S: ProgressSink>(self, bar: S) -> ProgressBarIterator<It, S> {
+        ProgressBarIterator::new(bar, self)
+    }
+
+    #[track_caller]
+    fn progress_chunked(self, chunk: usize) -> ProgressBarChunkedIterator<It>
+    where
+        It: ExactSizeIterator,
+    {
+        ProgressBarChunkedIterator::new(ProgressBar::new(), self, chunk)
+    }
+
+    #[track_caller]
+    fn progress_items(self) -> impl Iterator<Item = (ProgressBar, T)> {
+        ProgressBar::new().split_each(self)
+    }
+
+    #[track_caller]
+    fn progress_enumerate(self) -> ProgressBarEnumerateIterator<It> {
+        ProgressBarEnumerateIterator::new(ProgressBar::new(), self)
+    }
+}
+
+/// A callback which formats the message of a [`ProgressBarEnumerateIterator`], from the index of the item and the number of items.
+type ItemMessage = Box<dyn Fn(usize, Option<usize>) -> String + Send + Sync>;
+
+/// An iterator which yields the index of each item, and displays the current item in the message of its progress bar.
+///
+/// See [`ProgressBarIterable::progress_enumerate`].
+pub struct ProgressBarEnumerateIterator<It: Iterator> {
+    inner: ProgressBarIterator<std::iter::Enumerate<It>>,
+    /// The number of items, if known
+    length: Option<usize>,
+    message: ItemMessage,
+}
+
+impl<It: Iterator> ProgressBarEnumerateIterator<It> {
+    pub(crate) fn new(progress: ProgressBar, inner: It) -> Self {
+        let length = inner.size_hint().1;
+        Self {
+            inner: ProgressBarIterator::new(progress, inner.enumerate()),
+            length,
+            message: Box::new(|i, length| match length {
+                Some(length) => format!("item {}/{}", i + 1, length),
+                None => format!("item {}", i + 1),
+            }),
+        }
+    }
+
+    /// Sets how the message of the bar is formatted, from the index of the current item and the number of items (if known).
+    ///
+    /// By default the message is `item 3/10`, where the item number starts at 1.
+    ///
+    /// ```
+    /// use headway::ProgressBarIterable;
+    ///
+    /// let pages = (0..20)
+    ///     .progress_enumerate()
+    ///     .with_item_message(|i, length| format!("page {} of {}", i + 1, length.unwrap_or(0)));
+    /// for (i, page) in pages {
+    ///     // Fetch the page...
+    /// }
+    /// ```
+    pub fn with_item_message(
+        mut self,
+        message: impl Fn(usize, Option<usize>) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.message = Box::new(message);
+        self
+    }
+}
+
+impl<It: Iterator> Iterator for ProgressBarEnumerateIterator<It> {
+    type Item = (usize, It::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let r = self.inner.next();
+        if let Some((i, _)) = &r {
+            self.inner
+                .progress
+                .set_message((self.message)(*i, self.length));
+        }
+        r
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<It: ExactSizeIterator> ExactSizeIterator for ProgressBarEnumerateIterator<It> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+/// An iterator which only updates its progress bar once per chunk of items.
+///
+/// See [`ProgressBarIterable::progress_chunked`].
+pub struct ProgressBarChunkedIterator<It: Iterator> {
+    progress: ProgressBar,
+    inner: It,
+    chunk: usize,
+    /// Items yielded since the bar was last updated
+    pending: usize,
+}
+
+impl<It: ExactSizeIterator> ProgressBarChunkedIterator<It> {
+    pub(crate) fn new(progress: ProgressBar, inner: It, chunk: usize) -> Self {
+        let chunk = chunk.max(1);
+        progress.set_length(inner.len());
+        if let Some(state) = &progress.state {
+            state.lock().chunks = Some(ChunkInterpolation {
+                chunk,
+                last_update: clock::now(),
+                rate: None,
+            });
+        }
+        Self {
+            progress,
+            inner,
+            chunk,
+            pending: 0,
+        }
+    }
+
+    /// Sets the message of the progress bar.
+    ///
+    /// Equivalent to [`ProgressBar::set_message`].
+    pub fn with_message(self, message: &str) -> Self {
+        self.progress.set_message(message);
+        self
+    }
+}
+
+impl<It: Iterator> Iterator for ProgressBarChunkedIterator<It> {
+    type Item = It::Item;
+
+    fn next(&mut self) -> Option<It::Item> {
+        let r = self.inner.next();
+        if r.is_none() {
+            self.progress.finish();
+        } else {
+            self.pending += 1;
+            if self.pending >= self.chunk {
+                if let Some(state) = &self.progress.state {
+                    state.lock().advance_chunk(self.pending);
+                }
+                self.pending = 0;
+            }
+        }
+        r
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<It: ExactSizeIterator> ExactSizeIterator for ProgressBarChunkedIterator<It> {
+    fn len(&self) -> usize {
+        self.inner.len()
     }
 }
@@ -0,0 +1,43 @@
+//! Integration with [rayon](https://docs.rs/rayon), enabled by the `rayon` feature.
+
+use ::rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::sync::Mutex;
+use crate::ProgressBar;
+
+impl ProgressBar {
+    /// Splits the bar into N bars, each representing an item in a parallel iterator.
+    ///
+    /// This works like [`Self::split_each`], but the nested bars are created lazily by whichever thread processes the item,
+    /// so that the items can be processed by a thread pool. Nested bars are only displayed once a thread has started working on them.
+    /// Regular iterators can be used with [`par_bridge`](rayon::iter::ParallelBridge::par_bridge).
+    ///
+    /// If the length of the iterator is known, it is used as the length of the bar.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use rayon::prelude::*;
+    ///
+    /// let files = vec!["a.txt", "b.txt", "c.txt", "d.txt"];
+    /// ProgressBar::new()
+    ///     .with_message("Compressing")
+    ///     .split_each_par(files)
+    ///     .for_each(|(bar, file)| {
+    ///         bar.set_message(file);
+    ///         for _ in bar.wrap(0..100) {}
+    ///     });
+    /// ```
+    ///
+    /// Requires the `rayon` feature.
+    pub fn split_each_par<I: IntoParallelIterator>(
+        self,
+        items: I,
+    ) -> impl ParallelIterator<Item = (ProgressBar, I::Item)> {
+        let items = items.into_par_iter();
+        if let Some(len) = items.opt_len() {
+            self.set_length(len);
+        }
+        let splitter = Mutex::new(self.split_sized());
+        items.map(move |item| (splitter.lock().take(1), item))
+    }
+}
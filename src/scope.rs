@@ -0,0 +1,71 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::ProgressBarState;
+
+thread_local! {
+    /// Labels of the calls to [`scope_label`] which are running on this thread, innermost last
+    static LABELS: RefCell<Vec<Arc<str>>> = const { RefCell::new(vec![]) };
+}
+
+/// Removes the label from the stack when the scope ends, even if it panics.
+struct ScopeGuard;
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        LABELS.with(|labels| labels.borrow_mut().pop());
+    }
+}
+
+/// Tags every bar created while running the closure with a label, including bars created by libraries.
+///
+/// Bars with the same label are displayed together in a section below the label, and the label is included when the bars are
+/// exported using [`crate::export_trace`], together with the total time spent in each label.
+/// This is useful when third-party code creates bars which can't be changed otherwise.
+///
+/// Scopes can be nested, in which case bars get the label of the innermost scope.
+/// Only bars created on the current thread are labelled, so bars which a library creates on a thread pool are not.
+/// The label of a bar can be read using [`ProgressBar::label`](crate::ProgressBar::label).
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// let index = headway::scope_label("indexing", || {
+///     // A library function which creates its own bars
+///     let p = ProgressBar::new().with_length(100).with_message("Reading files");
+///     for _ in 0..100 {
+///         p.inc();
+///     }
+///     p.label()
+/// });
+/// assert_eq!(index.as_deref(), Some("indexing"));
+/// ```
+pub fn scope_label<R>(label: impl Into<String>, f: impl FnOnce() -> R) -> R {
+    let label: Arc<str> = label.into().into();
+    LABELS.with(|labels| labels.borrow_mut().push(label));
+    let _guard = ScopeGuard;
+    f()
+}
+
+/// The label of the innermost [`scope_label`] running on this thread.
+pub(crate) fn current_label() -> Option<Arc<str>> {
+    LABELS.with(|labels| labels.borrow().last().cloned())
+}
+
+/// Bars which were created with the same label, see [`sections`]
+pub(crate) type Section<'a> = (Option<Arc<str>>, Vec<&'a Arc<Mutex<ProgressBarState>>>);
+
+/// Groups bars by their labels, in the order each label first appears. Bars without a label come first.
+pub(crate) fn sections(bars: &[Arc<Mutex<ProgressBarState>>]) -> Vec<Section<'_>> {
+    let mut sections: Vec<Section> = vec![(None, vec![])];
+    for bar in bars {
+        let label = bar.lock().label.clone();
+        match sections.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, section)) => section.push(bar),
+            None => sections.push((label, vec![bar])),
+        }
+    }
+    sections.retain(|(_, bars)| !bars.is_empty());
+    sections
+}
@@ -0,0 +1,212 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::{Aggregation, BarNode};
+use crate::{ProgressBar, MANAGER};
+
+/// Scenarios which take longer than this are considered to have hung
+const SCENARIO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A scenario exercised by [`selftest`]. Returns a description of the problem if it failed.
+type Scenario = fn() -> Result<(), String>;
+
+/// Exercises the display with pathological cases, and prints a report of which of them worked.
+///
+/// This draws to the terminal like any other bars, so that problems with unusual terminals can be seen as well.
+/// The scenarios are:
+///
+/// * 1,000 bars at the same time
+/// * Bars nested hundreds of levels deep
+/// * Many threads creating and finishing bars as fast as they can
+/// * Printing to stdout from several threads while bars are updated
+/// * The terminal being resized while bars are drawn (simulated using [`set_terminal_size_override`](crate::set_terminal_size_override))
+///
+/// A scenario fails if it panics, takes more than a minute, or leaves the display in an inconsistent state.
+/// Returns true if all scenarios passed. Bars should not be used by other threads while the test runs.
+///
+/// ```no_run
+/// if !headway::selftest() {
+///     std::process::exit(1);
+/// }
+/// ```
+pub fn selftest() -> bool {
+    let scenarios: [(&str, Scenario); 5] = [
+        ("1,000 bars", many_bars),
+        ("deep nesting", deep_nesting),
+        ("create/finish churn", churn),
+        ("concurrent println", concurrent_println),
+        ("terminal resize", resize),
+    ];
+    let mut report = String::from("headway self test\n");
+    let mut passed = 0;
+    for (name, scenario) in scenarios {
+        let start = Instant::now();
+        let result = run(scenario);
+        let elapsed = start.elapsed().as_secs_f64();
+        match result {
+            Ok(()) => {
+                passed += 1;
+                report.push_str(&format!("  pass  {} ({:.1}s)\n", name, elapsed));
+            }
+            Err(problem) => report.push_str(&format!(
+                "  FAIL  {} ({:.1}s): {}\n",
+                name, elapsed, problem
+            )),
+        }
+    }
+    report.push_str(&format!(
+        "{} of {} scenarios passed\n",
+        passed,
+        scenarios.len()
+    ));
+    println!("{}", report);
+    passed == scenarios.len()
+}
+
+/// Runs the scenario on a separate thread, so that panics and hangs can be reported.
+fn run(scenario: Scenario) -> Result<(), String> {
+    let visible_before = MANAGER.lock().bars.len();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = catch_unwind(AssertUnwindSafe(scenario)).unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Err(format!("panicked: {}", message))
+        });
+        let _ = sender.send(result);
+    });
+    receiver
+        .recv_timeout(SCENARIO_TIMEOUT)
+        .map_err(|_| "timed out".to_string())??;
+
+    // Finished bars are removed by the next frame
+    thread::sleep(Duration::from_millis(100));
+    crate::redraw();
+    let visible_after = MANAGER.lock().bars.len();
+    if visible_after > visible_before {
+        return Err(format!(
+            "{} finished bars are still displayed",
+            visible_after - visible_before
+        ));
+    }
+    Ok(())
+}
+
+fn many_bars() -> Result<(), String> {
+    let bars = (0..1000)
+        .map(|i| {
+            ProgressBar::new()
+                .with_length(100)
+                .with_message(format!("bar {}", i))
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..100 {
+        for bar in &bars {
+            bar.inc();
+        }
+    }
+    for mut bar in bars {
+        bar.finish();
+    }
+    Ok(())
+}
+
+fn deep_nesting() -> Result<(), String> {
+    const DEPTH: usize = 500;
+    let root = BarNode::new();
+    root.set_message("deep nesting");
+    let mut leaf = root.clone();
+    for _ in 0..DEPTH {
+        leaf = leaf.add_child(Aggregation::Weighted(1.0));
+    }
+    let mut bar = ProgressBar::from_node(root.clone());
+    leaf.set_length(100);
+    for _ in 0..100 {
+        leaf.inc();
+        thread::sleep(Duration::from_millis(5));
+    }
+    let completed = root.snapshot().completed;
+    bar.finish();
+    if (completed - 1.0).abs() > 1e-6 {
+        return Err(format!(
+            "the root reached {:.1}% when the leaf was done",
+            completed * 100.0
+        ));
+    }
+    Ok(())
+}
+
+fn churn() -> Result<(), String> {
+    thread::scope(|s| {
+        for t in 0..8 {
+            s.spawn(move || {
+                for i in 0..500 {
+                    let bar = ProgressBar::new().with_message(format!("thread {} bar {}", t, i));
+                    for _ in bar.wrap(0..10) {}
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn concurrent_println() -> Result<(), String> {
+    let bar = ProgressBar::new().with_length(400).with_message("printing");
+    thread::scope(|s| {
+        for t in 0..4 {
+            let bar = &bar;
+            s.spawn(move || {
+                for i in 0..100 {
+                    println!("thread {} line {}", t, i);
+                    bar.inc();
+                    thread::sleep(Duration::from_millis(2));
+                }
+            });
+        }
+    });
+    // Printing must not lose any of the increments made at the same time
+    match bar.state.as_ref().map(|state| state.lock().position) {
+        Some(400) => Ok(()),
+        position => Err(format!(
+            "the bar ended at position {:?} instead of 400",
+            position
+        )),
+    }
+}
+
+fn resize() -> Result<(), String> {
+    let previous = MANAGER.lock().terminal_size_override;
+    let bars = (0..10)
+        .map(|i| {
+            ProgressBar::new()
+                .with_length(100)
+                .with_message(format!("resizing {}", i))
+        })
+        .collect::<Vec<_>>();
+    let sizes = [(80, 24), (20, 5), (1, 1), (300, 100), (0, 0), (40, 10)];
+    let mut result = Ok(());
+    for step in 0..60 {
+        let (cols, rows) = sizes[step % sizes.len()];
+        crate::set_terminal_size_override(cols, rows);
+        if crate::terminal_size() != Some((cols, rows)) {
+            result = Err(format!("the size was not changed to {}x{}", cols, rows));
+        }
+        for bar in &bars {
+            bar.inc();
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    match previous {
+        Some((cols, rows)) => crate::set_terminal_size_override(cols, rows),
+        None => crate::clear_terminal_size_override(),
+    }
+    for mut bar in bars {
+        bar.finish();
+    }
+    result
+}
@@ -0,0 +1,95 @@
+use crate::{ProgressBar, ProgressBarIterator};
+
+/// Something which can display progress.
+///
+/// This trait is implemented by [`ProgressBar`], but other crates can implement it too, to be able to use
+/// helpers like [`ProgressBarIterable::progress_with`](crate::ProgressBarIterable::progress_with) and [`ProgressSink::wrap`] without depending on the concrete types in this crate.
+///
+/// ```
+/// use headway::{ProgressBarIterable, ProgressSink};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// /// Reports progress to a GUI, or anything else.
+/// #[derive(Default)]
+/// struct Counter {
+///     position: AtomicUsize,
+/// }
+///
+/// impl ProgressSink for Counter {
+///     fn set_len(&self, _len: usize) {}
+///     fn set_pos(&self, pos: usize) {
+///         self.position.store(pos, Ordering::Relaxed);
+///     }
+///     fn inc(&self) {
+///         self.position.fetch_add(1, Ordering::Relaxed);
+///     }
+///     fn set_message(&self, _message: &str) {}
+///     fn finish(&mut self) {}
+///     fn abandon(&mut self) {}
+/// }
+///
+/// for _ in (0..10).progress_with(Counter::default()) {
+///     // Do some work...
+/// }
+/// ```
+pub trait ProgressSink {
+    /// Sets the total amount of work. See [`ProgressBar::set_length`].
+    fn set_len(&self, len: usize);
+    /// Sets the amount of work that has been completed. See [`ProgressBar::set_position`].
+    fn set_pos(&self, pos: usize);
+    /// Increments the amount of work that has been completed by 1. See [`ProgressBar::inc`].
+    fn inc(&self);
+    /// Sets a message describing the work. See [`ProgressBar::set_message`].
+    fn set_message(&self, message: &str);
+    /// Marks the work as completed. See [`ProgressBar::finish`].
+    fn finish(&mut self);
+    /// Marks the work as abandoned. See [`ProgressBar::abandon`].
+    fn abandon(&mut self);
+
+    /// Wraps the sink around an iterator, like [`ProgressBar::wrap`].
+    ///
+    /// Splitting work into nested bars, like using [`ProgressBar::split_each`], needs the bars of this crate,
+    /// but other sinks can be passed to code which only wraps iterators.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, ProgressSink};
+    ///
+    /// fn checksum(data: &[u8], progress: impl ProgressSink) -> u32 {
+    ///     progress.wrap(data.iter()).map(|&b| b as u32).sum()
+    /// }
+    ///
+    /// assert_eq!(checksum(&[1, 2, 3], ProgressBar::new()), 6);
+    /// ```
+    fn wrap<It: Iterator>(self, it: It) -> ProgressBarIterator<It, Self>
+    where
+        Self: Sized,
+    {
+        ProgressBarIterator::new(self, it)
+    }
+}
+
+impl ProgressSink for ProgressBar {
+    fn set_len(&self, len: usize) {
+        self.set_length(len);
+    }
+
+    fn set_pos(&self, pos: usize) {
+        self.set_position(pos);
+    }
+
+    fn inc(&self) {
+        ProgressBar::inc(self);
+    }
+
+    fn set_message(&self, message: &str) {
+        ProgressBar::set_message(self, message);
+    }
+
+    fn finish(&mut self) {
+        ProgressBar::finish(self);
+    }
+
+    fn abandon(&mut self) {
+        ProgressBar::abandon(self);
+    }
+}
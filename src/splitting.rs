@@ -1,6 +1,39 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{NestedBars, NestedMeta, ProgressBar, ProgressBarState};
+use crate::profile::{measured_durations, CalibratedWeight};
+use crate::sync::Mutex;
+use crate::{NestedBars, NestedMeta, ProgressBar, ProgressBarState, TimingProfile};
+
+/// How a split bar is displayed once it has ended, if some of its parts were never finished.
+///
+/// A part may be unfinished because its child was dropped before it was finished, or because no child was ever created for it,
+/// like when only 70% of a [weighted split](ProgressBar::split_weighted) was taken, or when a loop over [`ProgressBar::split_each`] exited early.
+/// Children which are still in progress are not affected.
+///
+/// The split ends when the nester (and with it the parent bar) is dropped.
+///
+/// ```
+/// use headway::{CompletionPolicy, ProgressBar};
+///
+/// // Optional steps which were skipped should not make the whole job look like it failed
+/// let mut p = ProgressBar::new()
+///     .split_weighted()
+///     .with_completion_policy(CompletionPolicy::Complete);
+/// for _ in p.take(0.7).wrap(0..100) {}
+/// drop(p);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CompletionPolicy {
+    /// Unfinished parts are displayed as abandoned.
+    #[default]
+    Abandon,
+    /// Unfinished parts are displayed as completed, so that the bar ends at 100% once all children have ended.
+    Complete,
+    /// Parts which no child was created for are displayed as empty, so that the bar stays at the progress it reached.
+    /// Children which were abandoned are still displayed as abandoned.
+    Hold,
+}
 
 /// Helper for spliting progress bars
 pub struct ProgressBarWeightedNester {
@@ -9,6 +42,12 @@ pub struct ProgressBarWeightedNester {
 }
 
 impl ProgressBarWeightedNester {
+    /// Sets how unfinished parts of the parent are displayed once it has ended. See [`CompletionPolicy`].
+    pub fn with_completion_policy(self, policy: CompletionPolicy) -> Self {
+        self.bar.state.as_ref().unwrap().lock().completion_policy = policy;
+        self
+    }
+
     /// Adds a new child progress bar, representing a fraction of the parent.
     ///
     /// Normally the total fraction of all child bars that you add should sum up to 1.0.
@@ -24,11 +63,15 @@ impl ProgressBarWeightedNester {
             "fraction_of_total must be at most 1.0"
         );
 
-        let s = Arc::new(Mutex::new(ProgressBarState::default()));
+        let parent = self.bar.state.as_ref().unwrap();
+        let s = Arc::new(Mutex::new(ProgressBarState {
+            parent: Arc::downgrade(parent),
+            ..Default::default()
+        }));
         if let Some(NestedBars {
             bars,
             meta: NestedMeta::Weighted(weights),
-        }) = &mut self.bar.state.as_ref().unwrap().lock().unwrap().nested
+        }) = &mut parent.lock().nested
         {
             bars.push(s.clone());
             weights.push(fraction_of_total);
@@ -62,6 +105,12 @@ pub struct ProgressBarSizedNester {
 }
 
 impl ProgressBarSizedNester {
+    /// Sets how unfinished parts of the parent are displayed once it has ended. See [`CompletionPolicy`].
+    pub fn with_completion_policy(self, policy: CompletionPolicy) -> Self {
+        self.bar.state.as_ref().unwrap().lock().completion_policy = policy;
+        self
+    }
+
     /// Adds a new child progress bar, representing `count` items, to the parent.
     ///
     /// The child bar will have its length set to `count`, but this is not strictly necessary.
@@ -74,7 +123,7 @@ impl ProgressBarSizedNester {
         if let Some(NestedBars {
             bars,
             meta: NestedMeta::Sized(counts),
-        }) = &mut self.bar.state.as_ref().unwrap().lock().unwrap().nested
+        }) = &mut self.bar.state.as_ref().unwrap().lock().nested
         {
             bars.push(s.clone());
             counts.push(count as f64);
@@ -90,7 +139,7 @@ impl ProgressBarSizedNester {
     ///
     /// This method only works if the parent bar has a length set. Otherwise this function will panic.
     pub fn remaining(&mut self) -> ProgressBar {
-        let len = self.bar.state.as_ref().unwrap().lock().unwrap().length;
+        let len = self.bar.state.as_ref().unwrap().lock().length;
         match len {
             Some(len) => {
                 if let Some(remaining) = len.checked_sub(self.taken_count) {
@@ -118,6 +167,12 @@ pub struct ProgressBarSummedNester {
 }
 
 impl ProgressBarSummedNester {
+    /// Sets how unfinished parts of the parent are displayed once it has ended. See [`CompletionPolicy`].
+    pub fn with_completion_policy(self, policy: CompletionPolicy) -> Self {
+        self.bar.state.as_ref().unwrap().lock().completion_policy = policy;
+        self
+    }
+
     /// Adds a new child progress bar to the parent.
     ///
     /// The parent will display the sum of all children's progress and lengths.
@@ -126,13 +181,129 @@ impl ProgressBarSummedNester {
         if let Some(NestedBars {
             bars,
             meta: NestedMeta::Summed,
-        }) = &mut self.bar.state.as_ref().unwrap().lock().unwrap().nested
+        }) = &mut self.bar.state.as_ref().unwrap().lock().nested
+        {
+            bars.push(s.clone());
+        } else {
+            unreachable!();
+        }
+
+        ProgressBar { state: Some(s) }
+    }
+
+    /// Number of children which are still in progress.
+    pub fn active_children(&self) -> usize {
+        self.bar.state.as_ref().unwrap().lock().child_counts().0
+    }
+
+    /// Number of children which have been abandoned, either explicitly or by being dropped before they were finished.
+    ///
+    /// Abandoned children are also shown on the line of the parent, like `(3 failed)`, so that a partial failure is visible at a glance.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let p = ProgressBar::new().split_summed();
+    /// let workers = (0..4).map(|_| p.take().with_length(10)).collect::<Vec<_>>();
+    /// for (i, mut worker) in workers.into_iter().enumerate() {
+    ///     if i == 2 {
+    ///         worker.abandon();
+    ///     } else {
+    ///         worker.finish();
+    ///     }
+    /// }
+    /// assert_eq!(p.abandoned_children(), 1);
+    /// assert_eq!(p.active_children(), 0);
+    /// ```
+    pub fn abandoned_children(&self) -> usize {
+        self.bar.state.as_ref().unwrap().lock().child_counts().1
+    }
+}
+
+/// Helper for spliting progress bars
+pub struct ProgressBarCustomNester {
+    pub(crate) bar: ProgressBar,
+}
+
+impl ProgressBarCustomNester {
+    /// Adds a new child progress bar to the parent.
+    ///
+    /// The progress of the parent is calculated from all children using the [`Aggregate`](crate::core::Aggregate) passed to [`ProgressBar::split_custom`].
+    pub fn take(&self) -> ProgressBar {
+        let s = Arc::new(Mutex::new(ProgressBarState::default()));
+        if let Some(NestedBars {
+            bars,
+            meta: NestedMeta::Custom(_),
+        }) = &mut self.bar.state.as_ref().unwrap().lock().nested
+        {
+            bars.push(s.clone());
+        } else {
+            unreachable!();
+        }
+
+        ProgressBar { state: Some(s) }
+    }
+}
+
+/// Helper for spliting progress bars
+pub struct ProgressBarCalibratedNester {
+    pub(crate) bar: ProgressBar,
+    pub(crate) profile: TimingProfile,
+    pub(crate) names: Vec<String>,
+}
+
+impl ProgressBarCalibratedNester {
+    /// Sets how unfinished parts of the parent are displayed once it has ended. See [`CompletionPolicy`].
+    pub fn with_completion_policy(self, policy: CompletionPolicy) -> Self {
+        self.bar.state.as_ref().unwrap().lock().completion_policy = policy;
+        self
+    }
+
+    /// Adds a new child progress bar, representing a named part of the parent.
+    ///
+    /// The `estimate` is a guess of how large this part is compared to the other parts, like the fractions in [`ProgressBar::split_weighted`].
+    /// It is only used if the duration of this part is not known from the timing profile,
+    /// and it is scaled based on how long the other parts actually took compared to their estimates.
+    pub fn take(&mut self, name: impl Into<String>, estimate: f64) -> ProgressBar {
+        assert!(
+            estimate.is_finite() && estimate >= 0.0,
+            "estimate must be non-negative"
+        );
+        let name = name.into();
+
+        let s = Arc::new(Mutex::new(ProgressBarState::default()));
+        if let Some(NestedBars {
+            bars,
+            meta: NestedMeta::Calibrated(weights),
+        }) = &mut self.bar.state.as_ref().unwrap().lock().nested
         {
             bars.push(s.clone());
+            weights.push(CalibratedWeight {
+                estimate,
+                profiled: self.profile.get(&name).map(|d| d.as_secs_f64()),
+            });
         } else {
             unreachable!();
         }
 
+        self.names.push(name);
         ProgressBar { state: Some(s) }
     }
+
+    /// The timing profile, updated with the durations of all parts which have finished so far.
+    ///
+    /// Parts are assumed to run one after another, so a part is considered to have started when it was created
+    /// or when the previous part finished, whichever is later.
+    /// Save this using [`TimingProfile::save`] when the job is done, to get more accurate progress the next time it runs.
+    pub fn profile(&self) -> TimingProfile {
+        let mut profile = self.profile.clone();
+        if let Some(NestedBars { bars, .. }) = &self.bar.state.as_ref().unwrap().lock().nested {
+            for (name, duration) in self.names.iter().zip(measured_durations(bars)) {
+                if let Some(duration) = duration {
+                    profile.set(name.clone(), Duration::from_secs_f64(duration));
+                }
+            }
+        }
+        profile
+    }
 }
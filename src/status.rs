@@ -0,0 +1,156 @@
+//! Single line status reports, for displaying the progress of background jobs in e.g. shell prompts and tmux status bars.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::{on_frame, RenderedFrame, MANAGER};
+
+/// Keeps a file updated with a single line describing the overall progress, like `45% Uploading assets`.
+///
+/// The line contains the average progress of all visible bars, and the message of the first unfinished bar.
+/// When no bars are visible, the file is emptied.
+/// The file is replaced atomically, so readers never see partially written content.
+///
+/// This can be used to display the progress of a long-running job in a shell prompt or a tmux status bar:
+///
+/// ```text
+/// set -g status-right '#(cat /tmp/backup.status)'
+/// ```
+///
+/// Errors while writing the file are ignored.
+///
+/// ```no_run
+/// use headway::ProgressBar;
+///
+/// headway::write_status_file("/tmp/backup.status");
+/// let p = ProgressBar::new().with_message("Backing up");
+/// for _ in p.wrap(0..100) {
+///     // ...
+/// }
+/// ```
+pub fn write_status_file(path: impl Into<PathBuf>) {
+    let path = path.into();
+    let mut tmp_path = path.clone().into_os_string();
+    tmp_path.push(".tmp");
+    let mut last = None;
+    on_frame(move |frame| {
+        let line = status_line(frame);
+        if last.as_ref() == Some(&line) {
+            return;
+        }
+        let contents = if line.is_empty() {
+            String::new()
+        } else {
+            format!("{}\n", line)
+        };
+        if std::fs::write(&tmp_path, contents)
+            .and_then(|_| std::fs::rename(&tmp_path, &path))
+            .is_ok()
+        {
+            last = Some(line);
+        }
+    });
+}
+
+/// Sends a single line describing the overall progress to a UNIX socket, each time it changes.
+///
+/// The line is formatted like in [`write_status_file`], and is terminated by a newline.
+/// An empty line is sent when no bars are visible.
+/// If the socket cannot be connected to, sending is retried when the status changes next time.
+///
+/// ```no_run
+/// headway::send_status_to_socket("/run/user/1000/backup.sock");
+/// ```
+#[cfg(unix)]
+pub fn send_status_to_socket(path: impl Into<PathBuf>) {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    let path = path.into();
+    let mut stream: Option<UnixStream> = None;
+    let mut last = None;
+    on_frame(move |frame| {
+        let line = status_line(frame);
+        if last.as_ref() == Some(&line) {
+            return;
+        }
+        if stream.is_none() {
+            stream = UnixStream::connect(&path).ok();
+        }
+        if let Some(s) = &mut stream {
+            if writeln!(s, "{}", line).is_ok() {
+                last = Some(line);
+            } else {
+                // The listener has probably gone away. Try to reconnect next time.
+                stream = None;
+            }
+        }
+    });
+}
+
+/// Displays the overall progress in the title of the terminal window, like `45% Backing up 3 volumes`.
+///
+/// This makes the progress visible when the terminal is not focused, for example when hovering over it in the taskbar.
+/// The title contains the average progress of all visible bars, followed by the title set using [`crate::set_title`],
+/// or otherwise the message of the first unfinished bar.
+/// The original window title is restored once all bars have finished.
+///
+/// The window title is only changed when the output is a terminal, and never in accessible mode (see [`crate::set_accessible_mode`]).
+///
+/// ```
+/// use headway::ProgressBar;
+///
+/// headway::set_window_title_progress(true);
+/// let p = ProgressBar::new().with_message("Rendering");
+/// for _ in p.wrap(0..100) {
+///     // ...
+/// }
+/// ```
+pub fn set_window_title_progress(enabled: bool) {
+    MANAGER.lock().window_title_progress = enabled;
+}
+
+/// The window title to use for the given frame. See [`set_window_title_progress`].
+pub(crate) fn window_title(frame: &RenderedFrame) -> String {
+    let mut title = format_status(frame, frame.title().or_else(|| current_message(frame)));
+    // Control characters would end the escape sequence early
+    title.retain(|c| !c.is_control());
+    title
+}
+
+/// The overall progress of all bars in the frame, like `45% Uploading assets`.
+fn status_line(frame: &RenderedFrame) -> String {
+    format_status(frame, current_message(frame))
+}
+
+/// The average progress of all bars in the frame, followed by the given text.
+fn format_status(frame: &RenderedFrame, text: Option<&str>) -> String {
+    let progress = frame
+        .bars()
+        .iter()
+        .filter_map(|b| b.progress())
+        .collect::<Vec<_>>();
+
+    let mut line = String::new();
+    if !progress.is_empty() {
+        let average = progress.iter().sum::<f64>() / progress.len() as f64;
+        // SAFETY: Writes to strings cannot fail
+        write!(line, "{}%", (average * 100.0).floor() as usize).unwrap();
+    }
+    if let Some(text) = text {
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(text);
+    }
+    line
+}
+
+/// The message of the first unfinished bar in the frame.
+fn current_message(frame: &RenderedFrame) -> Option<&str> {
+    frame
+        .bars()
+        .iter()
+        .find(|b| !b.is_finished() && !b.is_abandoned())
+        .and_then(|b| b.message())
+}
@@ -0,0 +1,771 @@
+use std::time::Duration;
+
+use crate::{
+    template::Template, CountFormat, DurationFormat, Labels, PlainFormat, Theme, BAR_EMPTY,
+    BAR_FILLED, BAR_LEFT_BORDER, BAR_PARTIALLY_FILLED, BAR_RIGHT_BORDER, MANAGER,
+};
+
+/// Controls how a progress bar is displayed.
+///
+/// A style can be set for individual bars using [`ProgressBar::set_style`](crate::ProgressBar::set_style),
+/// or for all bars at once using [`set_default_style`].
+///
+/// There are several presets available, like [`Style::ascii`] and [`Style::cargo`], which can be further customized if desired.
+///
+/// ```
+/// use headway::{ProgressBar, Style};
+///
+/// let p = ProgressBar::new()
+///     .with_style(Style::ascii().with_prefix_width(10))
+///     .with_prefix("Fetching");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Style {
+    pub(crate) prefix_width: usize,
+    pub(crate) bold_prefix: bool,
+    pub(crate) aggregate_bar_only: bool,
+    pub(crate) bar_width: usize,
+    pub(crate) auto_width: Option<(usize, usize)>,
+    pub(crate) filled: char,
+    pub(crate) empty: char,
+    pub(crate) smooth_fill: bool,
+    pub(crate) partial_fill: Vec<char>,
+    pub(crate) left_border: String,
+    pub(crate) right_border: String,
+    pub(crate) stall_threshold: Option<Duration>,
+    pub(crate) message_interval: Duration,
+    pub(crate) labels: Labels,
+    pub(crate) theme: Theme,
+    pub(crate) count_format: CountFormat,
+    pub(crate) duration_format: DurationFormat,
+    pub(crate) show_elapsed: bool,
+    pub(crate) show_eta: bool,
+    pub(crate) show_activity: bool,
+    pub(crate) show_rate: bool,
+    pub(crate) length_growth: bool,
+    pub(crate) completed_color: Option<Color>,
+    pub(crate) completed_glyph: String,
+    pub(crate) segments: bool,
+    pub(crate) thresholds: Vec<Threshold>,
+    pub(crate) template: Option<Template>,
+    pub(crate) animation_phase: AnimationPhase,
+    pub(crate) plain_format: PlainFormat,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Style {
+    /// The default style.
+    ///
+    /// ```text
+    /// ▕██████████▌         ▏ 53/100
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            prefix_width: 0,
+            bold_prefix: false,
+            aggregate_bar_only: false,
+            bar_width: 20,
+            auto_width: None,
+            filled: BAR_FILLED,
+            empty: BAR_EMPTY,
+            smooth_fill: true,
+            partial_fill: BAR_PARTIALLY_FILLED.to_vec(),
+            left_border: BAR_LEFT_BORDER.to_string(),
+            right_border: BAR_RIGHT_BORDER.to_string(),
+            stall_threshold: None,
+            message_interval: Duration::ZERO,
+            labels: Labels::new(),
+            theme: Theme::new(),
+            count_format: CountFormat::Plain,
+            duration_format: DurationFormat::Compact,
+            show_elapsed: false,
+            show_eta: false,
+            show_activity: false,
+            show_rate: false,
+            length_growth: false,
+            completed_color: Some(Color::Green),
+            completed_glyph: String::new(),
+            segments: false,
+            thresholds: vec![],
+            template: None,
+            animation_phase: AnimationPhase::PerBar,
+            plain_format: PlainFormat::Readable,
+        }
+    }
+
+    /// A wider bar using unicode block characters, with a shaded background.
+    ///
+    /// ```text
+    /// ▕███████████████▉░░░░░░░░░░░░░░▏ 53/100
+    /// ```
+    pub fn fancy_unicode() -> Self {
+        Self::new().with_bar_width(30).with_bar_chars('█', '░')
+    }
+
+    /// A style which only uses ASCII characters.
+    ///
+    /// Useful for terminals or fonts which render block characters poorly.
+    ///
+    /// ```text
+    /// [##########----------] 53/100
+    /// ```
+    pub fn ascii() -> Self {
+        Self::new()
+            .with_bar_chars('#', '-')
+            .with_smooth_fill(false)
+            .with_borders("[", "]")
+    }
+
+    /// A compact bar without borders.
+    ///
+    /// ```text
+    /// ━━━━━───── 53/100
+    /// ```
+    pub fn minimal() -> Self {
+        Self::new()
+            .with_bar_width(10)
+            .with_bar_chars('━', '─')
+            .with_smooth_fill(false)
+            .with_borders("", "")
+    }
+
+    /// A style which mimics the output of `docker pull`.
+    ///
+    /// ```text
+    /// 3f4ca61aafcd [=====================                   ] 53/100 Downloading
+    /// ```
+    pub fn docker() -> Self {
+        Self::new()
+            .with_bar_width(40)
+            .with_bar_chars('=', ' ')
+            .with_smooth_fill(false)
+            .with_borders("[", "]")
+    }
+
+    /// A style which mimics the output of `cargo`.
+    ///
+    /// The prefix of each bar is displayed as a bold, right-aligned verb, followed by the message.
+    /// Only bars which have been split (see e.g. [`ProgressBar::split_summed`](crate::ProgressBar::split_summed)) show the actual bar.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// headway::set_default_style(Style::cargo());
+    /// let crates = ["serde", "rand", "headway"];
+    /// for (mut child, name) in ProgressBar::new().with_prefix("Building").split_each(crates.iter()) {
+    ///     let mut line = ProgressBar::new().with_prefix("Compiling").with_message(*name);
+    ///     // Compile the crate...
+    ///     line.finish();
+    ///     child.finish();
+    /// }
+    /// ```
+    ///
+    /// While compiling the second crate, this renders as
+    ///
+    /// ```text
+    ///     Building ▕██████▋             ▏ 1/3
+    ///    Compiling rand
+    /// ```
+    pub fn cargo() -> Self {
+        Self::new()
+            .with_prefix_width(12)
+            .with_bold_prefix(true)
+            .with_aggregate_bar_only(true)
+    }
+
+    /// Pads the prefix of the bar so that it is right-aligned in a column of the given width.
+    ///
+    /// The column is reserved even for bars without a prefix, so that all bars line up.
+    /// A width of zero disables padding.
+    pub fn with_prefix_width(mut self, width: usize) -> Self {
+        self.prefix_width = width;
+        self
+    }
+
+    /// Sets the width of the bar itself, in characters.
+    ///
+    /// This does not include the borders, or any text displayed next to the bar.
+    /// This disables [`Self::with_auto_width`].
+    pub fn with_bar_width(mut self, width: usize) -> Self {
+        self.bar_width = width;
+        self.auto_width = None;
+        self
+    }
+
+    /// Makes the bar expand to fill the width of the terminal, but keeps its width between `min` and `max` characters.
+    ///
+    /// The width is recomputed for every frame, so bars adapt when the terminal is resized or the text next to them changes.
+    /// If the width of the terminal is not known, the width set using [`Self::with_bar_width`] is used instead.
+    /// For templates (see [`Self::with_template`]) this applies to `{bar}` placeholders without an explicit width.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// headway::set_default_style(Style::new().with_auto_width(10, 80));
+    /// let p = ProgressBar::new().with_length(100).with_message("Fetching");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is larger than `max`.
+    pub fn with_auto_width(mut self, min: usize, max: usize) -> Self {
+        assert!(
+            min <= max,
+            "the minimum width must not be larger than the maximum"
+        );
+        self.auto_width = Some((min, max));
+        self
+    }
+
+    /// Sets the characters used for the filled and empty parts of the bar.
+    ///
+    /// For terminals which render block characters poorly, this can be combined with [`Self::with_partial_fill`] and [`Self::with_borders`]
+    /// to draw bars using only ASCII characters.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// let style = Style::new()
+    ///     .with_bar_chars('=', '-')
+    ///     .with_partial_fill(['>'])
+    ///     .with_borders("[", "]");
+    /// let p = ProgressBar::new().with_style(style).with_length(100);
+    /// p.set_position(53);
+    /// ```
+    ///
+    /// This renders as
+    ///
+    /// ```text
+    /// [==========>---------] 53/100
+    /// ```
+    pub fn with_bar_chars(mut self, filled: char, empty: char) -> Self {
+        self.filled = filled;
+        self.empty = empty;
+        self
+    }
+
+    /// Sets the text drawn on the left and right side of the bar.
+    ///
+    /// Use empty strings to draw no borders at all.
+    pub fn with_borders(mut self, left: impl Into<String>, right: impl Into<String>) -> Self {
+        self.left_border = left.into();
+        self.right_border = right.into();
+        self
+    }
+
+    /// If enabled, partially filled cells are drawn using unicode block characters of different widths.
+    ///
+    /// This makes the bar advance more smoothly, but only looks good if the filled character is a full block (`█`).
+    /// The characters can be changed using [`Self::with_partial_fill`].
+    pub fn with_smooth_fill(mut self, enabled: bool) -> Self {
+        self.smooth_fill = enabled;
+        self
+    }
+
+    /// Sets the characters used for cells which are only partially filled, from the least to the most filled.
+    ///
+    /// The cell is divided evenly between the characters, and cells which are filled less than the first character represents are drawn as empty.
+    /// For example, with `['>']` cells which are at least half filled are drawn as `>`.
+    /// By default, unicode block characters of increasing widths are used (`▏▎▍▌▋▊▉`).
+    ///
+    /// This also enables [`Self::with_smooth_fill`].
+    pub fn with_partial_fill(mut self, chars: impl IntoIterator<Item = char>) -> Self {
+        self.partial_fill = chars.into_iter().collect();
+        self.smooth_fill = true;
+        self
+    }
+
+    /// The character used for a cell which is filled by the given fraction, or None if it should be drawn as empty. See [`Self::with_partial_fill`].
+    pub(crate) fn partial_fill_char(&self, fraction: f64) -> Option<char> {
+        let step = (fraction * (self.partial_fill.len() + 1) as f64).floor() as usize;
+        step.checked_sub(1)
+            .and_then(|i| self.partial_fill.get(i))
+            .copied()
+    }
+
+    /// Marks bars which have not been updated for the given duration as stalled.
+    ///
+    /// Stalled bars are displayed with a dimmed `stalled 45s` suffix, so that hung workers are easy to notice.
+    /// Any update to a bar, like incrementing it or setting its message, resets the timer.
+    /// Slow steps which do not update the bar can use [`ProgressBar::touch`](crate::ProgressBar::touch) to avoid being marked as stalled.
+    ///
+    /// By default, bars are never marked as stalled.
+    pub fn with_stall_threshold(mut self, threshold: Duration) -> Self {
+        self.stall_threshold = Some(threshold);
+        self
+    }
+
+    /// Limits how often the displayed message can change.
+    ///
+    /// When the message is set for every item, like in the `split_each` example, it may change faster than it can be read.
+    /// With an interval, a new message is only displayed once the previous one has been visible for at least that long.
+    /// The most recent message is always the one displayed next, and finished bars always display their final message.
+    /// The interval of a top-level bar applies to the messages of its nested bars too, since they are displayed on the same line.
+    ///
+    /// By default, the message is displayed as soon as it changes.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// let p = ProgressBar::new()
+    ///     .with_style(Style::new().with_message_interval(Duration::from_millis(500)));
+    /// for (item, i) in p.split_each(0..1000) {
+    ///     item.set_message(format!("Processing item {}", i));
+    ///     for _ in item.wrap(0..10) {}
+    /// }
+    /// ```
+    pub fn with_message_interval(mut self, interval: Duration) -> Self {
+        self.message_interval = interval;
+        self
+    }
+
+    /// Sets the words displayed next to the bar, for example to translate them to another language.
+    ///
+    /// See [`Labels`].
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Sets the colors used to draw the bar, see [`Theme`].
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets how item counts are displayed, like the position and length of the bar.
+    ///
+    /// This is useful for bars with very large lengths, which are hard to read otherwise.
+    ///
+    /// ```
+    /// use headway::{CountFormat, ProgressBar, Style};
+    ///
+    /// let p = ProgressBar::new()
+    ///     .with_style(Style::new().with_count_format(CountFormat::Si))
+    ///     .with_length(200_000_000);
+    /// p.set_position(123_456_789);
+    /// ```
+    ///
+    /// This renders as
+    ///
+    /// ```text
+    /// ▕████████████▎       ▏ 123.4M/200M
+    /// ```
+    pub fn with_count_format(mut self, format: CountFormat) -> Self {
+        self.count_format = format;
+        self
+    }
+
+    /// Displays how long the bar has been running, like `1m12s elapsed`.
+    ///
+    /// Once the bar is finished or abandoned, the time it took is displayed instead.
+    pub fn with_elapsed(mut self, enabled: bool) -> Self {
+        self.show_elapsed = enabled;
+        self
+    }
+
+    /// Displays an estimate of the remaining time, like `3m12s left`.
+    ///
+    /// The estimate assumes that the bar keeps progressing at the same average speed as it has since it started making progress.
+    /// It is only displayed for bars with a length which have made some progress.
+    ///
+    /// For bars split using weights or sizes, the estimate is the sum of the estimates of the children. Children which have not started yet
+    /// are estimated from their weight, based on how long the other children have taken per unit of weight.
+    pub fn with_eta(mut self, enabled: bool) -> Self {
+        self.show_eta = enabled;
+        self
+    }
+
+    /// Displays how much of its lifetime the bar spent making progress, like `active 80% of 5m`, once it is finished or abandoned.
+    ///
+    /// Time between progress updates counts as idle if the updates are at least a second apart.
+    /// A bar which spends a lot of time idle is often waiting on something else, like a lock or I/O.
+    /// For bars which have been split, the active time is the sum of the active times of the children.
+    ///
+    /// ```text
+    /// ▕████████████████████▏ 20/20 active 80% of 5m
+    /// ```
+    ///
+    /// The same information is available as [`BarTiming::active`](crate::BarTiming::active) when inspecting frames.
+    pub fn with_activity(mut self, enabled: bool) -> Self {
+        self.show_activity = enabled;
+        self
+    }
+
+    /// Displays how fast the bar is making progress, like `1.2k it/s`.
+    ///
+    /// While the bar is in progress, this is the rate over the last few seconds, so it drops when the bar stops making progress.
+    /// Once the bar is finished or abandoned, the average rate over the whole time it was making progress is displayed instead.
+    /// The rate is only known for bars which are updated directly, and not for bars which have been split.
+    ///
+    /// ```text
+    /// ▕██████████▌         ▏ 530/1000 1.2k it/s
+    /// ```
+    ///
+    /// The same value is available from [`ProgressBar::per_sec`](crate::ProgressBar::per_sec).
+    pub fn with_rate(mut self, enabled: bool) -> Self {
+        self.show_rate = enabled;
+        self
+    }
+
+    /// Makes it easier to follow what happens when the length of a bar grows, for example because more work was discovered.
+    ///
+    /// Normally the bar jumps backwards when the length grows. With this option, the bar instead shrinks smoothly to the new scale,
+    /// and the number of added items is displayed for a few seconds.
+    ///
+    /// ```text
+    /// ▕████████▌           ▏ 60/140 (+40 added)
+    /// ```
+    pub fn with_length_growth(mut self, enabled: bool) -> Self {
+        self.length_growth = enabled;
+        self
+    }
+
+    /// Sets how durations are displayed, like the elapsed time or how long a bar has been stalled.
+    ///
+    /// ```
+    /// use headway::{DurationFormat, ProgressBar, Style};
+    ///
+    /// let p = ProgressBar::new().with_style(
+    ///     Style::new()
+    ///         .with_elapsed(true)
+    ///         .with_eta(true)
+    ///         .with_duration_format(DurationFormat::Clock),
+    /// );
+    /// ```
+    ///
+    /// Halfway through a task which has been running for 3 minutes, this renders as
+    ///
+    /// ```text
+    /// ▕██████████          ▏ 50/100 00:03:00 elapsed 00:03:00 left
+    /// ```
+    pub fn with_duration_format(mut self, format: DurationFormat) -> Self {
+        self.duration_format = format;
+        self
+    }
+
+    /// Sets the color of the filled part of bars which have been finished, or None to draw them like any other bar.
+    ///
+    /// This makes it easy to tell apart finished bars from bars which are full, but still waiting for something.
+    /// Finished bars are green by default.
+    pub fn with_completed_color(mut self, color: Option<Color>) -> Self {
+        self.completed_color = color;
+        self
+    }
+
+    /// Sets a symbol which is displayed in front of bars which have been finished, like `✓`.
+    ///
+    /// Other bars are padded with spaces instead, so that all bars line up.
+    /// Use an empty string to disable the symbol, which is the default.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// headway::set_default_style(Style::new().with_completed_glyph("✓"));
+    /// let mut p = ProgressBar::new().with_length(100);
+    /// p.finish();
+    /// ```
+    ///
+    /// Next to a bar which is still in progress, this renders as
+    ///
+    /// ```text
+    /// ✓ ▕████████████████████▏ 100/100
+    ///   ▕██████████▌         ▏ 53/100
+    /// ```
+    pub fn with_completed_glyph(mut self, glyph: impl Into<String>) -> Self {
+        self.completed_glyph = glyph.into();
+        self
+    }
+
+    /// Draws separators between the children of bars which have been split using
+    /// [`ProgressBar::split_weighted`](crate::ProgressBar::split_weighted) or [`ProgressBar::split_sized`](crate::ProgressBar::split_sized).
+    ///
+    /// The remaining part of the child which is currently being worked on is highlighted, if colors are enabled.
+    /// This shows the structure of the work, even though the children are not displayed separately.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// let mut p = ProgressBar::new()
+    ///     .with_style(Style::new().with_segments(true))
+    ///     .split_weighted();
+    /// let download = p.take(0.5);
+    /// let verify = p.take(0.25);
+    /// let install = p.take(0.25);
+    /// ```
+    ///
+    /// Halfway through verifying, this renders as
+    ///
+    /// ```text
+    /// ▕██████████│█▌  │    ▏ 62%
+    /// ```
+    pub fn with_segments(mut self, enabled: bool) -> Self {
+        self.segments = enabled;
+        self
+    }
+
+    /// Colors the position or percentage next to the bar when the given rule matches.
+    ///
+    /// This can be used to make it easy to spot bars which need attention in a large number of bars.
+    /// Rules are checked in the order they were added, and the first matching rule decides the color.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use headway::{Color, ProgressBar, Style, Threshold};
+    ///
+    /// let style = Style::new()
+    ///     .with_threshold(Threshold::at_least(1.0, Color::Green))
+    ///     .with_threshold(Threshold::below(0.1, Color::Red).after(Duration::from_secs(60)));
+    /// let p = ProgressBar::new().with_style(style);
+    /// ```
+    pub fn with_threshold(mut self, threshold: Threshold) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// The color of the first threshold which matches, see [`Self::with_threshold`].
+    pub(crate) fn threshold_color(
+        &self,
+        progress: Option<f64>,
+        elapsed: Duration,
+    ) -> Option<Color> {
+        let progress = progress?;
+        self.thresholds
+            .iter()
+            .find(|t| t.matches(progress, elapsed))
+            .map(|t| t.color)
+    }
+
+    /// Lays out the line of each bar using a template, like `"{prefix} {bar:30} {pos}/{len} {eta} {msg}"`.
+    ///
+    /// Each placeholder in braces is replaced by a part of the bar:
+    ///
+    /// * `{prefix}` and `{msg}`: the prefix and message of the bar
+    /// * `{bar}`: the bar itself, including its borders. `{bar:30}` sets its width, which is [`Self::with_bar_width`] otherwise.
+    /// * `{pos}` and `{len}`: the position and length, formatted using [`Self::with_count_format`]
+    /// * `{percent}`: the percentage which is done, like `53%`
+    /// * `{elapsed}` and `{eta}`: how long the bar has been running, and the estimated time left, formatted using [`Self::with_duration_format`]
+    /// * `{rate}`: how fast the bar is making progress, like `1.2k it/s`
+    ///
+    /// Other placeholders can be padded to a minimum width, left-aligned using `{msg:20}` or right-aligned using `{prefix:>12}`.
+    /// Fields which are not known, like the time left before any progress has been made, are empty.
+    /// Use `{{` and `}}` to insert literal braces.
+    ///
+    /// The options which add text after the bar, like [`Self::with_eta`], have no effect on bars drawn using a template,
+    /// but the reason a bar was abandoned, extra fields (see [`ProgressBar::add_extra_field`](crate::ProgressBar::add_extra_field))
+    /// and whether a bar has stalled are still displayed at the end of the line.
+    /// Templates are not used for plain text output, like in accessible mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template contains an unknown placeholder, or an unmatched brace.
+    ///
+    /// ```
+    /// use headway::{ProgressBar, Style};
+    ///
+    /// let p = ProgressBar::new()
+    ///     .with_style(Style::ascii().with_template("{prefix:>10} {bar:30} {pos}/{len} {eta} {msg}"))
+    ///     .with_prefix("Fetching")
+    ///     .with_message("assets.tar.gz")
+    ///     .with_length(100);
+    /// p.set_position(53);
+    /// ```
+    ///
+    /// This renders as
+    ///
+    /// ```text
+    ///   Fetching [###############---------------] 53/100 12s assets.tar.gz
+    /// ```
+    pub fn with_template(mut self, template: &str) -> Self {
+        match Template::parse(template) {
+            Ok(template) => self.template = Some(template),
+            Err(problem) => panic!("invalid template {:?}: {}", template, problem),
+        }
+        self
+    }
+
+    /// Displays the prefix of the bar in bold.
+    pub fn with_bold_prefix(mut self, bold: bool) -> Self {
+        self.bold_prefix = bold;
+        self
+    }
+
+    /// Sets how the bar is described when it is printed as plain text, like in accessible mode (see [`crate::set_accessible_mode`])
+    /// and in logs (see [`crate::set_journal_output`]). See [`PlainFormat`].
+    ///
+    /// With [`PlainFormat::Columns`], bars which are printed once they are finished because the output is not a terminal
+    /// are printed as columns as well, so that the output can be piped to tools like `awk`.
+    ///
+    /// ```
+    /// use headway::testing::MemoryTarget;
+    /// use headway::{Config, PlainFormat, ProgressBar, Style};
+    ///
+    /// // Like running `my-tool | awk '{ print $3 }'`
+    /// let pipe = MemoryTarget::new().with_terminal(false);
+    /// headway::init_with(Config::new().with_draw_target(pipe.clone())).unwrap();
+    /// headway::set_default_style(Style::new().with_plain_format(PlainFormat::Columns));
+    ///
+    /// let mut p = ProgressBar::new().with_length(200).with_message("Downloading");
+    /// p.set_position(50);
+    /// p.abandon();
+    /// assert_eq!(
+    ///     pipe.printed(),
+    ///     "abandoned  25           50          200        0        - Downloading\n"
+    /// );
+    /// ```
+    pub fn with_plain_format(mut self, format: PlainFormat) -> Self {
+        self.plain_format = format;
+        self
+    }
+
+    /// Sets how the animation of indeterminate bars is timed, see [`AnimationPhase`].
+    ///
+    /// ```
+    /// use headway::{AnimationPhase, Style};
+    ///
+    /// // Keep all spinners in step, so that many bars don't shimmer
+    /// headway::set_default_style(Style::new().with_animation_phase(AnimationPhase::Synchronized));
+    /// ```
+    pub fn with_animation_phase(mut self, phase: AnimationPhase) -> Self {
+        self.animation_phase = phase;
+        self
+    }
+
+    /// Only draw the bar itself for bars which have been split into children.
+    ///
+    /// Other bars will only display their prefix and message.
+    pub fn with_aggregate_bar_only(mut self, enabled: bool) -> Self {
+        self.aggregate_bar_only = enabled;
+        self
+    }
+}
+
+/// Sets the style used by all bars which do not have a style of their own.
+///
+/// See [`Style`].
+pub fn set_default_style(style: Style) {
+    let mut manager = MANAGER.lock();
+    manager.default_style = style;
+    manager.render_cache.clear();
+}
+
+/// How the animation of indeterminate bars is timed. See [`Style::with_animation_phase`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationPhase {
+    /// Each bar is animated separately. Every [`ProgressBar::heartbeat`](crate::ProgressBar::heartbeat) nudges the animation of the bar forward.
+    #[default]
+    PerBar,
+    /// All bars share a single phase, so that they animate in step. Heartbeats do not affect the animation.
+    Synchronized,
+    /// All bars share a single phase, and every cell of a bar has the same color, so that whole bars pulse instead of moving.
+    Uniform,
+}
+
+/// A rule which colors the numbers next to a bar depending on its progress. See [`Style::with_threshold`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Threshold {
+    min: f64,
+    max: f64,
+    after: Duration,
+    color: Color,
+}
+
+impl Threshold {
+    /// Matches bars where the fraction which is complete is less than the given value.
+    pub fn below(fraction: f64, color: Color) -> Self {
+        Self {
+            min: f64::NEG_INFINITY,
+            max: fraction,
+            after: Duration::ZERO,
+            color,
+        }
+    }
+
+    /// Matches bars where the fraction which is complete is at least the given value.
+    pub fn at_least(fraction: f64, color: Color) -> Self {
+        Self {
+            min: fraction,
+            max: f64::INFINITY,
+            after: Duration::ZERO,
+            color,
+        }
+    }
+
+    /// Only matches bars which have been running for at least the given duration.
+    pub fn after(mut self, elapsed: Duration) -> Self {
+        self.after = elapsed;
+        self
+    }
+
+    fn matches(&self, progress: f64, elapsed: Duration) -> bool {
+        progress >= self.min && progress < self.max && elapsed >= self.after
+    }
+}
+
+/// A terminal color.
+///
+/// See for example [`ProgressBar::set_color`](crate::ProgressBar::set_color).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Grey,
+    /// One of the 256 colors in the extended ANSI palette.
+    Fixed(u8),
+    /// A 24-bit color. Not all terminals support these.
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Writes the escape code which sets this as the foreground color.
+    pub(crate) fn write_foreground(self, out: &mut String) {
+        use std::fmt::Write;
+
+        // SAFETY: Writes to strings cannot fail
+        match self {
+            Color::Black => out.push_str("\u{001b}[30m"),
+            Color::Red => out.push_str("\u{001b}[31m"),
+            Color::Green => out.push_str("\u{001b}[32m"),
+            Color::Yellow => out.push_str("\u{001b}[33m"),
+            Color::Blue => out.push_str("\u{001b}[34m"),
+            Color::Magenta => out.push_str("\u{001b}[35m"),
+            Color::Cyan => out.push_str("\u{001b}[36m"),
+            Color::White => out.push_str("\u{001b}[37m"),
+            Color::Grey => out.push_str("\u{001b}[90m"),
+            Color::Fixed(index) => write!(out, "\u{001b}[38;5;{}m", index).unwrap(),
+            Color::Rgb(r, g, b) => write!(out, "\u{001b}[38;2;{};{};{}m", r, g, b).unwrap(),
+        }
+    }
+
+    /// Writes the escape code which sets this as the background color.
+    pub(crate) fn write_background(self, out: &mut String) {
+        use std::fmt::Write;
+
+        // SAFETY: Writes to strings cannot fail
+        match self {
+            Color::Black => out.push_str("\u{001b}[40m"),
+            Color::Red => out.push_str("\u{001b}[41m"),
+            Color::Green => out.push_str("\u{001b}[42m"),
+            Color::Yellow => out.push_str("\u{001b}[43m"),
+            Color::Blue => out.push_str("\u{001b}[44m"),
+            Color::Magenta => out.push_str("\u{001b}[45m"),
+            Color::Cyan => out.push_str("\u{001b}[46m"),
+            Color::White => out.push_str("\u{001b}[47m"),
+            Color::Grey => out.push_str("\u{001b}[100m"),
+            Color::Fixed(index) => write!(out, "\u{001b}[48;5;{}m", index).unwrap(),
+            Color::Rgb(r, g, b) => write!(out, "\u{001b}[48;2;{};{};{}m", r, g, b).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Synchronization primitives used throughout the crate.
+//!
+//! With the `parking_lot` feature enabled, these are backed by [parking_lot](https://docs.rs/parking_lot),
+//! which is faster when a lock is not contended, as is usually the case for the locks of individual bars.
+
+#[cfg(not(feature = "parking_lot"))]
+use std::sync::PoisonError;
+
+#[cfg(not(feature = "parking_lot"))]
+pub(crate) type MutexGuard<'a, T> = std::sync::MutexGuard<'a, T>;
+#[cfg(feature = "parking_lot")]
+pub(crate) type MutexGuard<'a, T> = parking_lot::MutexGuard<'a, T>;
+
+/// A mutex which ignores lock poisoning.
+///
+/// If a thread panics while holding a lock, the standard library mutex becomes poisoned and every subsequent
+/// `lock().unwrap()` will panic too, even in completely unrelated threads.
+/// Progress bar state is always valid (if possibly slightly stale), so we just keep using it instead.
+/// This also makes it safe to abandon bars from `Drop` while unwinding.
+#[cfg(not(feature = "parking_lot"))]
+#[derive(Default)]
+pub(crate) struct Mutex<T: ?Sized>(std::sync::Mutex<T>);
+
+/// A mutex which ignores lock poisoning. parking_lot mutexes are never poisoned.
+#[cfg(feature = "parking_lot")]
+#[derive(Default)]
+pub(crate) struct Mutex<T: ?Sized>(parking_lot::Mutex<T>);
+
+#[cfg(not(feature = "parking_lot"))]
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Self(std::sync::Mutex::new(value))
+    }
+}
+
+#[cfg(not(feature = "parking_lot"))]
+impl<T: ?Sized> Mutex<T> {
+    /// Acquires the lock, blocking the current thread until it is available.
+    ///
+    /// Unlike [`std::sync::Mutex::lock`], this never fails.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Acquires the lock if it is available, without blocking.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        match self.0.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(std::sync::TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        }
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Self(parking_lot::Mutex::new(value))
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl<T: ?Sized> Mutex<T> {
+    /// Acquires the lock, blocking the current thread until it is available.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock()
+    }
+
+    /// Acquires the lock if it is available, without blocking.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.0.try_lock()
+    }
+}
+
+/// A condition variable which ignores lock poisoning, for use with [`Mutex`].
+#[cfg(not(feature = "parking_lot"))]
+#[derive(Default)]
+pub(crate) struct Condvar(std::sync::Condvar);
+
+/// A condition variable for use with [`Mutex`].
+#[cfg(feature = "parking_lot")]
+#[derive(Default)]
+pub(crate) struct Condvar(parking_lot::Condvar);
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self(Default::default())
+    }
+
+    /// Blocks the current thread until this condition variable is notified.
+    ///
+    /// The lock is released while waiting, and acquired again before returning.
+    #[cfg(not(feature = "parking_lot"))]
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.0.wait(guard).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Blocks the current thread until this condition variable is notified.
+    ///
+    /// The lock is released while waiting, and acquired again before returning.
+    #[cfg(feature = "parking_lot")]
+    pub fn wait<'a, T>(&self, mut guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.0.wait(&mut guard);
+        guard
+    }
+
+    /// Wakes up one thread blocked on this condition variable.
+    pub fn notify_one(&self) {
+        self.0.notify_one();
+    }
+}
@@ -0,0 +1,129 @@
+//! Parsing of the templates set using [`Style::with_template`](crate::Style::with_template).
+
+/// A value which can be inserted into a template, like `{pos}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Field {
+    Prefix,
+    Message,
+    /// The bar graphic, with an optional width which overrides the width of the style
+    Bar(Option<usize>),
+    Position,
+    Length,
+    Percent,
+    Elapsed,
+    Eta,
+    Rate,
+}
+
+/// A part of a parsed template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Text(String),
+    /// A field, padded to a minimum width. Text is left-aligned unless `right_align` is set.
+    Field {
+        field: Field,
+        width: usize,
+        right_align: bool,
+    },
+}
+
+/// A line layout like `{prefix} {bar:30} {pos}/{len} {eta} {msg}`, split into its parts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Template {
+    pub(crate) segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parses a template, or returns a description of the first problem in it.
+    pub(crate) fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // Braces are escaped by doubling them, like in format strings
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(format!("unclosed placeholder `{{{}`", placeholder))
+                            }
+                        }
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err("unmatched `}`, use `}}` to insert a brace".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+}
+
+/// Parses the contents of a placeholder, like `bar:30` or `msg:>12`.
+fn parse_placeholder(placeholder: &str) -> Result<Segment, String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+    let (right_align, width) = match spec {
+        Some(spec) => {
+            let (right_align, digits) = match spec.strip_prefix('>') {
+                Some(digits) => (true, digits),
+                None => (false, spec.strip_prefix('<').unwrap_or(spec)),
+            };
+            let width = digits
+                .parse::<usize>()
+                .map_err(|_| format!("invalid width `{}` in `{{{}}}`", spec, placeholder))?;
+            (right_align, Some(width))
+        }
+        None => (false, None),
+    };
+    let field = match name {
+        "prefix" => Field::Prefix,
+        "msg" => Field::Message,
+        // The width of the bar is the number of cells, so it is not used for padding
+        "bar" => {
+            if right_align {
+                return Err(format!(
+                    "the bar can not be aligned in `{{{}}}`",
+                    placeholder
+                ));
+            }
+            return Ok(Segment::Field {
+                field: Field::Bar(width),
+                width: 0,
+                right_align: false,
+            });
+        }
+        "pos" => Field::Position,
+        "len" => Field::Length,
+        "percent" => Field::Percent,
+        "elapsed" => Field::Elapsed,
+        "eta" => Field::Eta,
+        "rate" => Field::Rate,
+        _ => return Err(format!("unknown placeholder `{{{}}}`", placeholder)),
+    };
+    Ok(Segment::Field {
+        field,
+        width: width.unwrap_or(0),
+        right_align,
+    })
+}
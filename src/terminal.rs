@@ -0,0 +1,98 @@
+use crate::MANAGER;
+
+/// Overrides the detected size of the terminal.
+///
+/// Layouts which adapt to the size of the terminal will use this size instead, regardless of the actual environment.
+/// This is useful in tests and when generating screenshots, to get deterministic output.
+///
+/// ```
+/// headway::set_terminal_size_override(80, 24);
+/// assert_eq!(headway::terminal_size(), Some((80, 24)));
+/// headway::clear_terminal_size_override();
+/// ```
+pub fn set_terminal_size_override(cols: usize, rows: usize) {
+    MANAGER.lock().terminal_size_override = Some((cols, rows));
+}
+
+/// Removes an override set using [`set_terminal_size_override`].
+pub fn clear_terminal_size_override() {
+    MANAGER.lock().terminal_size_override = None;
+}
+
+/// The size of the terminal as `(columns, rows)`.
+///
+/// Returns the size set using [`set_terminal_size_override`] if there is one.
+/// Otherwise returns the size of the terminal the bars are drawn to (see [`Config::with_target`](crate::Config::with_target)),
+/// or None if it is not a terminal.
+pub fn terminal_size() -> Option<(usize, usize)> {
+    MANAGER
+        .lock()
+        .terminal_size_override
+        .or_else(detect_terminal_size)
+}
+
+/// Draws live bars even if the output is not detected as a terminal.
+///
+/// This is useful when running under tools like `script` or in CI systems which display ANSI escape codes,
+/// but where the output is not a tty. Pass false to go back to drawing live bars only if the output is a terminal.
+///
+/// Interactive rendering is forced by default if the `HEADWAY_INTERACTIVE` environment variable is set to something other than `0`.
+///
+/// ```
+/// headway::force_interactive(true);
+/// ```
+pub fn force_interactive(enabled: bool) {
+    let mut manager = MANAGER.lock();
+    manager.interactive_output = enabled || crate::config::config().is_terminal();
+    if manager.needs_thread() && !manager.bars.is_empty() {
+        manager.ensure_thread_started();
+    }
+}
+
+/// True if interactive rendering is forced by the `HEADWAY_INTERACTIVE` environment variable, see [`force_interactive`].
+pub(crate) fn interactive_from_env() -> bool {
+    std::env::var_os("HEADWAY_INTERACTIVE").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+pub(crate) fn detect_terminal_size() -> Option<(usize, usize)> {
+    crate::config::config().terminal_size()
+}
+
+/// True if the terminal is known to support synchronized output, see [`crate::set_synchronized_output`].
+///
+/// There is no reliable way to ask the terminal without reading its reply from stdin, so this checks for terminals
+/// which are known to support it, using the environment variables they set.
+pub(crate) fn synchronized_output_from_env() -> bool {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let term_program = var("TERM_PROGRAM");
+    let term = var("TERM");
+    matches!(
+        term_program.as_str(),
+        "WezTerm" | "iTerm.app" | "ghostty" | "vscode" | "WarpTerminal" | "contour" | "rio"
+    ) || [
+        "kitty",
+        "foot",
+        "alacritty",
+        "ghostty",
+        "wezterm",
+        "contour",
+    ]
+    .iter()
+    .any(|name| term.contains(name))
+        || std::env::var_os("WT_SESSION").is_some()
+}
+
+/// Whether colors are forced on or off by the environment, following the `NO_COLOR` and `CLICOLOR` conventions.
+///
+/// `CLICOLOR_FORCE` takes precedence, so that colors can be forced on even if `NO_COLOR` is set globally.
+/// Returns None if colors should be used only when the output is a terminal.
+pub(crate) fn color_from_env() -> Option<bool> {
+    let enabled = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    if enabled("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+        Some(true)
+    } else if enabled("NO_COLOR").is_some() || enabled("CLICOLOR").is_some_and(|v| v == "0") {
+        Some(false)
+    } else {
+        None
+    }
+}
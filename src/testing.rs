@@ -0,0 +1,180 @@
+//! Helpers for testing code which displays progress bars.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::DrawTarget;
+
+/// A [`DrawTarget`] which records everything the bars would have drawn, so that it can be inspected in tests.
+///
+/// The target behaves like a terminal, so every frame of the bars is drawn to it. Frames are recorded without escape codes,
+/// one line per bar, and text which is printed permanently above the bars (like the final state of finished bars) is recorded separately.
+///
+/// Unlike [`crate::test_capture`], the output goes through the same path as output to a real terminal.
+/// The target is set using [`Config::with_draw_target`](crate::Config::with_draw_target), which must happen before the first bar is created.
+/// Handles to the target are cheap to clone, and all clones share the same recording.
+///
+/// ```
+/// use headway::testing::MemoryTarget;
+/// use headway::{Config, ProgressBar};
+///
+/// let target = MemoryTarget::new().with_size(60, 20);
+/// headway::init_with(Config::new().with_draw_target(target.clone())).unwrap();
+///
+/// let p = ProgressBar::new().with_message("Compressing");
+/// for _ in p.wrap(0..10) {}
+/// assert_eq!(target.printed(), "▕████████████████████▏ 10/10 Compressing\n");
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryTarget {
+    recording: Arc<Mutex<Recording>>,
+    size: Option<(usize, usize)>,
+    /// True if the target pretends to be a pipe rather than a terminal, see [`MemoryTarget::with_terminal`]
+    piped: bool,
+}
+
+#[derive(Default)]
+struct Recording {
+    /// Bytes written since the last flush
+    pending: Vec<u8>,
+    /// Everything written so far, including escape codes
+    raw: String,
+    /// Text printed above the bars
+    printed: String,
+    frames: Vec<String>,
+}
+
+impl MemoryTarget {
+    /// Creates an empty target, without a known size.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pretends to be a terminal of the given size, in columns and rows.
+    ///
+    /// Lines are shortened to the width, like in a real terminal, which keeps the recorded frames the same on every machine.
+    pub fn with_size(mut self, cols: usize, rows: usize) -> Self {
+        self.size = Some((cols, rows));
+        self
+    }
+
+    /// Pretends to be a pipe instead of a terminal if false, like when the output of the program is redirected to a file.
+    ///
+    /// Bars are then only written once they are finished, so everything ends up in [`Self::printed`].
+    pub fn with_terminal(mut self, terminal: bool) -> Self {
+        self.piped = !terminal;
+        self
+    }
+
+    /// All frames drawn so far, oldest first.
+    ///
+    /// Each frame contains one line per visible bar, preceded by the title if one has been set (see [`crate::set_title`]).
+    pub fn frames(&self) -> Vec<String> {
+        self.recording.lock().frames.clone()
+    }
+
+    /// The most recently drawn frame, if any.
+    pub fn last_frame(&self) -> Option<String> {
+        self.recording.lock().frames.last().cloned()
+    }
+
+    /// The text printed permanently above the bars so far, like the final state of bars which have been finished or abandoned.
+    pub fn printed(&self) -> String {
+        self.recording.lock().printed.clone()
+    }
+
+    /// Everything written to the target so far, including escape codes.
+    pub fn raw(&self) -> String {
+        self.recording.lock().raw.clone()
+    }
+
+    /// Forgets everything recorded so far.
+    pub fn clear(&self) {
+        let mut recording = self.recording.lock();
+        recording.raw.clear();
+        recording.printed.clear();
+        recording.frames.clear();
+    }
+}
+
+impl Recording {
+    /// Splits a chunk of output into the text printed above the bars, and the frame.
+    ///
+    /// The frame is always followed by moving the cursor back up to its first line, which tells how many lines it has.
+    fn record(&mut self, chunk: &str) {
+        self.raw.push_str(chunk);
+        let mut text = String::new();
+        let mut frame_start = None;
+        let mut chars = chunk.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\u{1b}' {
+                text.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('[') => {
+                    let mut sequence = String::new();
+                    for c in chars.by_ref() {
+                        sequence.push(c);
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                    if let Some(lines) = sequence.strip_suffix('F') {
+                        let lines = lines.parse::<usize>().unwrap_or(1);
+                        // The start of the line `lines` lines up
+                        let start = text
+                            .rmatch_indices('\n')
+                            .nth(lines)
+                            .map_or(0, |(i, _)| i + 1);
+                        frame_start = Some(start);
+                    }
+                }
+                // Operating system commands, like setting the window title, end with a bell or `ESC \`
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' || (c == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        match frame_start {
+            Some(start) => {
+                self.printed.push_str(&text[..start]);
+                self.frames.push(text[start..].to_string());
+            }
+            None => self.printed.push_str(&text),
+        }
+    }
+}
+
+impl Write for MemoryTarget {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.recording.lock().pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Output is always flushed after each frame, so this is where frames are recorded.
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut recording = self.recording.lock();
+        let pending = std::mem::take(&mut recording.pending);
+        if !pending.is_empty() {
+            recording.record(&String::from_utf8_lossy(&pending));
+        }
+        Ok(())
+    }
+}
+
+impl DrawTarget for MemoryTarget {
+    fn is_terminal(&self) -> bool {
+        !self.piped
+    }
+
+    fn size(&self) -> Option<(usize, usize)> {
+        self.size
+    }
+}
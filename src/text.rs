@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const ELLIPSIS: char = '…';
+
+/// Shortens text so that it takes up at most `width` columns in a terminal, ending it with `…` if anything was removed.
+///
+/// Text is only cut between grapheme clusters, so accents, flags and emoji sequences are never split up.
+/// Wide characters, like most CJK characters, take up two columns. ANSI escape codes take up no space and are kept,
+/// and if the text is shortened, a reset code is added after the ellipsis so that colors do not leak into the rest of the line.
+///
+/// This is useful for fields added using [`ProgressBar::add_extra_field`](crate::ProgressBar::add_extra_field),
+/// which should not push the line past the edge of the terminal.
+///
+/// ```
+/// use headway::truncate_to_width;
+///
+/// assert_eq!(truncate_to_width("cache hit rate 93%", 10), "cache hit…");
+/// assert_eq!(truncate_to_width("short", 10), "short");
+/// assert_eq!(truncate_to_width("日本語のテキスト", 7), "日本語…");
+/// assert_eq!(truncate_to_width("👨‍👩‍👧 family", 4), "👨‍👩‍👧 …");
+/// assert_eq!(truncate_to_width("🇸🇪🇳🇴 flags", 4), "🇸🇪…");
+/// ```
+pub fn truncate_to_width(text: &str, width: usize) -> Cow<'_, str> {
+    if display_width(text) <= width {
+        return Cow::Borrowed(text);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    let mut has_escapes = false;
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        if rest.starts_with('\u{1b}') {
+            has_escapes = true;
+            let mut chars = rest.chars().peekable();
+            let start = out.len();
+            out.extend(chars.next());
+            copy_escape(&mut chars, &mut out);
+            rest = &rest[out.len() - start..];
+            continue;
+        }
+        let plain_end = rest.find('\u{1b}').unwrap_or(rest.len());
+        for cluster in rest[..plain_end].graphemes(true) {
+            let cluster_width = cluster.width();
+            // Leave space for the ellipsis
+            if used + cluster_width + 1 > width {
+                break 'outer;
+            }
+            used += cluster_width;
+            out.push_str(cluster);
+        }
+        rest = &rest[plain_end..];
+    }
+    out.push(ELLIPSIS);
+    if has_escapes {
+        out.push_str("\u{1b}[0m");
+    }
+    Cow::Owned(out)
+}
+
+/// The number of terminal columns the text takes up, ignoring ANSI escape codes.
+///
+/// Wide characters, like most CJK characters, take up two columns. Each grapheme cluster is measured separately, so that
+/// emoji sequences and flags are measured as a single emoji, like terminals display them, and like [`truncate_to_width`] cuts them.
+pub(crate) fn display_width(text: &str) -> usize {
+    if !text.chars().any(char::is_control) {
+        return cluster_width(text);
+    }
+    // Escape codes and other control characters take up no space
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            copy_escape(&mut chars, &mut String::new());
+        } else if !c.is_control() {
+            plain.push(c);
+        }
+    }
+    cluster_width(&plain)
+}
+
+/// The width of text without escape codes, as the sum of the widths of its grapheme clusters.
+fn cluster_width(text: &str) -> usize {
+    text.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// Makes text fit on a single row of the terminal.
+///
+/// Line breaks and other control characters are replaced with spaces, while ANSI escape codes are kept.
+/// If the width of the terminal is known, the text is then shortened using [`truncate_to_width`].
+pub(crate) fn fit_to_row(text: &str, width: Option<usize>) -> Cow<'_, str> {
+    let is_break = |c: char| c.is_control() && c != '\u{1b}';
+    let text = if text.chars().any(is_break) {
+        Cow::Owned(
+            text.chars()
+                .map(|c| if is_break(c) { ' ' } else { c })
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(text)
+    };
+    match width {
+        Some(width) if display_width(&text) > width => {
+            Cow::Owned(truncate_to_width(&text, width).into_owned())
+        }
+        _ => text,
+    }
+}
+
+/// Removes ANSI escape codes and replaces other control characters, like tabs, with spaces.
+pub(crate) fn strip_control(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(char::is_control) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            copy_escape(&mut chars, &mut String::new());
+        } else if c.is_control() {
+            out.push(' ');
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Copies the rest of an escape code, after the escape character itself.
+fn copy_escape(chars: &mut std::iter::Peekable<std::str::Chars>, out: &mut String) {
+    match chars.peek() {
+        // Control sequences, like colors, end with a character in the range @ to ~
+        Some('[') => {
+            out.extend(chars.next());
+            for c in chars.by_ref() {
+                out.push(c);
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+        Some(_) => out.extend(chars.next()),
+        None => {}
+    }
+}
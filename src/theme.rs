@@ -0,0 +1,69 @@
+use crate::Color;
+
+/// The colors used to draw progress bars.
+///
+/// A theme can be set for individual bars or for all bars at once, as part of a [`Style`](crate::Style) using
+/// [`Style::with_theme`](crate::Style::with_theme). Colors are only used when the output is a terminal.
+///
+/// ```
+/// use headway::{Color, ProgressBar, Style, Theme};
+///
+/// let theme = Theme::new()
+///     .with_filled(Some(Color::Cyan))
+///     .with_abandoned(Color::Magenta)
+///     .with_indeterminate((17..22).map(Color::Fixed));
+/// headway::set_default_style(Style::new().with_theme(theme));
+///
+/// let p = ProgressBar::new().with_length(100);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub(crate) filled: Option<Color>,
+    pub(crate) abandoned: Color,
+    pub(crate) indeterminate: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Theme {
+    /// The default theme.
+    ///
+    /// The filled part of bars uses the default color of the terminal, abandoned parts are red,
+    /// and parts which are in progress are animated using shades of grey.
+    pub fn new() -> Self {
+        Self {
+            filled: None,
+            abandoned: Color::Red,
+            // The greyscale ramp of the extended ANSI palette
+            indeterminate: (232..=255).map(Color::Fixed).collect(),
+        }
+    }
+
+    /// Sets the color of the filled part of bars, or None to use the default color of the terminal.
+    ///
+    /// Bars which have a color of their own (see [`ProgressBar::set_color`](crate::ProgressBar::set_color)) use that instead,
+    /// and finished bars use the color set using [`Style::with_completed_color`](crate::Style::with_completed_color).
+    pub fn with_filled(mut self, color: Option<Color>) -> Self {
+        self.filled = color;
+        self
+    }
+
+    /// Sets the color of the part of bars which has been abandoned, which is drawn using `X`.
+    pub fn with_abandoned(mut self, color: Color) -> Self {
+        self.abandoned = color;
+        self
+    }
+
+    /// Sets the colors which parts of bars that are in progress, but without known progress, are animated with.
+    ///
+    /// A wave moves through the colors in order and back, so the colors should typically go from dark to bright.
+    /// If no colors are given, those parts are drawn without any animation.
+    pub fn with_indeterminate(mut self, palette: impl IntoIterator<Item = Color>) -> Self {
+        self.indeterminate = palette.into_iter().collect();
+        self
+    }
+}
@@ -0,0 +1,160 @@
+//! Integration with [tokio](https://docs.rs/tokio), enabled by the `tokio` feature.
+
+use std::sync::Arc;
+
+use ::tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::{add_poller, redraw, LifecycleState, ProgressBar, ProgressBarState};
+
+impl ProgressBar {
+    /// Returns a channel which receives the progress of the bar whenever it changes.
+    ///
+    /// The value is the fraction of the bar which is complete, between 0 and 1, including the progress of any nested bars.
+    /// For bars without a known length the value is 0.
+    /// The channel receives the final progress when the bar is finished or abandoned, and is closed shortly after that.
+    ///
+    /// This is useful for binding a progress widget in a GUI (like egui, iced or Tauri) to a bar which is driven by backend code.
+    /// The channel is updated by the background thread (roughly every 20 ms), until the bar is finished or all receivers have been dropped.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    ///
+    /// let mut p = ProgressBar::new().with_length(10);
+    /// let progress = p.watch();
+    /// // In a GUI, for example using egui:
+    /// // ui.add(egui::ProgressBar::new(*progress.borrow() as f32));
+    /// for _ in 0..10 {
+    ///     p.inc();
+    /// }
+    /// p.finish();
+    /// assert_eq!(*progress.borrow(), 1.0);
+    /// ```
+    pub fn watch(&self) -> watch::Receiver<f64> {
+        let Some(state) = &self.state else {
+            // The bar has already been finished, so the channel is closed right away
+            return watch::channel(1.0).1;
+        };
+        let (sender, receiver) = {
+            let mut state = state.lock();
+            let (sender, receiver) = watch::channel(state.progress().unwrap_or(0.0));
+            let sender = Arc::new(sender);
+            state.watchers.push(sender.clone());
+            (sender, receiver)
+        };
+
+        let state = Arc::downgrade(state);
+        add_poller(move || {
+            let Some(state) = state.upgrade() else {
+                return false;
+            };
+            if sender.is_closed() {
+                return false;
+            }
+            let state = state.lock();
+            let value = state.progress().unwrap_or(0.0);
+            sender.send_if_modified(|v| {
+                let modified = *v != value;
+                *v = value;
+                modified
+            });
+            // The final value has been sent above, so the channel can be closed
+            state.lifecycle == LifecycleState::InProgress
+        });
+        receiver
+    }
+}
+
+impl ProgressBar {
+    /// Links the bar to a [`CancellationToken`] from [tokio-util](https://docs.rs/tokio-util), so that cancelling either one cancels the other.
+    ///
+    /// When the token is cancelled, the bar and all of its children are abandoned, and displayed like `(abandoned: cancelled)`.
+    /// When the bar is cancelled using [`Self::cancel`], the token is cancelled. This way async applications
+    /// which already use tokens to stop tasks don't need a second mechanism for their bars.
+    /// Finishing or abandoning the bar in other ways does not cancel the token.
+    ///
+    /// The token is checked by the background thread (roughly every 20 ms), so the bar is abandoned shortly after the token is cancelled.
+    ///
+    /// ```
+    /// use headway::ProgressBar;
+    /// use tokio_util::sync::CancellationToken;
+    ///
+    /// let token = CancellationToken::new();
+    /// let p = ProgressBar::new()
+    ///     .with_length(100)
+    ///     .with_cancellation_token(token.clone());
+    /// p.inc();
+    /// p.cancel();
+    /// assert!(token.is_cancelled());
+    /// ```
+    pub fn link_cancellation_token(&self, token: CancellationToken) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        state.lock().cancellation_tokens.push(token.clone());
+
+        let state = Arc::downgrade(state);
+        add_poller(move || {
+            let Some(state) = state.upgrade() else {
+                return false;
+            };
+            let mut state = state.lock();
+            if state.lifecycle != LifecycleState::InProgress {
+                return false;
+            }
+            if token.is_cancelled() {
+                state.cancel();
+                return false;
+            }
+            true
+        });
+    }
+
+    /// Equivalent to [`Self::link_cancellation_token`], but may be more ergonomic in some situations since it returns `self`.
+    pub fn with_cancellation_token(self, token: CancellationToken) -> Self {
+        self.link_cancellation_token(token);
+        self
+    }
+
+    /// Cancels the bar, and all tokens linked to it using [`Self::link_cancellation_token`].
+    ///
+    /// The bar and all of its children are abandoned, and displayed like `(abandoned: cancelled)`.
+    /// Unlike [`Self::abandon`], this does not consume the bar, so it can be called from any task which has a reference to it.
+    pub fn cancel(&self) {
+        if let Some(state) = &self.state {
+            let tokens = {
+                let mut state = state.lock();
+                state.cancel();
+                std::mem::take(&mut state.cancellation_tokens)
+            };
+            // Cancelling wakes up tasks waiting for the token, so avoid holding the lock while doing it
+            for token in tokens {
+                token.cancel();
+            }
+        }
+        redraw();
+    }
+}
+
+impl ProgressBarState {
+    /// Abandons the bar and its children because it was cancelled. See [`ProgressBar::cancel`].
+    fn cancel(&mut self) {
+        if self.lifecycle == LifecycleState::InProgress {
+            self.abandon_reason
+                .get_or_insert_with(|| "cancelled".to_string());
+        }
+        self.end_recursive(LifecycleState::Abandoned);
+    }
+
+    /// Sends the final progress to all channels created using [`ProgressBar::watch`], and closes them.
+    pub(crate) fn notify_watchers(&mut self) {
+        let value = self.progress().unwrap_or(0.0);
+        for sender in self.watchers.drain(..) {
+            sender.send_if_modified(|v| {
+                let modified = *v != value;
+                *v = value;
+                modified
+            });
+        }
+    }
+}
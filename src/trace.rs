@@ -0,0 +1,301 @@
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::frame::BarTiming;
+use crate::sync::Mutex;
+use crate::{LifecycleState, ProgressBarState, MANAGER};
+
+/// Maximum number of removed top-level bars remembered for [`export_trace`]
+const MAX_HISTORY: usize = 10_000;
+
+/// The lifetime of a bar and its children, kept after the bar has been removed from the screen so that it can be included in [`export_trace`].
+#[derive(Clone)]
+pub(crate) struct TraceRecord {
+    name: String,
+    /// See [`crate::scope_label`]
+    label: Option<Arc<str>>,
+    timing: BarTiming,
+    lifecycle: LifecycleState,
+    children: Vec<TraceRecord>,
+}
+
+impl TraceRecord {
+    pub fn new(state: &ProgressBarState) -> Self {
+        let name = match (&state.prefix, &state.message) {
+            (Some(prefix), Some(message)) => format!("{} {}", prefix, message),
+            (Some(text), None) | (None, Some(text)) => text.clone(),
+            (None, None) => format!("bar {}", state.id),
+        };
+        let children = state
+            .nested
+            .as_ref()
+            .map(|nested| {
+                nested
+                    .bars
+                    .iter()
+                    .map(|bar| TraceRecord::new(&bar.lock()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            name,
+            label: state.label.clone(),
+            timing: BarTiming::new(state),
+            lifecycle: state.lifecycle,
+            children,
+        }
+    }
+
+    /// Start and end of the bar in microseconds. Bars which are still in progress end at `now`.
+    fn span(&self, now: f64) -> (f64, f64) {
+        let start = self.timing.started.unwrap_or(self.timing.created);
+        let end = self.timing.finished.unwrap_or(now).max(start);
+        (start * 1e6, end * 1e6)
+    }
+}
+
+/// Remembers top-level bars which are no longer displayed, so that they are included in [`export_trace`].
+///
+/// Does nothing unless recording has been enabled using [`set_trace_recording`].
+pub(crate) fn remember(
+    history: &mut Option<Vec<TraceRecord>>,
+    removed: impl Iterator<Item = Arc<Mutex<ProgressBarState>>>,
+) {
+    let Some(history) = history else {
+        return;
+    };
+    history.extend(removed.map(|bar| TraceRecord::new(&bar.lock())));
+    if history.len() > MAX_HISTORY {
+        let excess = history.len() - MAX_HISTORY;
+        history.drain(0..excess);
+    }
+}
+
+/// Keeps bars which are no longer displayed, so that they can be included in [`export_trace`].
+///
+/// Recording is disabled by default, since it costs memory and time every time a bar is removed,
+/// so without it only the bars which are currently displayed are exported.
+/// Only bars which are removed after recording has been enabled are kept, and disabling it forgets them.
+///
+/// ```no_run
+/// use headway::ProgressBarIterable;
+///
+/// headway::set_trace_recording(true);
+/// for _ in (0..100).progress() {}
+/// headway::export_trace("trace.json").unwrap();
+/// ```
+pub fn set_trace_recording(enabled: bool) {
+    let mut manager = MANAGER.lock();
+    match (enabled, &manager.trace_history) {
+        (true, None) => manager.trace_history = Some(vec![]),
+        (false, Some(_)) => manager.trace_history = None,
+        _ => {}
+    }
+}
+
+/// Writes the lifetimes of all bars created so far to a file in the [Trace Event Format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nNsnb4OKZfI).
+///
+/// The file can be opened in `chrome://tracing` or [Perfetto](https://ui.perfetto.dev), which turns the progress bars of a long pipeline into a profile of it.
+/// Each top-level bar gets its own track. Nested bars are drawn below their parent, and children which ran at the same time get tracks of their own.
+/// Bars created within a [`scope_label`](crate::scope_label) get the label as their category.
+/// The time spent in each label is added up and included as the metadata of the trace (`otherData`), in seconds.
+/// Bars with the same label which ran at the same time are only counted once.
+/// A bar is considered to start when it first made progress, and to end when it was finished or abandoned.
+/// Bars which are still in progress end at the time of the export.
+///
+/// Bars which are no longer displayed are only included if they were recorded, see [`set_trace_recording`].
+/// Only the most recent bars are remembered, to limit memory usage in programs which create a lot of them.
+///
+/// ```no_run
+/// use headway::ProgressBarIterable;
+///
+/// headway::set_trace_recording(true);
+/// for _ in (0..100).progress() {}
+/// headway::export_trace("trace.json").unwrap();
+/// ```
+///
+/// The totals of each label can be read from the end of the file:
+///
+/// ```
+/// use headway::{ManualClock, ProgressBar};
+/// use std::time::Duration;
+///
+/// headway::set_trace_recording(true);
+/// let clock = ManualClock::new();
+/// headway::set_clock(Some(clock.clone()));
+/// for _ in 0..2 {
+///     headway::scope_label("indexing", || {
+///         let mut p = ProgressBar::new().with_length(10);
+///         p.inc();
+///         clock.advance(Duration::from_secs(3));
+///         p.finish();
+///     });
+/// }
+///
+/// let path = std::env::temp_dir().join("headway-label-totals.json");
+/// headway::export_trace(&path).unwrap();
+/// let trace = std::fs::read_to_string(&path).unwrap();
+/// assert!(trace.ends_with("\"otherData\":{\"indexing\":6.000}}\n"));
+/// ```
+pub fn export_trace(path: impl AsRef<Path>) -> io::Result<()> {
+    let records = {
+        let manager = MANAGER.lock();
+        let mut records = manager.trace_history.clone().unwrap_or_default();
+        records.extend(manager.bars.iter().map(|bar| TraceRecord::new(&bar.lock())));
+        records
+    };
+    let now = crate::clock::since(*crate::TIMELINE_ORIGIN).as_secs_f64();
+
+    let mut events = vec![];
+    let mut next_track = 0;
+    for record in &records {
+        next_track += 1;
+        write_track_name(&mut events, next_track, &record.name);
+        let (start, end) = record.span(now);
+        write_events(
+            &mut events,
+            record,
+            next_track,
+            &mut next_track,
+            start,
+            end,
+            now,
+        );
+    }
+    let totals = label_totals(&records, now)
+        .iter()
+        .map(|(label, seconds)| format!("{}:{:.3}", json_string(label), seconds))
+        .collect::<Vec<_>>();
+    std::fs::write(
+        path,
+        format!(
+            "{{\"traceEvents\":[\n{}\n],\n\"otherData\":{{{}}}}}\n",
+            events.join(",\n"),
+            totals.join(",")
+        ),
+    )
+}
+
+/// The start and end of each bar with a label, grouped by label. See [`label_totals`].
+type LabelSpans = Vec<(Arc<str>, Vec<(f64, f64)>)>;
+
+/// The total time in seconds during which at least one bar with each label was running, in the order the labels first appear.
+fn label_totals(records: &[TraceRecord], now: f64) -> Vec<(Arc<str>, f64)> {
+    fn collect(
+        record: &TraceRecord,
+        parent_label: Option<&Arc<str>>,
+        now: f64,
+        spans: &mut LabelSpans,
+    ) {
+        let label = record.label.as_ref().or(parent_label);
+        // Children with the same label as their parent run within its span, so only the outermost bar is counted
+        if let Some(label) = label.filter(|&label| Some(label) != parent_label) {
+            let span = record.span(now);
+            match spans.iter_mut().find(|(l, _)| l == label) {
+                Some((_, label_spans)) => label_spans.push(span),
+                None => spans.push((label.clone(), vec![span])),
+            }
+        }
+        for child in &record.children {
+            collect(child, label, now, spans);
+        }
+    }
+
+    let mut spans = vec![];
+    for record in records {
+        collect(record, None, now, &mut spans);
+    }
+    spans
+        .into_iter()
+        .map(|(label, mut spans)| {
+            spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut total = 0.0;
+            let mut covered_until = f64::NEG_INFINITY;
+            for (start, end) in spans {
+                let start = start.max(covered_until);
+                if end > start {
+                    total += end - start;
+                    covered_until = end;
+                }
+            }
+            (label, total / 1e6)
+        })
+        .collect()
+}
+
+/// Writes a complete event for the bar, clamped to the span of its parent, followed by the events of its children.
+fn write_events(
+    events: &mut Vec<String>,
+    record: &TraceRecord,
+    track: u64,
+    next_track: &mut u64,
+    parent_start: f64,
+    parent_end: f64,
+    now: f64,
+) {
+    let (start, end) = record.span(now);
+    let start = start.clamp(parent_start, parent_end);
+    let end = end.clamp(start, parent_end);
+    let state = match record.lifecycle {
+        LifecycleState::InProgress => "in progress",
+        LifecycleState::Completed => "completed",
+        LifecycleState::Abandoned => "abandoned",
+    };
+    events.push(format!(
+        "{{\"name\":{},\"cat\":{},\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{:.0},\"dur\":{:.0},\"args\":{{\"state\":\"{}\",\"stalled_seconds\":{}}}}}",
+        json_string(&record.name),
+        json_string(record.label.as_deref().unwrap_or("bar")),
+        track,
+        start,
+        end - start,
+        state,
+        record.timing.stalled,
+    ));
+
+    // Children which run one after another are nested below the parent on the same track
+    let mut spans = record
+        .children
+        .iter()
+        .map(|child| child.span(now))
+        .collect::<Vec<_>>();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let sequential = spans.windows(2).all(|w| w[1].0 >= w[0].1);
+    for child in &record.children {
+        let child_track = if sequential {
+            track
+        } else {
+            *next_track += 1;
+            write_track_name(events, *next_track, &child.name);
+            *next_track
+        };
+        write_events(events, child, child_track, next_track, start, end, now);
+    }
+}
+
+fn write_track_name(events: &mut Vec<String>, track: u64, name: &str) {
+    events.push(format!(
+        "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":{}}}}}",
+        track,
+        json_string(name)
+    ));
+}
+
+/// Quotes and escapes a string for use in JSON.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            // SAFETY: Writes to strings cannot fail
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{add_poller, LifecycleState, ProgressBar};
+
+/// How often the size of a watched file is checked
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+impl ProgressBar {
+    /// Drives the bar using the size of a file which is being written to.
+    ///
+    /// The length of the bar is set to `expected_bytes`, and the position is continuously updated to the current size of the file.
+    /// If the path is a directory, the total size of all files in it (recursively) is used instead.
+    /// A file which does not exist yet is treated as empty.
+    ///
+    /// This is useful when wrapping external programs, like encoders or compressors, whose progress is only visible through their output.
+    /// The file is watched until the bar is finished or abandoned.
+    ///
+    /// ```no_run
+    /// use headway::ProgressBar;
+    /// use std::process::Command;
+    ///
+    /// let mut p = ProgressBar::new().with_message("Compressing");
+    /// p.watch_file("archive.tar.gz", 120_000_000);
+    /// Command::new("tar").args(["czf", "archive.tar.gz", "data"]).status().unwrap();
+    /// p.finish();
+    /// ```
+    pub fn watch_file(&self, path: impl Into<PathBuf>, expected_bytes: u64) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        self.set_length(expected_bytes as usize);
+
+        let path = path.into();
+        let state = Arc::downgrade(state);
+        let mut last_check: Option<Instant> = None;
+        add_poller(move || {
+            let Some(state) = state.upgrade() else {
+                return false;
+            };
+            if last_check.is_some_and(|t| t.elapsed() < WATCH_INTERVAL) {
+                return true;
+            }
+            last_check = Some(Instant::now());
+
+            // Avoid holding the lock while touching the file system
+            let size = path_size(&path).unwrap_or(0) as usize;
+            let mut state = state.lock();
+            if state.lifecycle != LifecycleState::InProgress {
+                return false;
+            }
+            if state.position != size {
+                state.position = size;
+                state.record_progress();
+            }
+            true
+        });
+    }
+}
+
+/// Size of a file, or the total size of all files in a directory.
+fn path_size(path: &Path) -> std::io::Result<u64> {
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut total = 0;
+    // Files may be removed while we are iterating, so ignore errors for individual entries
+    for entry in std::fs::read_dir(path)?.flatten() {
+        // Symlinks inside the directory are not followed, to avoid loops
+        total += match entry.file_type() {
+            Ok(t) if t.is_dir() => path_size(&entry.path()).unwrap_or(0),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        };
+    }
+    Ok(total)
+}
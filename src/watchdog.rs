@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::sync::Mutex;
+use crate::{LifecycleState, ProgressBarState};
+
+type StallCallback = dyn FnMut(&Stall) + Send;
+
+/// A callback registered using [`ProgressBar::on_stall`](crate::ProgressBar::on_stall).
+#[derive(Clone)]
+pub(crate) struct StallWatcher {
+    pub timeout: Duration,
+    pub callback: Arc<Mutex<Box<StallCallback>>>,
+    /// The activity timestamp of the bar when the callback was last invoked.
+    /// Used to ensure the callback is only invoked once per stall.
+    pub fired_for_activity: Option<Instant>,
+}
+
+/// Information about a stalled bar, passed to callbacks registered using [`ProgressBar::on_stall`](crate::ProgressBar::on_stall).
+pub struct Stall {
+    duration: Duration,
+    message: Option<String>,
+    state: Arc<Mutex<ProgressBarState>>,
+}
+
+impl Stall {
+    /// How long the bar has gone without being updated.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The message displayed next to the bar, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Abandons the stalled bar.
+    ///
+    /// The bar will be displayed as abandoned even if it is later finished by the code that owns it.
+    pub fn abandon(&self) {
+        let mut state = self.state.lock();
+        state.lifecycle = LifecycleState::Abandoned;
+        state.finished_at.get_or_insert_with(clock::now);
+        state.abandoned_externally = true;
+    }
+}
+
+/// A stall callback which should be invoked, once all locks have been released.
+pub(crate) struct PendingStall {
+    callback: Arc<Mutex<Box<StallCallback>>>,
+    stall: Stall,
+}
+
+impl PendingStall {
+    pub fn invoke(self) {
+        (self.callback.lock())(&self.stall);
+    }
+}
+
+/// Finds all callbacks for stalled bars in the given bar or any of its children.
+pub(crate) fn collect_stalls(bar: &Arc<Mutex<ProgressBarState>>, pending: &mut Vec<PendingStall>) {
+    let mut state = bar.lock();
+    if let Some(nested) = &state.nested {
+        for child in &nested.bars {
+            collect_stalls(child, pending);
+        }
+    }
+
+    if state.lifecycle != LifecycleState::InProgress || state.stall_watchers.is_empty() {
+        return;
+    }
+
+    let last_activity = state.last_activity();
+    let elapsed = clock::since(last_activity);
+    let message = state.message();
+    for watcher in &mut state.stall_watchers {
+        if elapsed >= watcher.timeout && watcher.fired_for_activity != Some(last_activity) {
+            watcher.fired_for_activity = Some(last_activity);
+            pending.push(PendingStall {
+                callback: watcher.callback.clone(),
+                stall: Stall {
+                    duration: elapsed,
+                    message: message.clone(),
+                    state: bar.clone(),
+                },
+            });
+        }
+    }
+}